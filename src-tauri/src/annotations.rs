@@ -0,0 +1,171 @@
+// Notes, étiquettes et catégorie des transactions
+//
+// Ces informations appartiennent à l'utilisateur de cet appareil : elles ne font pas
+// partie des octets signés et sont retirées avant tout envoi à un pair. Les
+// catégories et étiquettes sont normalisées (minuscules, espaces rognés) pour que
+// les filtres et les totaux par catégorie ne dépendent pas de la saisie.
+
+use serde::{Deserialize, Serialize};
+
+/// Categories offered by default; any other name is accepted
+pub const DEFAULT_CATEGORIES: &[&str] = &[
+    "food",
+    "transport",
+    "airtime",
+    "utilities",
+    "health",
+    "education",
+    "rent",
+    "family",
+    "business",
+    "other",
+];
+
+pub const MAX_NOTE_CHARS: usize = 500;
+pub const MAX_TAGS: usize = 10;
+pub const MAX_LABEL_CHARS: usize = 32;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxAnnotations {
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+impl TxAnnotations {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = normalize_label(tag);
+        self.tags.contains(&tag)
+    }
+}
+
+pub fn normalize_label(label: &str) -> String {
+    label.trim().trim_start_matches('#').trim().to_lowercase()
+}
+
+fn check_label(label: &str) -> Result<(), String> {
+    if label.is_empty() {
+        return Err("Tags and categories cannot be empty".to_string());
+    }
+    if label.chars().count() > MAX_LABEL_CHARS {
+        return Err(format!("Tags and categories are limited to {} characters", MAX_LABEL_CHARS));
+    }
+    Ok(())
+}
+
+/// Validate and normalize user input; blank notes and categories are cleared
+pub fn build(note: Option<String>, tags: Vec<String>, category: Option<String>) -> Result<TxAnnotations, String> {
+    let note = note
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty());
+    if note.as_ref().is_some_and(|n| n.chars().count() > MAX_NOTE_CHARS) {
+        return Err(format!("Note is limited to {} characters", MAX_NOTE_CHARS));
+    }
+
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = normalize_label(&tag);
+        check_label(&tag)?;
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(format!("At most {} tags per transaction", MAX_TAGS));
+    }
+
+    let category = category
+        .map(|c| normalize_label(&c))
+        .filter(|c| !c.is_empty());
+    if let Some(category) = &category {
+        check_label(category)?;
+    }
+
+    Ok(TxAnnotations { note, tags: normalized, category })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::events::{MemorySink, WalletEvent};
+    use crate::BankingEngine;
+
+    #[test]
+    fn labels_are_normalized_and_deduplicated() {
+        let built = build(
+            Some("  lunch with Awa  ".to_string()),
+            vec!["#Work".to_string(), " work ".to_string(), "Team".to_string()],
+            Some(" Food ".to_string()),
+        )
+        .unwrap();
+
+        assert_eq!(built.note.as_deref(), Some("lunch with Awa"));
+        assert_eq!(built.tags, vec!["work", "team"]);
+        assert_eq!(built.category.as_deref(), Some("food"));
+        assert!(built.has_tag("#WORK"));
+    }
+
+    #[test]
+    fn blank_note_and_category_are_cleared() {
+        let built = build(Some("   ".to_string()), Vec::new(), Some("  ".to_string())).unwrap();
+        assert_eq!(built, TxAnnotations::default());
+    }
+
+    #[test]
+    fn limits_are_enforced() {
+        assert!(build(Some("x".repeat(MAX_NOTE_CHARS + 1)), Vec::new(), None).is_err());
+        assert!(build(None, vec!["#".to_string()], None).is_err());
+        assert!(build(None, vec!["x".repeat(MAX_LABEL_CHARS + 1)], None).is_err());
+        let too_many = (0..=MAX_TAGS).map(|i| format!("tag{}", i)).collect();
+        assert!(build(None, too_many, None).is_err());
+    }
+
+    #[test]
+    fn annotations_survive_restart() {
+        let dir = temp_dir();
+        let mut engine = wallet();
+        engine.set_data_dir(dir.clone()).unwrap();
+        let payee = wallet();
+        let tx = engine.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+        engine.annotate_transaction(&tx.id, Some("lunch".to_string()), vec!["work".to_string()], Some("Food".to_string())).unwrap();
+
+        let mut reloaded = BankingEngine::new();
+        reloaded.set_data_dir(dir.clone()).unwrap();
+        let annotations = &reloaded.transactions.get(&tx.id).unwrap().annotations;
+        assert_eq!(annotations.category.as_deref(), Some("food"));
+        assert_eq!(annotations.note.as_deref(), Some("lunch"));
+        assert_eq!(reloaded.spent_this_month().get("food"), Some(&100));
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn annotation_stands_when_budget_alert_cannot_be_saved() {
+        let dir = temp_dir();
+        let mut engine = wallet();
+        engine.set_data_dir(dir.clone()).unwrap();
+        let sink = MemorySink::new();
+        engine.set_event_sink(Box::new(sink.clone()));
+        engine.set_budget("food", 100).unwrap();
+        let payee = wallet();
+        let tx = engine.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+        sink.drain();
+
+        // Le fichier temporaire des budgets ne peut plus être écrit
+        std::fs::create_dir_all(dir.join("budgets.tmp")).unwrap();
+        let annotated = engine.annotate_transaction(&tx.id, None, Vec::new(), Some("food".to_string())).unwrap();
+
+        assert_eq!(annotated.annotations.category.as_deref(), Some("food"));
+        assert!(sink.drain().iter().any(|e| matches!(e, WalletEvent::BudgetAlert(a) if a.threshold == 100)));
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn unknown_transaction_cannot_be_annotated() {
+        let mut engine = wallet();
+        assert!(engine.annotate_transaction("missing", Some("note".to_string()), Vec::new(), None).is_err());
+    }
+}
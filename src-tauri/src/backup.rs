@@ -0,0 +1,258 @@
+// Sauvegarde complète et chiffrée du portefeuille
+//
+// Format du fichier :
+//   "FLXB" | version (1) | sel Argon2 (16) | nonce (12) | état chiffré
+// L'état (JSON) est chiffré avec ChaCha20-Poly1305, la clé étant dérivée du mot de
+// passe de sauvegarde par Argon2id ; l'en-tête sert de données associées.
+//
+// Une sauvegarde est une photo : restaurer une copie ancienne rendrait dépensables
+// des fonds du coffre déjà dépensés hors ligne. L'import refuse donc une sauvegarde
+// plus ancienne que l'état local du même portefeuille, et bloque les paiements
+// sortants jusqu'à ce que le serveur ait confirmé le dernier numéro de séquence.
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+
+use crate::budgets::BudgetState;
+use crate::contacts::ContactBook;
+use crate::merchant::MerchantState;
+use crate::replay::ReplayGuard;
+use crate::succession::KeySuccession;
+use crate::{KeyPair, PaymentReceipt, PaymentRequest, Refund, Transaction, Wallet};
+
+const MAGIC: &[u8; 4] = b"FLXB";
+pub const BACKUP_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+const MIN_PASSWORD_LEN: usize = 8;
+
+/// Everything needed to bring the wallet back on another device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletBackup {
+    pub created_at: String,
+    pub wallet: Wallet,
+    pub keypair: KeyPair,
+    pub mnemonic: Option<String>,
+    pub pin_hash: Option<String>,
+    pub transactions: Vec<Transaction>,
+    pub receipts: Vec<PaymentReceipt>,
+    pub refunds: Vec<Refund>,
+    pub issued_requests: Vec<PaymentRequest>,
+    pub incoming_requests: Vec<PaymentRequest>,
+    /// Last sequence number used by this wallet when the backup was taken
+    pub next_sequence: u64,
+    pub replay_guard: ReplayGuard,
+    pub contacts: ContactBook,
+    pub merchant: MerchantState,
+    #[serde(default)]
+    pub genesis_public_key: Option<String>,
+    #[serde(default)]
+    pub key_successions: Vec<KeySuccession>,
+    #[serde(default)]
+    pub budgets: BudgetState,
+}
+
+fn derive_key(password: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+pub fn encrypt(backup: &WalletBackup, password: &str) -> Result<Vec<u8>, String> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(format!("Backup password must be at least {} characters", MIN_PASSWORD_LEN));
+    }
+
+    let salt: [u8; SALT_LEN] = rand::random();
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let key = derive_key(password, &salt)?;
+
+    let mut out = Vec::with_capacity(HEADER_LEN);
+    out.extend_from_slice(MAGIC);
+    out.push(BACKUP_VERSION);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+
+    let plaintext = serde_json::to_vec(backup).map_err(|e| e.to_string())?;
+    let ciphertext = ChaCha20Poly1305::new(&Key::from(key))
+        .encrypt(&Nonce::from(nonce), Payload { msg: &plaintext, aad: &out })
+        .map_err(|_| "Encryption failed".to_string())?;
+
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+pub fn decrypt(data: &[u8], password: &str) -> Result<WalletBackup, String> {
+    if data.len() < HEADER_LEN || &data[..MAGIC.len()] != MAGIC {
+        return Err("Not a Fluxa backup file".to_string());
+    }
+
+    let version = data[MAGIC.len()];
+    if version != BACKUP_VERSION {
+        return Err(format!("Unsupported backup version: {}", version));
+    }
+
+    let (header, ciphertext) = data.split_at(HEADER_LEN);
+    let salt = &header[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce.copy_from_slice(&header[HEADER_LEN - NONCE_LEN..]);
+
+    let key = derive_key(password, salt)?;
+    let plaintext = ChaCha20Poly1305::new(&Key::from(key))
+        .decrypt(&Nonce::from(nonce), Payload { msg: ciphertext, aad: header })
+        .map_err(|_| "Wrong password or corrupted backup".to_string())?;
+
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Corrupted backup contents: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::BankingEngine;
+
+    fn backup() -> WalletBackup {
+        WalletBackup {
+            created_at: "2026-10-01T10:00:00+00:00".to_string(),
+            wallet: Wallet {
+                id: "wallet-a".to_string(),
+                online_balance: 25000,
+                offline_balance: 15000,
+                total_balance: 40000,
+                created_at: "2026-09-01T10:00:00+00:00".to_string(),
+                last_updated: "2026-10-01T10:00:00+00:00".to_string(),
+            },
+            keypair: KeyPair {
+                public_key: "11".repeat(32),
+                private_key: "22".repeat(32),
+                created_at: "2026-09-01T10:00:00+00:00".to_string(),
+            },
+            mnemonic: None,
+            pin_hash: None,
+            transactions: Vec::new(),
+            receipts: Vec::new(),
+            refunds: Vec::new(),
+            issued_requests: Vec::new(),
+            incoming_requests: Vec::new(),
+            next_sequence: 7,
+            replay_guard: ReplayGuard::default(),
+            contacts: ContactBook::default(),
+            merchant: MerchantState::default(),
+            genesis_public_key: None,
+            key_successions: Vec::new(),
+            budgets: BudgetState::default(),
+        }
+    }
+
+    #[test]
+    fn round_trip_with_the_right_password() {
+        let data = encrypt(&backup(), "correct horse").unwrap();
+        assert_eq!(&data[..4], MAGIC);
+        assert_eq!(data[4], BACKUP_VERSION);
+
+        let restored = decrypt(&data, "correct horse").unwrap();
+        assert_eq!(restored.wallet.id, "wallet-a");
+        assert_eq!(restored.next_sequence, 7);
+        assert!(decrypt(&data, "wrong horse").is_err());
+    }
+
+    #[test]
+    fn each_backup_uses_a_fresh_salt_and_nonce() {
+        let first = encrypt(&backup(), "correct horse").unwrap();
+        let second = encrypt(&backup(), "correct horse").unwrap();
+        assert_ne!(first[..HEADER_LEN], second[..HEADER_LEN]);
+    }
+
+    #[test]
+    fn short_passwords_are_refused() {
+        assert!(encrypt(&backup(), "1234567").is_err());
+    }
+
+    #[test]
+    fn tampered_header_or_body_is_detected() {
+        let data = encrypt(&backup(), "correct horse").unwrap();
+
+        // L'en-tête est authentifié comme données associées
+        let mut header = data.clone();
+        header[MAGIC.len() + 1] ^= 1;
+        assert!(decrypt(&header, "correct horse").is_err());
+
+        let mut body = data.clone();
+        let last = body.len() - 1;
+        body[last] ^= 1;
+        assert!(decrypt(&body, "correct horse").is_err());
+
+        let mut version = data.clone();
+        version[MAGIC.len()] = BACKUP_VERSION + 1;
+        assert!(decrypt(&version, "correct horse").unwrap_err().contains("version"));
+        assert!(decrypt(b"FLX", "correct horse").is_err());
+    }
+
+    #[test]
+    fn backup_restore_blocks_spending_until_signed_snapshot() {
+        let mut original = wallet_with_pin();
+        let payee = wallet();
+        original.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+        let stale = original.export_backup("1234", "correct horse").unwrap();
+        original.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+        let fresh = original.export_backup("1234", "correct horse").unwrap();
+
+        assert!(original.import_backup(&stale, "correct horse").is_err());
+
+        // A freshly initialized device only holds its opening deposit
+        let mut restored = wallet();
+        assert!(restored.import_backup(&fresh, "wrong password").is_err());
+        restored.import_backup(&fresh, "correct horse").unwrap();
+        assert_eq!(restored.get_wallet().id, original.get_wallet().id);
+        assert!(restored.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 1, None).is_err());
+
+        let (issuer_private, issuer_public) = trust_issuer(&mut restored);
+        let mut snapshot = snapshot_for(&restored, 25000, 14800);
+        snapshot.last_sequence = 5;
+        snapshot.sign(&issuer_public, &issuer_private).unwrap();
+        restored.apply_account_snapshot(snapshot).unwrap();
+
+        let next = restored.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 1, None).unwrap();
+        assert_eq!(next.sequence, 6);
+    }
+
+    #[test]
+    fn refund_waits_for_restore_to_finish() {
+        let (mut payer, mut payee) = (wallet(), wallet_with_pin());
+        let tx = pay(&mut payer, &mut payee, 1000);
+        let backup = payee.export_backup("1234", "correct horse").unwrap();
+
+        let mut restored = BankingEngine::new();
+        restored.import_backup(&backup, "correct horse").unwrap();
+        assert!(restored.issue_refund(tx.id, 1000, "refund".to_string()).is_err());
+        assert!(restored.refunds.is_empty());
+    }
+
+    #[test]
+    fn backup_export_needs_the_pin() {
+        let mut engine = wallet();
+        assert!(engine.export_backup("1234", "correct horse").is_err());
+
+        engine.set_pin("1234", None).unwrap();
+        assert!(engine.export_backup("9999", "correct horse").is_err());
+        assert_eq!(engine.failed_pin_attempts, 1);
+        engine.export_backup("1234", "correct horse").unwrap();
+    }
+
+    #[test]
+    fn backup_does_not_replace_another_wallet_with_history() {
+        let mut original = wallet_with_pin();
+        let backup = original.export_backup("1234", "correct horse").unwrap();
+
+        let (mut other, mut payee) = (wallet(), wallet());
+        pay(&mut other, &mut payee, 100);
+        let other_id = other.get_wallet().id;
+        assert!(other.import_backup(&backup, "correct horse").is_err());
+        assert_eq!(other.get_wallet().id, other_id);
+    }
+}
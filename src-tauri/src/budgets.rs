@@ -0,0 +1,272 @@
+// Budgets mensuels par catégorie
+//
+// La consommation d'un budget est la somme des paiements émis ce mois-ci (UTC) dans
+// la catégorie, annulations exclues. Chaque seuil d'alerte n'est signalé qu'une fois
+// par mois et par catégorie ; l'état des alertes est conservé avec les budgets pour
+// ne pas répéter une alerte après un redémarrage.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use chrono::Utc;
+
+use crate::annotations::normalize_label;
+use crate::Transaction;
+
+/// Percentages of the limit that raise an alert
+pub const ALERT_THRESHOLDS: &[u32] = &[80, 100];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Budget {
+    pub category: String,
+    pub monthly_limit: u64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetStatus {
+    pub category: String,
+    /// "2026-10"
+    pub month: String,
+    pub monthly_limit: u64,
+    pub spent: u64,
+    pub remaining: u64,
+    /// Share of the limit used, may exceed 100
+    pub percent: u32,
+    pub over_budget: bool,
+}
+
+impl BudgetStatus {
+    fn new(budget: &Budget, month: &str, spent: u64) -> Self {
+        let percent = (spent as u128 * 100 / budget.monthly_limit as u128).min(u32::MAX as u128) as u32;
+        BudgetStatus {
+            category: budget.category.clone(),
+            month: month.to_string(),
+            monthly_limit: budget.monthly_limit,
+            spent,
+            remaining: budget.monthly_limit.saturating_sub(spent),
+            percent,
+            over_budget: spent > budget.monthly_limit,
+        }
+    }
+}
+
+/// Payload of the `budget-alert` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetAlert {
+    pub category: String,
+    pub month: String,
+    pub threshold: u32,
+    pub spent: u64,
+    pub monthly_limit: u64,
+}
+
+pub fn current_month() -> String {
+    Utc::now().format("%Y-%m").to_string()
+}
+
+/// Payments sent per category; `transactions` must already be limited to the month
+pub fn spent_by_category<'a>(
+    transactions: impl Iterator<Item = &'a Transaction>,
+    wallet_id: &str,
+) -> HashMap<String, u64> {
+    let mut spent = HashMap::new();
+    for tx in transactions {
+        let is_payment = tx.tx_type == "online" || tx.tx_type == "offline";
+        if !is_payment || tx.status == "cancelled" || tx.from_wallet_id != wallet_id || tx.to_wallet_id == wallet_id {
+            continue;
+        }
+        if let Some(category) = &tx.annotations.category {
+            *spent.entry(category.clone()).or_insert(0) += tx.amount;
+        }
+    }
+    spent
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetState {
+    budgets: Vec<Budget>,
+    /// "month:category" -> highest threshold already signalled
+    #[serde(default)]
+    notified: HashMap<String, u32>,
+}
+
+impl BudgetState {
+    pub fn budgets(&self) -> Vec<Budget> {
+        self.budgets.clone()
+    }
+
+    pub fn find(&self, category: &str) -> Option<&Budget> {
+        let category = normalize_label(category);
+        self.budgets.iter().find(|b| b.category == category)
+    }
+
+    /// Create or change the budget of a category
+    pub fn set(&mut self, category: &str, monthly_limit: u64) -> Result<Budget, String> {
+        let category = normalize_label(category);
+        if category.is_empty() {
+            return Err("Category is required".to_string());
+        }
+        if monthly_limit == 0 {
+            return Err("Budget must be positive".to_string());
+        }
+
+        if let Some(budget) = self.budgets.iter_mut().find(|b| b.category == category) {
+            budget.monthly_limit = monthly_limit;
+            return Ok(budget.clone());
+        }
+
+        let budget = Budget {
+            category,
+            monthly_limit,
+            created_at: Utc::now().to_rfc3339(),
+        };
+        self.budgets.push(budget.clone());
+        Ok(budget)
+    }
+
+    pub fn remove(&mut self, category: &str) -> bool {
+        let category = normalize_label(category);
+        let before = self.budgets.len();
+        self.budgets.retain(|b| b.category != category);
+        self.notified.retain(|key, _| !key.ends_with(&format!(":{}", category)));
+        before != self.budgets.len()
+    }
+
+    pub fn status(&self, month: &str, spent: &HashMap<String, u64>) -> Vec<BudgetStatus> {
+        self.budgets.iter()
+            .map(|b| BudgetStatus::new(b, month, spent.get(&b.category).copied().unwrap_or(0)))
+            .collect()
+    }
+
+    /// Status if a payment of `amount` were added to `category`
+    pub fn preview(&self, month: &str, spent: &HashMap<String, u64>, category: &str, amount: u64) -> Option<BudgetStatus> {
+        self.find(category).map(|b| {
+            let already = spent.get(&b.category).copied().unwrap_or(0);
+            BudgetStatus::new(b, month, already.saturating_add(amount))
+        })
+    }
+
+    /// Thresholds crossed since the last call; each one is reported once per month
+    pub fn new_alerts(&mut self, month: &str, spent: &HashMap<String, u64>) -> Vec<BudgetAlert> {
+        let prefix = format!("{}:", month);
+        self.notified.retain(|key, _| key.starts_with(&prefix));
+
+        let mut alerts = Vec::new();
+        for status in self.status(month, spent) {
+            let key = format!("{}{}", prefix, status.category);
+            let already = self.notified.get(&key).copied().unwrap_or(0);
+            // Jumping past several thresholds at once only reports the highest
+            let crossed = ALERT_THRESHOLDS.iter()
+                .copied()
+                .filter(|&t| t > already && status.percent >= t)
+                .max();
+
+            if let Some(threshold) = crossed {
+                self.notified.insert(key, threshold);
+                alerts.push(BudgetAlert {
+                    category: status.category.clone(),
+                    month: month.to_string(),
+                    threshold,
+                    spent: status.spent,
+                    monthly_limit: status.monthly_limit,
+                });
+            }
+        }
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::events::{MemorySink, WalletEvent};
+
+    fn spent(category: &str, amount: u64) -> HashMap<String, u64> {
+        HashMap::from([(category.to_string(), amount)])
+    }
+
+    #[test]
+    fn each_threshold_alerts_once_per_month() {
+        let mut state = BudgetState::default();
+        state.set("Food", 1000).unwrap();
+
+        assert!(state.new_alerts("2026-10", &spent("food", 500)).is_empty());
+        let first = state.new_alerts("2026-10", &spent("food", 850));
+        assert_eq!(first.iter().map(|a| a.threshold).collect::<Vec<_>>(), vec![80]);
+        assert!(state.new_alerts("2026-10", &spent("food", 900)).is_empty());
+
+        let second = state.new_alerts("2026-10", &spent("food", 1200));
+        assert_eq!(second[0].threshold, 100);
+        assert!(state.new_alerts("2026-10", &spent("food", 1500)).is_empty());
+
+        // Nouveau mois : les seuils repartent de zéro
+        assert_eq!(state.new_alerts("2026-11", &spent("food", 900))[0].threshold, 80);
+    }
+
+    #[test]
+    fn jumping_several_thresholds_reports_the_highest() {
+        let mut state = BudgetState::default();
+        state.set("transport", 100).unwrap();
+        let alerts = state.new_alerts("2026-10", &spent("transport", 150));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].threshold, 100);
+    }
+
+    #[test]
+    fn status_and_preview() {
+        let mut state = BudgetState::default();
+        state.set("food", 1000).unwrap();
+
+        let status = &state.status("2026-10", &spent("food", 1200))[0];
+        assert_eq!(status.remaining, 0);
+        assert_eq!(status.percent, 120);
+        assert!(status.over_budget);
+
+        let preview = state.preview("2026-10", &spent("food", 700), "FOOD", u64::MAX).unwrap();
+        assert_eq!(preview.spent, u64::MAX);
+        assert!(state.preview("2026-10", &HashMap::new(), "rent", 10).is_none());
+    }
+
+    #[test]
+    fn invalid_budgets_are_refused() {
+        let mut state = BudgetState::default();
+        assert!(state.set("  ", 100).is_err());
+        assert!(state.set("food", 0).is_err());
+        assert!(!state.remove("food"));
+    }
+
+    #[test]
+    fn categorized_payment_counts_towards_budget_and_alerts() {
+        let mut engine = wallet();
+        let sink = MemorySink::new();
+        engine.set_event_sink(Box::new(sink.clone()));
+        engine.set_budget("food", 1000).unwrap();
+        let payee = wallet();
+
+        let tx = engine.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 850, Some("Food".to_string())).unwrap();
+        assert_eq!(tx.annotations.category.as_deref(), Some("food"));
+        assert_eq!(engine.spent_this_month().get("food"), Some(&850));
+
+        let alerts: Vec<u32> = sink.drain().into_iter()
+            .filter_map(|e| match e {
+                WalletEvent::BudgetAlert(alert) => Some(alert.threshold),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(alerts, vec![80]);
+
+        engine.create_online_transaction(payee.get_wallet().id, "Shop".to_string(), 200, Some("food".to_string())).unwrap();
+        assert!(sink.drain().iter().any(|e| matches!(e, WalletEvent::BudgetAlert(a) if a.threshold == 100)));
+    }
+
+    #[test]
+    fn invalid_category_creates_no_payment() {
+        let mut engine = wallet();
+        let payee = wallet();
+        let category = Some("x".repeat(crate::annotations::MAX_LABEL_CHARS + 1));
+        assert!(engine.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, category).is_err());
+        assert_eq!(engine.transactions.len(), 1);
+        assert_eq!(engine.get_wallet().offline_balance, 15000);
+    }
+}
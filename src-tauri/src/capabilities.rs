@@ -0,0 +1,206 @@
+// Détection des capacités matérielles (NFC, Bluetooth) et des canaux P2P utilisables
+//
+// Rust n'a pas accès directement aux API NfcAdapter / BluetoothAdapter : l'état est
+// fourni par un `CapabilityProvider`. Par défaut, le frontend rapporte l'état du
+// matériel au démarrage ; en attendant, un téléphone est supposé avoir le NFC et un
+// build desktop non. Les tests peuvent installer un fournisseur fixe.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionState {
+    Granted,
+    Denied,
+    /// Not asked yet; the OS will prompt on first use
+    Prompt,
+    /// The platform does not gate BLE behind a runtime permission
+    NotRequired,
+    Unknown,
+}
+
+/// Raw hardware state as seen by the platform
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareStatus {
+    pub nfc_present: bool,
+    pub nfc_enabled: bool,
+    pub ble_present: bool,
+    pub ble_enabled: bool,
+    pub ble_permission: PermissionState,
+}
+
+impl HardwareStatus {
+    /// Assumed state before the platform has reported anything: phones are
+    /// expected to have NFC, BLE waits for its permission to be known
+    pub fn platform_default() -> Self {
+        let mobile = cfg!(any(target_os = "android", target_os = "ios"));
+        HardwareStatus {
+            nfc_present: mobile,
+            nfc_enabled: mobile,
+            ble_present: false,
+            ble_enabled: false,
+            ble_permission: PermissionState::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCapabilities {
+    pub nfc_present: bool,
+    pub nfc_enabled: bool,
+    pub ble_present: bool,
+    pub ble_enabled: bool,
+    pub ble_permission: PermissionState,
+    /// P2P channels usable right now ("nfc", "ble", "qr")
+    pub channels: Vec<String>,
+    /// Whether the status came from the platform rather than defaults
+    pub reported: bool,
+}
+
+impl DeviceCapabilities {
+    pub fn from_status(status: &HardwareStatus, reported: bool) -> Self {
+        let mut channels = Vec::new();
+
+        if status.nfc_present && status.nfc_enabled {
+            channels.push("nfc".to_string());
+        }
+
+        let ble_allowed = matches!(
+            status.ble_permission,
+            PermissionState::Granted | PermissionState::NotRequired
+        );
+        if status.ble_present && status.ble_enabled && ble_allowed {
+            channels.push("ble".to_string());
+        }
+
+        // QR codes only need a screen and a camera, available on every supported device
+        channels.push("qr".to_string());
+
+        DeviceCapabilities {
+            nfc_present: status.nfc_present,
+            nfc_enabled: status.nfc_enabled,
+            ble_present: status.ble_present,
+            ble_enabled: status.ble_enabled,
+            ble_permission: status.ble_permission,
+            channels,
+            reported,
+        }
+    }
+
+    pub fn nfc_available(&self) -> bool {
+        self.nfc_present && self.nfc_enabled
+    }
+}
+
+pub trait CapabilityProvider: Send {
+    fn capabilities(&self) -> DeviceCapabilities;
+
+    /// Record a fresh status from the platform; fixed providers ignore it
+    fn report(&mut self, _status: HardwareStatus) {}
+}
+
+/// Provider fed by the native layer or frontend through `report_device_status`
+pub struct ReportedProvider {
+    status: HardwareStatus,
+    reported: bool,
+}
+
+impl ReportedProvider {
+    pub fn new() -> Self {
+        ReportedProvider {
+            status: HardwareStatus::platform_default(),
+            reported: false,
+        }
+    }
+}
+
+impl Default for ReportedProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CapabilityProvider for ReportedProvider {
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::from_status(&self.status, self.reported)
+    }
+
+    fn report(&mut self, status: HardwareStatus) {
+        self.status = status;
+        self.reported = true;
+    }
+}
+
+/// Provider returning a fixed status, for desktop builds without NFC and for tests
+pub struct FixedProvider {
+    status: HardwareStatus,
+}
+
+impl FixedProvider {
+    pub fn new(status: HardwareStatus) -> Self {
+        FixedProvider { status }
+    }
+}
+
+impl CapabilityProvider for FixedProvider {
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::from_status(&self.status, true)
+    }
+}
+
+/// Provider used when the app starts; the frontend reports the real state once loaded
+pub fn default_provider() -> Box<dyn CapabilityProvider> {
+    Box::new(ReportedProvider::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(nfc: bool, ble: bool, permission: PermissionState) -> HardwareStatus {
+        HardwareStatus {
+            nfc_present: true,
+            nfc_enabled: nfc,
+            ble_present: true,
+            ble_enabled: ble,
+            ble_permission: permission,
+        }
+    }
+
+    #[test]
+    fn channels_follow_hardware_state() {
+        let provider = FixedProvider::new(status(true, true, PermissionState::Granted));
+        let capabilities = provider.capabilities();
+        assert_eq!(capabilities.channels, vec!["nfc", "ble", "qr"]);
+        assert!(capabilities.nfc_available());
+
+        let provider = FixedProvider::new(status(false, true, PermissionState::Denied));
+        let capabilities = provider.capabilities();
+        assert_eq!(capabilities.channels, vec!["qr"]);
+        assert!(!capabilities.nfc_available());
+
+        let provider = FixedProvider::new(status(false, true, PermissionState::NotRequired));
+        assert_eq!(provider.capabilities().channels, vec!["ble", "qr"]);
+    }
+
+    #[test]
+    fn fixed_provider_ignores_reports() {
+        let mut provider = FixedProvider::new(status(true, false, PermissionState::Prompt));
+        provider.report(status(false, false, PermissionState::Denied));
+        assert!(provider.capabilities().nfc_available());
+    }
+
+    #[test]
+    fn reported_provider_uses_platform_default_until_reported() {
+        let mut provider = ReportedProvider::new();
+        let before = provider.capabilities();
+        assert!(!before.reported);
+        assert_eq!(before.nfc_available(), cfg!(any(target_os = "android", target_os = "ios")));
+        assert!(!before.channels.contains(&"ble".to_string()));
+
+        provider.report(status(true, true, PermissionState::Granted));
+        let after = provider.capabilities();
+        assert!(after.reported);
+        assert_eq!(after.channels, vec!["nfc", "ble", "qr"]);
+    }
+}
@@ -0,0 +1,232 @@
+// Encodage binaire canonique et versionné des données signées
+//
+// Toute donnée signée commence par un en-tête de séparation de domaine :
+//   "FLUXA" | version (u8) | longueur du domaine (u8) | domaine
+// suivi des champs dans un ordre fixe :
+//   - chaîne : longueur u32 big-endian puis octets UTF-8
+//   - entier : u64 big-endian
+//   - option : 0x00 (absent) ou 0x01 suivi de la valeur
+//   - octets : longueur u32 big-endian puis octets bruts
+//
+// Le même encodage sert à la signature, à la vérification, au transport NFC/BLE
+// et au serveur de règlement. Changer l'ordre ou le type d'un champ impose
+// d'incrémenter ENCODING_VERSION.
+//
+// Vecteurs de référence (domaine "tx", version 1), à reproduire par toute implémentation :
+//   id "tx-1", from "wallet-a", to "wallet-b", merchant "Shop", amount 1500,
+//   timestamp "2024-01-01T00:00:00+00:00", tx_type "offline", request_id absent, sequence 7
+// encodage :
+//   464c555841010274780000000474782d310000000877616c6c65742d610000000877616c6c65742d62
+//   0000000453686f7000000000000005dc00000019323032342d30312d30315430303a30303a30302b30
+//   303a3030000000076f66666c696e65000000000000000007
+// clé privée 0101...01 (32 octets), clé publique :
+//   8a88e3dd7409f195fd52db2d3cba5d72ca6709bf1d94121bf3748801b40f6f5c
+// signature Ed25519 de l'encodage :
+//   c9a3987554c82204719a253afd7356d6ce5d10b405c89a5ded48411f831ef0cb
+//   94b2fbb9f2c386a828f23bc90262c321c633d43b591920afd76eeb5f761d930e
+
+pub const MAGIC: &[u8; 5] = b"FLUXA";
+pub const ENCODING_VERSION: u8 = 1;
+
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new(domain: &str) -> Self {
+        let mut buf = Vec::with_capacity(128);
+        buf.extend_from_slice(MAGIC);
+        buf.push(ENCODING_VERSION);
+        buf.push(domain.len() as u8);
+        buf.extend_from_slice(domain.as_bytes());
+        Encoder { buf }
+    }
+
+    pub fn str(self, value: &str) -> Self {
+        self.bytes(value.as_bytes())
+    }
+
+    pub fn u64(mut self, value: u64) -> Self {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn opt_str(mut self, value: Option<&str>) -> Self {
+        match value {
+            Some(v) => {
+                self.buf.push(1);
+                self.str(v)
+            }
+            None => {
+                self.buf.push(0);
+                self
+            }
+        }
+    }
+
+    pub fn bytes(mut self, value: &[u8]) -> Self {
+        self.buf.extend_from_slice(&(value.len() as u32).to_be_bytes());
+        self.buf.extend_from_slice(value);
+        self
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+pub struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Check the header and position the decoder on the first field
+    pub fn new(data: &'a [u8], domain: &str) -> Result<Self, String> {
+        let mut decoder = Decoder { data, pos: 0 };
+
+        if decoder.take(MAGIC.len())? != MAGIC {
+            return Err("Not a Fluxa payload".to_string());
+        }
+
+        let version = decoder.take(1)?[0];
+        if version != ENCODING_VERSION {
+            return Err(format!("Unsupported encoding version: {}", version));
+        }
+
+        let len = decoder.take(1)?[0] as usize;
+        if decoder.take(len)? != domain.as_bytes() {
+            return Err(format!("Expected a \"{}\" payload", domain));
+        }
+
+        Ok(decoder)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len())
+            .ok_or("Truncated payload")?;
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let raw = self.take(4)?;
+        let len = u32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]) as usize;
+        self.take(len)
+    }
+
+    pub fn str(&mut self) -> Result<String, String> {
+        let raw = self.bytes()?;
+        String::from_utf8(raw.to_vec()).map_err(|_| "Invalid UTF-8 in payload".to_string())
+    }
+
+    pub fn u64(&mut self) -> Result<u64, String> {
+        let raw = self.take(8)?;
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(raw);
+        Ok(u64::from_be_bytes(buf))
+    }
+
+    pub fn opt_str(&mut self) -> Result<Option<String>, String> {
+        match self.take(1)?[0] {
+            0 => Ok(None),
+            1 => self.str().map(Some),
+            _ => Err("Invalid option flag".to_string()),
+        }
+    }
+
+    /// Fail if anything is left after the last field
+    pub fn finish(self) -> Result<(), String> {
+        if self.pos != self.data.len() {
+            return Err("Trailing bytes in payload".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::TxAnnotations;
+    use crate::{crypto, Transaction};
+
+    const GOLDEN_ENCODING: &str = concat!(
+        "464c555841010274780000000474782d310000000877616c6c65742d610000000877616c6c65742d62",
+        "0000000453686f7000000000000005dc00000019323032342d30312d30315430303a30303a30302b30",
+        "303a3030000000076f66666c696e65000000000000000007",
+    );
+    const GOLDEN_PRIVATE_KEY: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+    const GOLDEN_PUBLIC_KEY: &str = "8a88e3dd7409f195fd52db2d3cba5d72ca6709bf1d94121bf3748801b40f6f5c";
+    const GOLDEN_SIGNATURE: &str = concat!(
+        "c9a3987554c82204719a253afd7356d6ce5d10b405c89a5ded48411f831ef0cb",
+        "94b2fbb9f2c386a828f23bc90262c321c633d43b591920afd76eeb5f761d930e",
+    );
+
+    fn golden_transaction() -> Transaction {
+        Transaction {
+            id: "tx-1".to_string(),
+            from_wallet_id: "wallet-a".to_string(),
+            to_wallet_id: "wallet-b".to_string(),
+            merchant_name: "Shop".to_string(),
+            amount: 1500,
+            timestamp: "2024-01-01T00:00:00+00:00".to_string(),
+            signature: String::new(),
+            tx_type: "offline".to_string(),
+            status: "pending".to_string(),
+            request_id: None,
+            sequence: 7,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
+        }
+    }
+
+    #[test]
+    fn golden_encoding() {
+        assert_eq!(hex::encode(golden_transaction().signing_bytes()), GOLDEN_ENCODING);
+    }
+
+    #[test]
+    fn golden_signature() {
+        let seed: [u8; 32] = hex::decode(GOLDEN_PRIVATE_KEY).unwrap().try_into().unwrap();
+        let (private_key, public_key) = crypto::keypair_from_seed(&seed);
+        assert_eq!(public_key, GOLDEN_PUBLIC_KEY);
+
+        let bytes = golden_transaction().signing_bytes();
+        assert_eq!(crypto::sign(&private_key, &bytes).unwrap(), GOLDEN_SIGNATURE);
+        assert!(crypto::verify(GOLDEN_PUBLIC_KEY, &bytes, GOLDEN_SIGNATURE).unwrap());
+    }
+
+    #[test]
+    fn golden_decoding() {
+        let tx = Transaction::from_signing_bytes(&hex::decode(GOLDEN_ENCODING).unwrap()).unwrap();
+        assert_eq!(tx.signing_bytes(), golden_transaction().signing_bytes());
+        assert_eq!(tx.sequence, 7);
+        assert_eq!(tx.request_id, None);
+    }
+
+    #[test]
+    fn decoder_rejects_bad_input() {
+        let data = Encoder::new("tx").str("a").u64(1).opt_str(Some("b")).finish();
+        assert!(Decoder::new(&data, "receipt").is_err());
+        assert!(Decoder::new(b"FLUXB\x01\x02tx", "tx").is_err());
+
+        let mut wrong_version = data.clone();
+        wrong_version[5] = 2;
+        assert!(Decoder::new(&wrong_version, "tx").is_err());
+
+        let mut decoder = Decoder::new(&data[..data.len() - 1], "tx").unwrap();
+        assert_eq!(decoder.str().unwrap(), "a");
+        assert_eq!(decoder.u64().unwrap(), 1);
+        assert!(decoder.opt_str().is_err());
+
+        let mut trailing = data.clone();
+        trailing.push(0);
+        let mut decoder = Decoder::new(&trailing, "tx").unwrap();
+        decoder.str().unwrap();
+        decoder.u64().unwrap();
+        decoder.opt_str().unwrap();
+        assert!(decoder.finish().is_err());
+    }
+}
@@ -0,0 +1,333 @@
+// Carnet d'adresses : surnoms -> portefeuille + clé publique
+//
+// Les contacts sont ajoutés à la main ou appris lors des échanges P2P terminés
+// (confiance à la première utilisation). Si un contact connu se présente ensuite avec
+// une autre clé, la clé enregistrée n'est pas remplacée : la nouvelle est mise en
+// attente et un avertissement reste affiché jusqu'à ce que l'utilisateur tranche.
+
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Contact {
+    pub wallet_id: String,
+    pub public_key: String,
+    /// Empty for contacts learned automatically until the user names them
+    pub nickname: String,
+    /// "manual" or "p2p"
+    pub source: String,
+    pub first_seen: String,
+    pub last_seen: String,
+    /// Different key presented by this wallet, waiting for the user's decision
+    pub pending_key: Option<String>,
+    /// Keys this contact used before, oldest first
+    #[serde(default)]
+    pub previous_keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyChangeWarning {
+    pub wallet_id: String,
+    pub nickname: String,
+    pub known_key: String,
+    pub presented_key: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContactBook {
+    contacts: Vec<Contact>,
+}
+
+impl ContactBook {
+    pub fn contacts(&self) -> Vec<Contact> {
+        self.contacts.clone()
+    }
+
+    pub fn find(&self, wallet_id: &str) -> Option<&Contact> {
+        self.contacts.iter().find(|c| c.wallet_id == wallet_id)
+    }
+
+    pub fn find_by_nickname(&self, nickname: &str) -> Option<&Contact> {
+        self.contacts.iter().find(|c| !c.nickname.is_empty() && c.nickname.eq_ignore_ascii_case(nickname))
+    }
+
+    fn check_nickname(&self, nickname: &str, wallet_id: &str) -> Result<(), String> {
+        if nickname.trim().is_empty() {
+            return Err("Nickname is required".to_string());
+        }
+        match self.find_by_nickname(nickname.trim()) {
+            Some(other) if other.wallet_id != wallet_id => Err("Nickname already used".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Add a contact by hand, or name one learned from an exchange
+    pub fn add(&mut self, nickname: &str, wallet_id: &str, public_key: &str) -> Result<Contact, String> {
+        self.check_nickname(nickname, wallet_id)?;
+        let now = Utc::now().to_rfc3339();
+
+        if let Some(existing) = self.contacts.iter_mut().find(|c| c.wallet_id == wallet_id) {
+            if existing.public_key != public_key {
+                return Err("Wallet is already known with a different key".to_string());
+            }
+            existing.nickname = nickname.trim().to_string();
+            return Ok(existing.clone());
+        }
+
+        let contact = Contact {
+            wallet_id: wallet_id.to_string(),
+            public_key: public_key.to_string(),
+            nickname: nickname.trim().to_string(),
+            source: "manual".to_string(),
+            first_seen: now.clone(),
+            last_seen: now,
+            pending_key: None,
+            previous_keys: Vec::new(),
+        };
+        self.contacts.push(contact.clone());
+        Ok(contact)
+    }
+
+    pub fn rename(&mut self, wallet_id: &str, nickname: &str) -> Result<Contact, String> {
+        self.check_nickname(nickname, wallet_id)?;
+        let contact = self.contacts.iter_mut()
+            .find(|c| c.wallet_id == wallet_id)
+            .ok_or("Contact not found")?;
+        contact.nickname = nickname.trim().to_string();
+        Ok(contact.clone())
+    }
+
+    pub fn remove(&mut self, wallet_id: &str) -> bool {
+        let before = self.contacts.len();
+        self.contacts.retain(|c| c.wallet_id != wallet_id);
+        before != self.contacts.len()
+    }
+
+    /// Record a wallet seen in a completed exchange. Returns a warning when a known
+    /// wallet presents a key other than the one on record.
+    pub fn observe(&mut self, wallet_id: &str, public_key: &str) -> Option<KeyChangeWarning> {
+        let now = Utc::now().to_rfc3339();
+
+        match self.contacts.iter_mut().find(|c| c.wallet_id == wallet_id) {
+            Some(contact) => {
+                contact.last_seen = now;
+                if contact.public_key == public_key {
+                    return None;
+                }
+                contact.pending_key = Some(public_key.to_string());
+                Some(KeyChangeWarning {
+                    wallet_id: contact.wallet_id.clone(),
+                    nickname: contact.nickname.clone(),
+                    known_key: contact.public_key.clone(),
+                    presented_key: public_key.to_string(),
+                })
+            }
+            None => {
+                self.contacts.push(Contact {
+                    wallet_id: wallet_id.to_string(),
+                    public_key: public_key.to_string(),
+                    nickname: String::new(),
+                    source: "p2p".to_string(),
+                    first_seen: now.clone(),
+                    last_seen: now,
+                    pending_key: None,
+                    previous_keys: Vec::new(),
+                });
+                None
+            }
+        }
+    }
+
+    /// User confirmed the contact really changed keys
+    pub fn accept_pending_key(&mut self, wallet_id: &str) -> Result<Contact, String> {
+        let contact = self.contacts.iter_mut()
+            .find(|c| c.wallet_id == wallet_id)
+            .ok_or("Contact not found")?;
+        let new_key = contact.pending_key.take().ok_or("No key change pending")?;
+        let old_key = std::mem::replace(&mut contact.public_key, new_key);
+        contact.previous_keys.push(old_key);
+        Ok(contact.clone())
+    }
+
+    /// Move a contact along the keys of its succession chain, current key last
+    pub fn rotate_key(&mut self, wallet_id: &str, chain: &[String]) -> Result<Contact, String> {
+        let contact = self.contacts.iter_mut()
+            .find(|c| c.wallet_id == wallet_id)
+            .ok_or("Contact not found")?;
+
+        for key in chain {
+            if &contact.public_key != key {
+                let old_key = std::mem::replace(&mut contact.public_key, key.clone());
+                contact.previous_keys.push(old_key);
+            }
+        }
+        if contact.pending_key.as_ref() == Some(&contact.public_key) {
+            contact.pending_key = None;
+        }
+        Ok(contact.clone())
+    }
+
+    /// User rejected the new key; the recorded one stays
+    pub fn reject_pending_key(&mut self, wallet_id: &str) -> Result<Contact, String> {
+        let contact = self.contacts.iter_mut()
+            .find(|c| c.wallet_id == wallet_id)
+            .ok_or("Contact not found")?;
+        contact.pending_key = None;
+        Ok(contact.clone())
+    }
+
+    pub fn warnings(&self) -> Vec<KeyChangeWarning> {
+        self.contacts.iter()
+            .filter_map(|c| {
+                c.pending_key.as_ref().map(|presented| KeyChangeWarning {
+                    wallet_id: c.wallet_id.clone(),
+                    nickname: c.nickname.clone(),
+                    known_key: c.public_key.clone(),
+                    presented_key: presented.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::events::{MemorySink, WalletEvent};
+    use crate::crypto;
+    use crate::p2p::handshake::HandshakeMessage;
+
+    #[test]
+    fn observe_learns_unknown_wallets_without_a_name() {
+        let mut book = ContactBook::default();
+        assert!(book.observe("wallet-a", "key-1").is_none());
+        let contact = book.find("wallet-a").unwrap();
+        assert_eq!((contact.source.as_str(), contact.nickname.as_str()), ("p2p", ""));
+        assert!(book.find_by_nickname("").is_none());
+        assert!(book.observe("wallet-a", "key-1").is_none());
+        assert_eq!(book.contacts().len(), 1);
+    }
+
+    #[test]
+    fn new_key_stays_pending_until_accepted() {
+        let mut book = ContactBook::default();
+        book.add("Ali", "wallet-a", "key-1").unwrap();
+
+        let warning = book.observe("wallet-a", "key-2").unwrap();
+        assert_eq!((warning.known_key.as_str(), warning.presented_key.as_str()), ("key-1", "key-2"));
+        assert_eq!(warning.nickname, "Ali");
+        assert_eq!(book.warnings().len(), 1);
+        assert_eq!(book.find("wallet-a").unwrap().public_key, "key-1");
+
+        let contact = book.accept_pending_key("wallet-a").unwrap();
+        assert_eq!(contact.public_key, "key-2");
+        assert_eq!(contact.previous_keys, ["key-1"]);
+        assert!(book.warnings().is_empty());
+        assert!(book.accept_pending_key("wallet-a").is_err());
+    }
+
+    #[test]
+    fn rejected_key_keeps_the_recorded_one() {
+        let mut book = ContactBook::default();
+        book.add("Ali", "wallet-a", "key-1").unwrap();
+        book.observe("wallet-a", "key-2");
+
+        let contact = book.reject_pending_key("wallet-a").unwrap();
+        assert_eq!(contact.public_key, "key-1");
+        assert!(contact.pending_key.is_none());
+    }
+
+    #[test]
+    fn rotation_follows_the_chain_and_clears_a_matching_warning() {
+        let mut book = ContactBook::default();
+        book.add("Ali", "wallet-a", "key-1").unwrap();
+        book.observe("wallet-a", "key-3");
+
+        let contact = book.rotate_key("wallet-a", &["key-1".to_string(), "key-2".to_string(), "key-3".to_string()]).unwrap();
+        assert_eq!(contact.public_key, "key-3");
+        assert_eq!(contact.previous_keys, ["key-1", "key-2"]);
+        assert!(contact.pending_key.is_none());
+    }
+
+    #[test]
+    fn nicknames_are_unique_and_keys_fixed() {
+        let mut book = ContactBook::default();
+        book.add("Ali", "wallet-a", "key-1").unwrap();
+        assert!(book.add("ali", "wallet-b", "key-2").is_err());
+        assert!(book.add("  ", "wallet-b", "key-2").is_err());
+        assert!(book.add("Ali B", "wallet-a", "key-9").is_err());
+
+        book.rename("wallet-a", "Ali Shop").unwrap();
+        assert_eq!(book.find_by_nickname("ALI SHOP").unwrap().wallet_id, "wallet-a");
+        assert!(book.remove("wallet-a"));
+        assert!(!book.remove("wallet-a"));
+    }
+
+    #[test]
+    fn key_exchange_alone_adds_no_contact() {
+        let (mut sender, mut receiver) = (wallet(), wallet());
+        secure_pair(&mut sender, &mut receiver);
+        assert!(sender.get_contacts().is_empty());
+        assert!(receiver.get_contacts().is_empty());
+
+        let ack = handshake_until_ack(&mut sender, &mut receiver, 500);
+        sender.p2p_handle_message(ack, None).unwrap();
+        assert!(sender.contacts.find(&receiver.get_wallet().id).is_some());
+        assert!(receiver.contacts.find(&sender.get_wallet().id).is_some());
+    }
+
+    #[test]
+    fn secure_ack_must_carry_the_channel_key() {
+        let (mut sender, mut receiver) = (wallet(), wallet());
+        let (sender_channel, receiver_channel) = secure_pair(&mut sender, &mut receiver);
+
+        let HandshakeMessage::Ack { session_id, mut receipt } = handshake_until_ack(&mut sender, &mut receiver, 500) else {
+            panic!("expected an acknowledgement");
+        };
+        receipt.payee_public_key = sender.get_public_key().unwrap();
+        let frame = receiver.p2p_secure_seal(&receiver_channel, &HandshakeMessage::Ack { session_id, receipt }).unwrap();
+        assert!(sender.p2p_secure_open(&sender_channel, &frame).is_err());
+    }
+
+    #[test]
+    fn unannounced_key_change_raises_a_contact_event() {
+        let (mut payer, mut payee) = (wallet(), wallet_with_pin());
+        pay(&mut payer, &mut payee, 100);
+        payee.rotate_keys("1234").unwrap();
+
+        let sink = MemorySink::new();
+        payer.set_event_sink(Box::new(sink.clone()));
+        pay(&mut payer, &mut payee, 100);
+
+        let warning = sink.drain().into_iter().find_map(|e| match e {
+            WalletEvent::ContactKeyChanged(warning) => Some(warning),
+            _ => None,
+        }).unwrap();
+        assert_eq!(warning.wallet_id, payee.get_wallet().id);
+        assert_eq!(warning.presented_key, payee.get_public_key().unwrap());
+        assert_eq!(payer.get_contact_warnings().len(), 1);
+    }
+
+    #[test]
+    fn payment_with_another_key_raises_a_contact_event() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        pay(&mut payer, &mut payee, 100);
+        let sink = MemorySink::new();
+        payee.set_event_sink(Box::new(sink.clone()));
+
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+        let (private_key, public_key) = crypto::generate_keypair();
+        let mut forged = tx.clone();
+        forged.signature = crypto::sign(&private_key, &forged.signing_bytes()).unwrap();
+        assert!(payee.receive_payment(forged, public_key.clone(), None).is_err());
+
+        let warning = sink.drain().into_iter().find_map(|e| match e {
+            WalletEvent::ContactKeyChanged(warning) => Some(warning),
+            _ => None,
+        }).unwrap();
+        assert_eq!(warning.wallet_id, payer.get_wallet().id);
+        assert_eq!(warning.known_key, payer.get_public_key().unwrap());
+        assert_eq!(warning.presented_key, public_key);
+    }
+}
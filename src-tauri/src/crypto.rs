@@ -0,0 +1,56 @@
+// Primitives de signature Ed25519 utilisées par le moteur, les reçus et la couche P2P
+
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature, Signer, Verifier};
+use std::convert::TryFrom;
+
+/// Generate a fresh Ed25519 key pair, returned as (private_key, public_key) hex strings
+pub fn generate_keypair() -> (String, String) {
+    let seed: [u8; 32] = rand::random();
+    keypair_from_seed(&seed)
+}
+
+/// Derive an Ed25519 key pair from a 32-byte seed
+pub fn keypair_from_seed(seed: &[u8; 32]) -> (String, String) {
+    // A 32-byte slice is always a valid Ed25519 secret key
+    let secret = SecretKey::from_bytes(seed).expect("32-byte seed");
+    let public = PublicKey::from(&secret);
+    (hex::encode(secret.to_bytes()), hex::encode(public.to_bytes()))
+}
+
+/// Check that a hex string is a well-formed Ed25519 public key
+pub fn validate_public_key(public_key: &str) -> Result<(), String> {
+    let key_bytes = hex::decode(public_key).map_err(|_| "Invalid public key encoding".to_string())?;
+    PublicKey::from_bytes(&key_bytes).map_err(|_| "Invalid public key".to_string())?;
+    Ok(())
+}
+
+fn load_keypair(private_key: &str) -> Result<Keypair, String> {
+    let bytes = hex::decode(private_key).map_err(|_| "Invalid private key encoding".to_string())?;
+    let secret = SecretKey::from_bytes(&bytes).map_err(|_| "Invalid private key".to_string())?;
+    let public = PublicKey::from(&secret);
+    Ok(Keypair { secret, public })
+}
+
+/// Sign raw bytes with a hex-encoded private key, returning a hex signature
+pub fn sign(private_key: &str, data: &[u8]) -> Result<String, String> {
+    let keypair = load_keypair(private_key)?;
+    Ok(hex::encode(keypair.sign(data).to_bytes()))
+}
+
+/// Verify a hex signature against a hex-encoded public key.
+/// Malformed signatures are reported as invalid rather than as errors.
+pub fn verify(public_key: &str, data: &[u8], signature: &str) -> Result<bool, String> {
+    let key_bytes = hex::decode(public_key).map_err(|_| "Invalid public key encoding".to_string())?;
+    let public = PublicKey::from_bytes(&key_bytes).map_err(|_| "Invalid public key".to_string())?;
+
+    let sig_bytes = match hex::decode(signature) {
+        Ok(bytes) => bytes,
+        Err(_) => return Ok(false),
+    };
+    let signature = match Signature::try_from(sig_bytes.as_slice()) {
+        Ok(sig) => sig,
+        Err(_) => return Ok(false),
+    };
+
+    Ok(public.verify(data, &signature).is_ok())
+}
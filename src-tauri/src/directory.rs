@@ -0,0 +1,341 @@
+// Annuaire des marchands vérifiés, gardé en cache pour fonctionner hors ligne
+//
+// `merchant_name` est saisi librement par le payeur : n'importe qui peut se présenter
+// comme "Orange Money Shop". Chaque fiche marchand porte un badge signé par l'émetteur
+// Fluxa qui lie le portefeuille, sa clé publique et son nom affiché. Avant de payer, on
+// vérifie le destinataire : marchand vérifié, inconnu, ou portefeuille qui reprend le
+// nom d'un marchand vérifié sans en être un (usurpation). Quand la clé du destinataire
+// est connue, elle doit être celle attestée par le badge : un portefeuille qui
+// reprend l'identifiant d'un marchand avec une autre clé est aussi une usurpation.
+
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+use crate::{codec, crypto};
+
+/// Issuer attestation that a wallet belongs to a verified merchant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationBadge {
+    pub issuer_public_key: String,
+    pub issued_at: String,
+    pub expires_at: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerchantIdentity {
+    pub wallet_id: String,
+    pub public_key: String,
+    pub display_name: String,
+    pub category: String,
+    pub badge: Option<VerificationBadge>,
+}
+
+impl MerchantIdentity {
+    pub fn signing_bytes(&self, issued_at: &str, expires_at: &str) -> Vec<u8> {
+        codec::Encoder::new("merchant-badge")
+            .str(&self.wallet_id)
+            .str(&self.public_key)
+            .str(&self.display_name)
+            .str(&self.category)
+            .str(issued_at)
+            .str(expires_at)
+            .finish()
+    }
+
+    /// Sign a badge for this identity; used by the issuer and in tests
+    pub fn sign_badge(
+        &mut self,
+        issuer_public_key: &str,
+        issuer_private_key: &str,
+        issued_at: &str,
+        expires_at: &str,
+    ) -> Result<(), String> {
+        let signature = crypto::sign(issuer_private_key, &self.signing_bytes(issued_at, expires_at))?;
+        self.badge = Some(VerificationBadge {
+            issuer_public_key: issuer_public_key.to_string(),
+            issued_at: issued_at.to_string(),
+            expires_at: expires_at.to_string(),
+            signature,
+        });
+        Ok(())
+    }
+
+    /// Whether the badge is signed by a trusted issuer; expiry is checked separately
+    pub fn has_valid_badge(&self, trusted_issuers: &[String]) -> Result<bool, String> {
+        let badge = match &self.badge {
+            Some(badge) => badge,
+            None => return Ok(false),
+        };
+
+        if !trusted_issuers.contains(&badge.issuer_public_key.to_lowercase()) {
+            return Ok(false);
+        }
+
+        crypto::verify(
+            &badge.issuer_public_key,
+            &self.signing_bytes(&badge.issued_at, &badge.expires_at),
+            &badge.signature,
+        )
+    }
+
+    pub fn badge_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.badge.as_ref()
+            .and_then(|b| DateTime::parse_from_rfc3339(&b.expires_at).ok())
+            .map(|expires_at| now > expires_at)
+            .unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MerchantStatus {
+    Verified,
+    Unverified,
+    /// The name matches a verified merchant but the wallet is not theirs
+    Impersonation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerchantCheck {
+    pub status: MerchantStatus,
+    /// Directory record for the destination wallet, or for the merchant it imitates
+    pub identity: Option<MerchantIdentity>,
+    pub warning: Option<String>,
+}
+
+/// Compare names loosely: case, spacing and punctuation do not make a different merchant
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Offline cache of verified merchants
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MerchantDirectory {
+    merchants: Vec<MerchantIdentity>,
+    pub updated_at: Option<String>,
+}
+
+impl MerchantDirectory {
+    /// Add or replace records; entries without a valid trusted badge are rejected.
+    /// Returns how many records were stored.
+    pub fn import(
+        &mut self,
+        identities: Vec<MerchantIdentity>,
+        trusted_issuers: &[String],
+    ) -> Result<usize, String> {
+        let mut stored = 0;
+        for identity in identities {
+            if !identity.has_valid_badge(trusted_issuers)? {
+                continue;
+            }
+            self.merchants.retain(|m| m.wallet_id != identity.wallet_id);
+            self.merchants.push(identity);
+            stored += 1;
+        }
+        self.updated_at = Some(Utc::now().to_rfc3339());
+        Ok(stored)
+    }
+
+    pub fn merchants(&self) -> Vec<MerchantIdentity> {
+        self.merchants.clone()
+    }
+
+    pub fn find(&self, wallet_id: &str) -> Option<&MerchantIdentity> {
+        self.merchants.iter().find(|m| m.wallet_id == wallet_id)
+    }
+
+    /// Classify a payment destination; `public_key` is the payee's key when known
+    pub fn check(
+        &self,
+        wallet_id: &str,
+        public_key: Option<&str>,
+        merchant_name: &str,
+        trusted_issuers: &[String],
+    ) -> Result<MerchantCheck, String> {
+        let now = Utc::now();
+
+        if let Some(identity) = self.find(wallet_id) {
+            if identity.has_valid_badge(trusted_issuers)? && !identity.badge_expired_at(now) {
+                if public_key.is_some_and(|key| !key.eq_ignore_ascii_case(&identity.public_key)) {
+                    return Ok(MerchantCheck {
+                        status: MerchantStatus::Impersonation,
+                        identity: Some(identity.clone()),
+                        warning: Some(format!(
+                            "Wallet key does not match the verified merchant \"{}\"",
+                            identity.display_name
+                        )),
+                    });
+                }
+                let warning = (normalize_name(merchant_name) != normalize_name(&identity.display_name))
+                    .then(|| format!("Wallet is registered as \"{}\"", identity.display_name));
+                return Ok(MerchantCheck {
+                    status: MerchantStatus::Verified,
+                    identity: Some(identity.clone()),
+                    warning,
+                });
+            }
+        }
+
+        let name = normalize_name(merchant_name);
+        if !name.is_empty() {
+            let imitated = self.merchants.iter().find(|m| {
+                m.wallet_id != wallet_id
+                    && normalize_name(&m.display_name) == name
+                    && !m.badge_expired_at(now)
+            });
+            if let Some(identity) = imitated {
+                if identity.has_valid_badge(trusted_issuers)? {
+                    return Ok(MerchantCheck {
+                        status: MerchantStatus::Impersonation,
+                        identity: Some(identity.clone()),
+                        warning: Some(format!(
+                            "\"{}\" is a verified merchant, but this is not their wallet",
+                            identity.display_name
+                        )),
+                    });
+                }
+            }
+        }
+
+        let warning = match self.find(wallet_id) {
+            Some(_) => "Merchant verification expired".to_string(),
+            None => "Merchant is not verified".to_string(),
+        };
+        Ok(MerchantCheck {
+            status: MerchantStatus::Unverified,
+            identity: None,
+            warning: Some(warning),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use chrono::Duration;
+
+    struct Issuer {
+        private_key: String,
+        public_key: String,
+    }
+
+    fn issuer() -> Issuer {
+        let (private_key, public_key) = crypto::generate_keypair();
+        Issuer { private_key, public_key }
+    }
+
+    fn merchant(issuer: &Issuer, wallet_id: &str, name: &str, valid_for: Duration) -> MerchantIdentity {
+        let (_, public_key) = crypto::generate_keypair();
+        let mut identity = MerchantIdentity {
+            wallet_id: wallet_id.to_string(),
+            public_key,
+            display_name: name.to_string(),
+            category: "telecom".to_string(),
+            badge: None,
+        };
+        let now = Utc::now();
+        identity.sign_badge(&issuer.public_key, &issuer.private_key, &now.to_rfc3339(), &(now + valid_for).to_rfc3339())
+            .unwrap();
+        identity
+    }
+
+    fn directory(issuer: &Issuer, identities: Vec<MerchantIdentity>) -> MerchantDirectory {
+        let mut directory = MerchantDirectory::default();
+        directory.import(identities, std::slice::from_ref(&issuer.public_key)).unwrap();
+        directory
+    }
+
+    #[test]
+    fn import_keeps_only_trusted_badges() {
+        let (trusted, rogue) = (issuer(), issuer());
+        let mut unsigned = merchant(&trusted, "wallet-c", "Kiosque", Duration::days(30));
+        unsigned.badge = None;
+
+        let mut directory = MerchantDirectory::default();
+        let stored = directory.import(
+            vec![
+                merchant(&trusted, "wallet-a", "Orange Money Shop", Duration::days(30)),
+                merchant(&rogue, "wallet-b", "Orange Money Shop", Duration::days(30)),
+                unsigned,
+            ],
+            std::slice::from_ref(&trusted.public_key),
+        ).unwrap();
+        assert_eq!(stored, 1);
+        assert_eq!(directory.merchants()[0].wallet_id, "wallet-a");
+    }
+
+    #[test]
+    fn verified_wallet_must_present_the_attested_key() {
+        let issuer = issuer();
+        let identity = merchant(&issuer, "wallet-a", "Orange Money Shop", Duration::days(30));
+        let directory = directory(&issuer, vec![identity.clone()]);
+        let trusted = [issuer.public_key.clone()];
+
+        let check = directory.check("wallet-a", Some(&identity.public_key), "orange-money shop", &trusted).unwrap();
+        assert_eq!(check.status, MerchantStatus::Verified);
+        assert!(check.warning.is_none());
+
+        let check = directory.check("wallet-a", None, "Orange Money Shop", &trusted).unwrap();
+        assert_eq!(check.status, MerchantStatus::Verified);
+
+        let (_, other_key) = crypto::generate_keypair();
+        let check = directory.check("wallet-a", Some(&other_key), "Orange Money Shop", &trusted).unwrap();
+        assert_eq!(check.status, MerchantStatus::Impersonation);
+    }
+
+    #[test]
+    fn borrowed_name_is_impersonation() {
+        let issuer = issuer();
+        let directory = directory(&issuer, vec![merchant(&issuer, "wallet-a", "Orange Money Shop", Duration::days(30))]);
+        let trusted = [issuer.public_key.clone()];
+
+        let check = directory.check("wallet-x", None, "ORANGE MONEY SHOP", &trusted).unwrap();
+        assert_eq!(check.status, MerchantStatus::Impersonation);
+        assert_eq!(check.identity.unwrap().wallet_id, "wallet-a");
+
+        let check = directory.check("wallet-x", None, "Chez Ali", &trusted).unwrap();
+        assert_eq!(check.status, MerchantStatus::Unverified);
+    }
+
+    #[test]
+    fn expired_badge_is_no_longer_verified() {
+        let issuer = issuer();
+        let directory = directory(&issuer, vec![merchant(&issuer, "wallet-a", "Orange Money Shop", -Duration::days(1))]);
+        let trusted = [issuer.public_key.clone()];
+
+        let check = directory.check("wallet-a", None, "Orange Money Shop", &trusted).unwrap();
+        assert_eq!(check.status, MerchantStatus::Unverified);
+        assert_eq!(check.warning.as_deref(), Some("Merchant verification expired"));
+    }
+
+    #[test]
+    fn contact_key_is_checked_against_the_directory() {
+        let mut payer = wallet();
+        let (issuer_private, issuer_public) = trust_issuer(&mut payer);
+        let (_, merchant_key) = crypto::generate_keypair();
+        let mut identity = MerchantIdentity {
+            wallet_id: "wallet-shop".to_string(),
+            public_key: merchant_key.clone(),
+            display_name: "Orange Money Shop".to_string(),
+            category: "telecom".to_string(),
+            badge: None,
+        };
+        let expires_at = (Utc::now() + chrono::Duration::days(30)).to_rfc3339();
+        identity.sign_badge(&issuer_public, &issuer_private, &Utc::now().to_rfc3339(), &expires_at).unwrap();
+        payer.import_merchant_directory(vec![identity]).unwrap();
+
+        let check = payer.check_merchant("wallet-shop", Some(&merchant_key), "Orange Money Shop").unwrap();
+        assert_eq!(check.status, MerchantStatus::Verified);
+
+        // Le contact enregistré avec une autre clé n'est pas le marchand attesté
+        let (_, other_key) = crypto::generate_keypair();
+        payer.add_contact("Shop", "wallet-shop", &other_key).unwrap();
+        let tx = payer.create_offline_transaction("wallet-shop".to_string(), "Orange Money Shop".to_string(), 100, None).unwrap();
+        assert_eq!(tx.merchant_status, Some(MerchantStatus::Impersonation));
+    }
+}
@@ -0,0 +1,205 @@
+// Événements de changement d'état envoyés à l'interface
+//
+// Le moteur signale chaque changement de solde ou de transaction à un récepteur
+// d'événements, au lieu de laisser l'interface interroger `get_wallet` et
+// `get_transactions` après chaque action. Dans l'application, le récepteur relaie les
+// événements à la webview via Tauri : un paiement arrivé en arrière-plan par BLE met
+// ainsi tous les écrans à jour. Hors application, le récepteur par défaut les ignore.
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::budgets::BudgetAlert;
+use crate::contacts::KeyChangeWarning;
+use crate::{PaymentReceipt, Transaction, Wallet};
+
+pub const BALANCE_CHANGED: &str = "balance-changed";
+pub const TRANSACTION_CREATED: &str = "transaction-created";
+pub const TRANSACTION_STATUS_CHANGED: &str = "transaction-status-changed";
+pub const PAYMENT_RECEIVED: &str = "payment-received";
+pub const SYNC_PROGRESS: &str = "sync-progress";
+pub const BUDGET_ALERT: &str = "budget-alert";
+pub const CONTACT_KEY_CHANGED: &str = "contact-key-changed";
+
+/// Transactions merged between two `sync-progress` events during a snapshot sync
+pub const SYNC_PROGRESS_STEP: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusChange {
+    pub tx_id: String,
+    pub old_status: String,
+    pub new_status: String,
+    pub transaction: Transaction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentReceived {
+    pub transaction: Transaction,
+    pub receipt: PaymentReceipt,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncProgress {
+    /// "account_snapshot" or "settlement"
+    pub operation: String,
+    pub processed: usize,
+    pub total: usize,
+    pub done: bool,
+}
+
+/// Every event the engine emits; the sink sends the inner payload under `name`
+#[derive(Debug, Clone)]
+pub enum WalletEvent {
+    BalanceChanged(Wallet),
+    TransactionCreated(Box<Transaction>),
+    TransactionStatusChanged(Box<StatusChange>),
+    PaymentReceived(Box<PaymentReceived>),
+    SyncProgress(SyncProgress),
+    BudgetAlert(BudgetAlert),
+    /// A known wallet presented a new key; it stays pending until the user decides
+    ContactKeyChanged(KeyChangeWarning),
+}
+
+impl WalletEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WalletEvent::BalanceChanged(_) => BALANCE_CHANGED,
+            WalletEvent::TransactionCreated(_) => TRANSACTION_CREATED,
+            WalletEvent::TransactionStatusChanged(_) => TRANSACTION_STATUS_CHANGED,
+            WalletEvent::PaymentReceived(_) => PAYMENT_RECEIVED,
+            WalletEvent::SyncProgress(_) => SYNC_PROGRESS,
+            WalletEvent::BudgetAlert(_) => BUDGET_ALERT,
+            WalletEvent::ContactKeyChanged(_) => CONTACT_KEY_CHANGED,
+        }
+    }
+}
+
+pub trait EventSink: Send {
+    fn emit(&self, event: &WalletEvent);
+}
+
+/// Sink used until the app installs its own
+pub struct NoopSink;
+
+impl EventSink for NoopSink {
+    fn emit(&self, _event: &WalletEvent) {}
+}
+
+/// Sink keeping events in memory, for tests and headless tools
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    events: Arc<Mutex<Vec<WalletEvent>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events received since the last call
+    pub fn drain(&self) -> Vec<WalletEvent> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+}
+
+impl EventSink for MemorySink {
+    fn emit(&self, event: &WalletEvent) {
+        self.events.lock().unwrap().push(event.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    fn progress(processed: usize) -> WalletEvent {
+        WalletEvent::SyncProgress(SyncProgress {
+            operation: "settlement".to_string(),
+            processed,
+            total: 2,
+            done: processed == 2,
+        })
+    }
+
+    #[test]
+    fn memory_sink_drains_events_in_order() {
+        let sink = MemorySink::new();
+        // Le moteur reçoit une copie : les deux partagent la même file
+        let engine_side = sink.clone();
+        engine_side.emit(&progress(1));
+        engine_side.emit(&progress(2));
+
+        let events = sink.drain();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], WalletEvent::SyncProgress(p) if p.processed == 1 && !p.done));
+        assert!(matches!(&events[1], WalletEvent::SyncProgress(p) if p.done));
+        assert!(sink.drain().is_empty());
+    }
+
+    #[test]
+    fn names_match_the_frontend_listeners() {
+        assert_eq!(progress(1).name(), "sync-progress");
+        assert_eq!(SYNC_PROGRESS, "sync-progress");
+        assert_eq!(BALANCE_CHANGED, "balance-changed");
+        assert_eq!(PAYMENT_RECEIVED, "payment-received");
+    }
+
+    #[test]
+    fn payment_lifecycle_emits_events() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        let (payer_sink, payee_sink) = (MemorySink::new(), MemorySink::new());
+        payer.set_event_sink(Box::new(payer_sink.clone()));
+        payee.set_event_sink(Box::new(payee_sink.clone()));
+
+        payer.transfer_to_vault(1000).unwrap();
+        let names: Vec<&str> = payer_sink.drain().iter().map(|e| e.name()).collect();
+        assert_eq!(names, [TRANSACTION_CREATED, BALANCE_CHANGED]);
+
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 500, None).unwrap();
+        assert_eq!(payer_sink.drain().len(), 2);
+
+        let receipt = payee.receive_payment(tx.clone(), payer.get_public_key().unwrap(), Some("ble".to_string())).unwrap();
+        let events = payee_sink.drain();
+        assert!(matches!(&events[0], WalletEvent::PaymentReceived(p) if p.transaction.id == tx.id));
+        assert!(matches!(&events[1], WalletEvent::BalanceChanged(w) if w.offline_balance == 15500));
+
+        payer.import_receipt(receipt).unwrap();
+        let events = payer_sink.drain();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            WalletEvent::TransactionStatusChanged(c) if c.old_status == "pending" && c.new_status == "confirmed"
+        ));
+
+        let online = payer.create_online_transaction("wallet-x".to_string(), "Shop".to_string(), 10, None).unwrap();
+        payer_sink.drain();
+        payer.cancel_transaction(online.id).unwrap();
+        let names: Vec<&str> = payer_sink.drain().iter().map(|e| e.name()).collect();
+        assert_eq!(names, [TRANSACTION_STATUS_CHANGED, BALANCE_CHANGED]);
+    }
+
+    #[test]
+    fn rejected_payment_emits_nothing_and_changes_nothing() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        let sink = MemorySink::new();
+        payee.set_event_sink(Box::new(sink.clone()));
+
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 500, None).unwrap();
+        payee.receive_payment(tx.clone(), payer.get_public_key().unwrap(), None).unwrap();
+        sink.drain();
+        let balance = payee.get_wallet().offline_balance;
+        let count = payee.get_transactions().len();
+
+        // Rejeu, puis signature invalide : aucune des deux erreurs ne doit émettre
+        assert!(payee.receive_payment(tx.clone(), payer.get_public_key().unwrap(), None).is_err());
+        let mut forged = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 500, None).unwrap();
+        forged.amount = 5000;
+        assert!(payee.receive_payment(forged, payer.get_public_key().unwrap(), None).is_err());
+
+        assert!(sink.drain().is_empty());
+        assert_eq!(payee.get_wallet().offline_balance, balance);
+        assert_eq!(payee.get_transactions().len(), count);
+        assert_eq!(payee.get_receipts().len(), 1);
+    }
+}
@@ -0,0 +1,527 @@
+// Historique des transactions indexé
+//
+// Le registre garde les transactions dans leur ordre d'arrivée et maintient trois
+// index : par identifiant, par date (du plus récent au plus ancien) et par
+// contrepartie. Les écrans d'historique interrogent le registre page par page avec
+// un curseur opaque, sans recopier tout l'historique à chaque appel.
+//
+// Sur disque, le registre est un journal en ajout seul : chaque création ou
+// modification d'une transaction ajoute son état complet. Au chargement, le dernier
+// état de chaque identifiant l'emporte et les index sont reconstruits.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Bound;
+use chrono::DateTime;
+
+use crate::annotations::normalize_label;
+use crate::Transaction;
+
+/// Ledger journal in the data directory, one transaction record per line
+pub const LEDGER_FILE: &str = "transactions.jsonl";
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+}
+
+/// Filters for a history page; every field is optional
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransactionQuery {
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+    /// Inclusive RFC 3339 bounds
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub min_amount: Option<u64>,
+    pub max_amount: Option<u64>,
+    /// Wallet ID on the other side of the transaction
+    pub counterparty: Option<String>,
+    pub direction: Option<Direction>,
+    /// Case-insensitive match on the merchant name or the note
+    pub search: Option<String>,
+    pub tx_type: Option<String>,
+    pub status: Option<String>,
+    pub category: Option<String>,
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionPage {
+    pub items: Vec<Transaction>,
+    /// Pass back as `cursor` to get the next page; `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Sort key: timestamp in milliseconds, then arrival position
+type TimeKey = (i64, usize);
+
+fn timestamp_ms(timestamp: &str) -> i64 {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.timestamp_millis())
+        .unwrap_or(0)
+}
+
+/// Optional RFC 3339 date as milliseconds since the epoch
+pub fn parse_bound(value: &Option<String>) -> Result<Option<i64>, String> {
+    value.as_ref()
+        .map(|v| {
+            DateTime::parse_from_rfc3339(v)
+                .map(|t| t.timestamp_millis())
+                .map_err(|_| format!("Invalid date: {}", v))
+        })
+        .transpose()
+}
+
+fn encode_cursor(key: TimeKey) -> String {
+    format!("{}:{}", key.0, key.1)
+}
+
+fn decode_cursor(cursor: &str) -> Result<TimeKey, String> {
+    let (ms, pos) = cursor.split_once(':').ok_or("Invalid cursor")?;
+    Ok((
+        ms.parse().map_err(|_| "Invalid cursor")?,
+        pos.parse().map_err(|_| "Invalid cursor")?,
+    ))
+}
+
+/// Transactions with their indexes; serialized as a plain list
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<Transaction>", into = "Vec<Transaction>")]
+pub struct Ledger {
+    entries: Vec<Transaction>,
+    by_id: HashMap<String, usize>,
+    by_time: BTreeSet<TimeKey>,
+    by_counterparty: HashMap<String, BTreeSet<TimeKey>>,
+}
+
+impl From<Vec<Transaction>> for Ledger {
+    fn from(transactions: Vec<Transaction>) -> Self {
+        let mut ledger = Ledger::default();
+        for tx in transactions {
+            ledger.push(tx);
+        }
+        ledger
+    }
+}
+
+impl From<Ledger> for Vec<Transaction> {
+    fn from(ledger: Ledger) -> Self {
+        ledger.entries
+    }
+}
+
+impl Ledger {
+    /// Rebuild from a journal of records: the last record of each ID wins, and
+    /// transactions keep the position of their first record
+    pub fn from_log(records: Vec<Transaction>) -> Self {
+        let mut latest: Vec<Transaction> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for tx in records {
+            match positions.get(&tx.id) {
+                Some(&i) => latest[i] = tx,
+                None => {
+                    positions.insert(tx.id.clone(), latest.len());
+                    latest.push(tx);
+                }
+            }
+        }
+        Ledger::from(latest)
+    }
+
+    pub fn push(&mut self, tx: Transaction) {
+        let position = self.entries.len();
+        let key = (timestamp_ms(&tx.timestamp), position);
+
+        self.by_id.insert(tx.id.clone(), position);
+        self.by_time.insert(key);
+        for wallet_id in [&tx.from_wallet_id, &tx.to_wallet_id] {
+            self.by_counterparty.entry(wallet_id.clone()).or_default().insert(key);
+        }
+        self.entries.push(tx);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Transaction> {
+        self.by_id.get(id).map(|&i| &self.entries[i])
+    }
+
+    /// Mutable access for status updates; IDs, timestamps and wallets must not change
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Transaction> {
+        self.by_id.get(id).map(|&i| &mut self.entries[i])
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.by_id.contains_key(id)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Transaction> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn to_vec(&self) -> Vec<Transaction> {
+        self.entries.clone()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Ledger::default();
+    }
+
+    fn matches(&self, tx: &Transaction, query: &TransactionQuery, own_wallet_id: &str, search: &str) -> bool {
+        if query.min_amount.is_some_and(|min| tx.amount < min)
+            || query.max_amount.is_some_and(|max| tx.amount > max)
+        {
+            return false;
+        }
+
+        match query.direction {
+            Some(Direction::Incoming) if tx.to_wallet_id != own_wallet_id => return false,
+            Some(Direction::Outgoing) if tx.from_wallet_id != own_wallet_id => return false,
+            _ => {}
+        }
+
+        if query.tx_type.as_ref().is_some_and(|t| &tx.tx_type != t)
+            || query.status.as_ref().is_some_and(|s| &tx.status != s)
+        {
+            return false;
+        }
+
+        if query.category.as_ref().is_some_and(|c| tx.annotations.category != Some(normalize_label(c)))
+            || query.tag.as_ref().is_some_and(|t| !tx.annotations.has_tag(t))
+        {
+            return false;
+        }
+
+        search.is_empty()
+            || tx.merchant_name.to_lowercase().contains(search)
+            || tx.annotations.note.as_ref().is_some_and(|n| n.to_lowercase().contains(search))
+    }
+
+    /// Transactions from `from_ms` (inclusive) until `until_ms` (exclusive), oldest first
+    pub fn between(&self, from_ms: Option<i64>, until_ms: Option<i64>) -> Vec<&Transaction> {
+        let lower = from_ms.map_or(Bound::Unbounded, |ms| Bound::Included((ms, 0)));
+        let upper = until_ms.map_or(Bound::Unbounded, |ms| Bound::Excluded((ms, 0)));
+        if let (Some(from), Some(until)) = (from_ms, until_ms) {
+            if from >= until {
+                return Vec::new();
+            }
+        }
+        self.by_time.range((lower, upper)).map(|key| &self.entries[key.1]).collect()
+    }
+
+    /// One page of history, newest first
+    pub fn query(&self, query: &TransactionQuery, own_wallet_id: &str) -> Result<TransactionPage, String> {
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let search = query.search.as_deref().unwrap_or("").trim().to_lowercase();
+
+        // Newest bound: the cursor or `to`, whichever is older
+        let mut upper = match parse_bound(&query.to)? {
+            Some(ms) => Bound::Included((ms, usize::MAX)),
+            None => Bound::Unbounded,
+        };
+        if let Some(cursor) = &query.cursor {
+            let key = decode_cursor(cursor)?;
+            let before_to = match upper {
+                Bound::Included(to) => key < to,
+                _ => true,
+            };
+            if before_to {
+                upper = Bound::Excluded(key);
+            }
+        }
+        let lower = match parse_bound(&query.from)? {
+            Some(ms) => Bound::Included((ms, 0)),
+            None => Bound::Unbounded,
+        };
+
+        let empty_range = match (lower, upper) {
+            (Bound::Included(low), Bound::Included(high)) => low > high,
+            (Bound::Included(low), Bound::Excluded(high)) => low >= high,
+            _ => false,
+        };
+        if empty_range {
+            return Ok(TransactionPage { items: Vec::new(), next_cursor: None });
+        }
+
+        // Counterparty queries only walk that wallet's entries
+        let index = match &query.counterparty {
+            Some(wallet_id) => match self.by_counterparty.get(wallet_id) {
+                Some(index) => index,
+                None => return Ok(TransactionPage { items: Vec::new(), next_cursor: None }),
+            },
+            None => &self.by_time,
+        };
+
+        let mut items = Vec::with_capacity(limit);
+        let mut last_key = None;
+        let mut more = false;
+
+        for &key in index.range((lower, upper)).rev() {
+            let tx = &self.entries[key.1];
+            if !self.matches(tx, query, own_wallet_id, &search) {
+                continue;
+            }
+            if items.len() == limit {
+                more = true;
+                break;
+            }
+            items.push(tx.clone());
+            last_key = Some(key);
+        }
+
+        Ok(TransactionPage {
+            items,
+            next_cursor: if more { last_key.map(encode_cursor) } else { None },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::BankingEngine;
+    use crate::annotations::TxAnnotations;
+
+    const ME: &str = "wallet-me";
+
+    fn tx(id: &str, minute: u32, to: &str, amount: u64) -> Transaction {
+        Transaction {
+            id: id.to_string(),
+            from_wallet_id: ME.to_string(),
+            to_wallet_id: to.to_string(),
+            merchant_name: format!("Shop {}", id),
+            amount,
+            timestamp: format!("2026-10-01T10:{:02}:00+00:00", minute),
+            signature: String::new(),
+            tx_type: "offline".to_string(),
+            status: "pending".to_string(),
+            request_id: None,
+            sequence: minute as u64,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
+        }
+    }
+
+    /// Ten payments, one per minute from 10:00 to 10:09, alternating between two payees
+    fn ledger() -> Ledger {
+        Ledger::from(
+            (0..10)
+                .map(|i| tx(&format!("t{}", i), i, if i % 2 == 0 { "bob" } else { "carol" }, 100 * (i as u64 + 1)))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn ids(page: &TransactionPage) -> Vec<String> {
+        page.items.iter().map(|t| t.id.clone()).collect()
+    }
+
+    #[test]
+    fn cursor_walks_every_entry_once_newest_first() {
+        let ledger = ledger();
+        let mut query = TransactionQuery { limit: Some(4), ..Default::default() };
+        let mut seen = Vec::new();
+
+        loop {
+            let page = ledger.query(&query, ME).unwrap();
+            seen.extend(ids(&page));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+
+        let expected: Vec<String> = (0..10).rev().map(|i| format!("t{}", i)).collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn last_full_page_has_no_cursor() {
+        let page = ledger().query(&TransactionQuery { limit: Some(10), ..Default::default() }, ME).unwrap();
+        assert_eq!(page.items.len(), 10);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn date_bounds_are_inclusive() {
+        let query = TransactionQuery {
+            from: Some("2026-10-01T10:02:00+00:00".to_string()),
+            to: Some("2026-10-01T10:05:00+00:00".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(&ledger().query(&query, ME).unwrap()), vec!["t5", "t4", "t3", "t2"]);
+    }
+
+    #[test]
+    fn cursor_combines_with_date_bounds() {
+        let ledger = ledger();
+        let mut query = TransactionQuery {
+            limit: Some(2),
+            from: Some("2026-10-01T10:02:00+00:00".to_string()),
+            to: Some("2026-10-01T10:06:00+00:00".to_string()),
+            ..Default::default()
+        };
+        let first = ledger.query(&query, ME).unwrap();
+        assert_eq!(ids(&first), vec!["t6", "t5"]);
+
+        query.cursor = first.next_cursor;
+        let second = ledger.query(&query, ME).unwrap();
+        assert_eq!(ids(&second), vec!["t4", "t3"]);
+
+        query.cursor = second.next_cursor;
+        let last = ledger.query(&query, ME).unwrap();
+        assert_eq!(ids(&last), vec!["t2"]);
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[test]
+    fn inverted_bounds_and_bad_input() {
+        let ledger = ledger();
+        let inverted = TransactionQuery {
+            from: Some("2026-10-01T10:06:00+00:00".to_string()),
+            to: Some("2026-10-01T10:02:00+00:00".to_string()),
+            ..Default::default()
+        };
+        assert!(ledger.query(&inverted, ME).unwrap().items.is_empty());
+
+        let bad_date = TransactionQuery { from: Some("yesterday".to_string()), ..Default::default() };
+        assert!(ledger.query(&bad_date, ME).is_err());
+        let bad_cursor = TransactionQuery { cursor: Some("abc".to_string()), ..Default::default() };
+        assert!(ledger.query(&bad_cursor, ME).is_err());
+    }
+
+    #[test]
+    fn counterparty_and_amount_filters() {
+        let query = TransactionQuery {
+            counterparty: Some("carol".to_string()),
+            min_amount: Some(400),
+            max_amount: Some(800),
+            ..Default::default()
+        };
+        assert_eq!(ids(&ledger().query(&query, ME).unwrap()), vec!["t7", "t5", "t3"]);
+    }
+
+    #[test]
+    fn between_is_half_open() {
+        let ledger = ledger();
+        let from = parse_bound(&Some("2026-10-01T10:03:00+00:00".to_string())).unwrap();
+        let until = parse_bound(&Some("2026-10-01T10:05:00+00:00".to_string())).unwrap();
+        let found: Vec<&str> = ledger.between(from, until).iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(found, vec!["t3", "t4"]);
+    }
+
+    #[test]
+    fn log_keeps_last_record_and_first_position() {
+        let mut confirmed = tx("t0", 0, "bob", 100);
+        confirmed.status = "confirmed".to_string();
+        let ledger = Ledger::from_log(vec![
+            tx("t0", 0, "bob", 100),
+            tx("t1", 1, "carol", 200),
+            confirmed,
+        ]);
+
+        assert_eq!(ledger.len(), 2);
+        assert_eq!(ledger.get("t0").unwrap().status, "confirmed");
+        let order: Vec<&str> = ledger.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(order, vec!["t0", "t1"]);
+        let page = ledger.query(&TransactionQuery { counterparty: Some("bob".to_string()), ..Default::default() }, ME).unwrap();
+        assert_eq!(page.items.len(), 1);
+    }
+
+    #[test]
+    fn ledger_survives_restart_with_latest_status() {
+        let dir = temp_dir();
+        let mut payer = wallet();
+        payer.set_data_dir(dir.clone()).unwrap();
+        let mut payee = wallet();
+
+        let kept = pay(&mut payer, &mut payee, 300);
+        let cancelled = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 200, None).unwrap();
+        payer.cancel_transaction(cancelled.id.clone()).unwrap();
+
+        let mut reloaded = BankingEngine::new();
+        reloaded.set_data_dir(dir.clone()).unwrap();
+        assert_eq!(reloaded.transactions.len(), 2);
+        assert_eq!(reloaded.transactions.get(&kept.id).unwrap().status, "confirmed");
+        assert_eq!(reloaded.transactions.get(&cancelled.id).unwrap().status, "cancelled");
+        assert_eq!(reloaded.get_wallet().total_balance, payer.get_wallet().total_balance);
+
+        let page = reloaded.query_transactions(&TransactionQuery::default()).unwrap();
+        assert_eq!(page.items[0].id, cancelled.id);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn restore_rewrites_the_ledger_journal() {
+        let dir = temp_dir();
+        let mut engine = wallet();
+        engine.set_data_dir(dir.clone()).unwrap();
+        let payee = wallet();
+        engine.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+
+        let phrase = engine.mnemonic.clone().unwrap();
+        engine.restore_from_mnemonic(&phrase, None).unwrap();
+
+        let mut reloaded = BankingEngine::new();
+        reloaded.set_data_dir(dir.clone()).unwrap();
+        assert!(reloaded.transactions.is_empty());
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn failed_journal_write_leaves_balance_and_sequence_unchanged() {
+        let dir = temp_dir();
+        let mut payer = wallet();
+        payer.set_data_dir(dir.clone()).unwrap();
+        let payee = wallet();
+        // A directory in place of the file makes the write fail
+        std::fs::create_dir_all(dir.join(LEDGER_FILE)).unwrap();
+
+        assert!(payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 1000, None).is_err());
+        assert!(payer.transfer_to_vault(1000).is_err());
+        assert!(payer.transfer_from_vault(1000).is_err());
+        assert_eq!(payer.get_wallet().online_balance, 25000);
+        assert_eq!(payer.get_wallet().offline_balance, 15000);
+        assert_eq!(payer.next_sequence, 0);
+        assert_eq!(payer.get_transactions().len(), 1);
+
+        std::fs::remove_dir(dir.join(LEDGER_FILE)).unwrap();
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 1000, None).unwrap();
+        assert_eq!(tx.sequence, 1);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn failed_wallet_write_leaves_balance_unchanged() {
+        let dir = temp_dir();
+        let (mut payer, mut payee) = (wallet(), wallet());
+        payee.set_data_dir(dir.clone()).unwrap();
+        let tx = pay(&mut payer, &mut payee, 1000);
+        std::fs::remove_file(dir.join("wallet.json")).unwrap();
+        std::fs::create_dir_all(dir.join("wallet.json")).unwrap();
+
+        let wallet_before = payee.get_wallet();
+        assert!(payee.create_offline_transaction(payer.get_wallet().id, "Shop".to_string(), 500, None).is_err());
+        assert!(payee.transfer_to_vault(500).is_err());
+        assert!(payee.issue_refund(tx.id.clone(), 500, "refund".to_string()).is_err());
+        assert_eq!(payee.get_wallet().online_balance, wallet_before.online_balance);
+        assert_eq!(payee.get_wallet().offline_balance, wallet_before.offline_balance);
+        assert_eq!(payee.next_sequence, 0);
+        assert!(payee.get_refunds(&tx.id).is_empty());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
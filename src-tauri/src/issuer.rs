@@ -0,0 +1,16 @@
+// Clés publiques de l'émetteur Fluxa (serveur), ancres de confiance pour les
+// badges marchands et les listes signées.
+//
+// Les clés sont fixées à la compilation via FLUXA_ISSUER_PUBLIC_KEYS (clés Ed25519
+// hexadécimales séparées par des virgules) pour qu'aucun appel depuis le frontend ne
+// puisse ajouter une ancre de confiance.
+
+/// Issuer keys baked into this build
+pub fn trusted_issuer_keys() -> Vec<String> {
+    option_env!("FLUXA_ISSUER_PUBLIC_KEYS")
+        .unwrap_or("")
+        .split(',')
+        .map(|k| k.trim().to_lowercase())
+        .filter(|k| !k.is_empty())
+        .collect()
+}
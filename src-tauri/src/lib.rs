@@ -2,11 +2,69 @@
 // Ce fichier a été nettoyé et adapté manuellement pour un style plus naturel
 
 use serde::{Deserialize, Serialize};
-use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Mutex;
 use uuid::Uuid;
 use chrono::Utc;
 use lazy_static::lazy_static;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub mod annotations;
+pub mod capabilities;
+pub mod backup;
+pub mod budgets;
+pub mod codec;
+pub mod contacts;
+pub mod crypto;
+pub mod directory;
+pub mod events;
+pub mod history;
+pub mod merchant;
+pub mod receipt;
+pub mod refund;
+pub mod payment_request;
+pub mod issuer;
+pub mod p2p;
+pub mod recovery;
+pub mod replay;
+pub mod revocation;
+pub mod statement;
+pub mod stats;
+pub mod storage;
+pub mod succession;
+
+#[cfg(test)]
+mod test_support;
+
+pub use receipt::PaymentReceipt;
+pub use refund::Refund;
+pub use payment_request::PaymentRequest;
+pub use p2p::{P2PPayload, SignedPayment};
+use replay::ReplayGuard;
+use recovery::AccountSnapshot;
+use backup::WalletBackup;
+use budgets::{Budget, BudgetState, BudgetStatus};
+use events::{EventSink, NoopSink, PaymentReceived, StatusChange, SyncProgress, WalletEvent, SYNC_PROGRESS_STEP};
+use annotations::TxAnnotations;
+use history::{Ledger, TransactionPage, TransactionQuery, LEDGER_FILE};
+use receipt::RECEIPTS_FILE;
+use stats::WalletStats;
+use statement::{ExportFormat, ExportRow, HistoryExport, MonthlyStatement};
+use succession::{KeyChain, KeySuccession};
+use revocation::{RevocationDelta, RevocationList, RevocationState, RevocationStatus};
+use contacts::{Contact, ContactBook, KeyChangeWarning};
+use directory::{MerchantCheck, MerchantDirectory, MerchantIdentity, MerchantStatus};
+use merchant::{MerchantProfile, MerchantState, TillEntry, TillSession, ZReport};
+use capabilities::{CapabilityProvider, DeviceCapabilities, HardwareStatus};
+use p2p::handshake::{HandshakeMessage, HandshakeRole, HandshakeSession, HandshakeState};
+use p2p::version::{self, PeerCapabilities};
+use p2p::discovery::{Advertisement, DeviceTracker, Proximity};
+use p2p::framing::{FrameChannel, DEFAULT_MAX_MESSAGE_BYTES, MIN_MTU};
+use p2p::session::{KeyExchange, SecureSession, SessionHello, SessionRole};
+
+/// Sender recorded on the opening deposit of a new wallet
+pub const FUNDING_WALLET_ID: &str = "fluxa-funding";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Wallet {
@@ -18,6 +76,29 @@ pub struct Wallet {
     pub last_updated: String,
 }
 
+impl Wallet {
+    /// Copy of the wallet with `amount` added to the online or offline balance
+    fn credited(&self, online: bool, amount: u64) -> Result<Wallet, String> {
+        let mut wallet = self.clone();
+        let balance = if online { &mut wallet.online_balance } else { &mut wallet.offline_balance };
+        *balance = balance.checked_add(amount).ok_or("Balance overflow")?;
+        wallet.total_balance = wallet.online_balance.checked_add(wallet.offline_balance)
+            .ok_or("Balance overflow")?;
+        wallet.last_updated = Utc::now().to_rfc3339();
+        Ok(wallet)
+    }
+
+    /// Copy of the wallet with `amount` taken from the online or offline balance
+    fn debited(&self, online: bool, amount: u64) -> Result<Wallet, String> {
+        let mut wallet = self.clone();
+        let balance = if online { &mut wallet.online_balance } else { &mut wallet.offline_balance };
+        *balance = balance.checked_sub(amount).ok_or("Insufficient balance")?;
+        wallet.total_balance = wallet.online_balance + wallet.offline_balance;
+        wallet.last_updated = Utc::now().to_rfc3339();
+        Ok(wallet)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
     pub id: String,
@@ -29,6 +110,61 @@ pub struct Transaction {
     pub signature: String,
     pub tx_type: String,
     pub status: String,
+    /// Payment request this transaction settles, if any
+    #[serde(default)]
+    pub request_id: Option<String>,
+    /// Per-sender counter used by receivers to detect replays
+    #[serde(default)]
+    pub sequence: u64,
+    /// Local record of how the payment travelled ("nfc", "ble", "qr"); not signed
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// Directory check of the payee when this device created the payment; not signed
+    #[serde(default)]
+    pub merchant_status: Option<MerchantStatus>,
+    /// User notes, tags and category; local only, never signed or sent
+    #[serde(default)]
+    pub annotations: TxAnnotations,
+}
+
+impl Transaction {
+    /// Canonical encoding of the fields covered by the payer's signature
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        codec::Encoder::new("tx")
+            .str(&self.id)
+            .str(&self.from_wallet_id)
+            .str(&self.to_wallet_id)
+            .str(&self.merchant_name)
+            .u64(self.amount)
+            .str(&self.timestamp)
+            .str(&self.tx_type)
+            .opt_str(self.request_id.as_deref())
+            .u64(self.sequence)
+            .finish()
+    }
+
+    /// Rebuild an unsigned transaction from its canonical encoding
+    pub fn from_signing_bytes(data: &[u8]) -> Result<Self, String> {
+        let mut decoder = codec::Decoder::new(data, "tx")?;
+        let tx = Transaction {
+            id: decoder.str()?,
+            from_wallet_id: decoder.str()?,
+            to_wallet_id: decoder.str()?,
+            merchant_name: decoder.str()?,
+            amount: decoder.u64()?,
+            timestamp: decoder.str()?,
+            tx_type: decoder.str()?,
+            request_id: decoder.opt_str()?,
+            sequence: decoder.u64()?,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
+            signature: String::new(),
+            status: "pending".to_string(),
+        };
+        decoder.finish()?;
+        Ok(tx)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,10 +182,80 @@ pub struct ApiResponse<T> {
     pub timestamp: String,
 }
 
+/// Our side of a secure channel opening, to send to the peer
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecureChannelStart {
+    pub channel_id: String,
+    pub hello: SessionHello,
+}
+
+/// Identity, PIN state and balances, saved in wallet.json
+#[derive(Serialize, Deserialize)]
+struct WalletState {
+    wallet: Wallet,
+    keypair: Option<KeyPair>,
+    mnemonic: Option<String>,
+    pin_hash: Option<String>,
+    failed_pin_attempts: u32,
+    next_sequence: u64,
+    restore_pending: bool,
+    genesis_public_key: Option<String>,
+    key_successions: Vec<KeySuccession>,
+}
+
 pub struct BankingEngine {
     wallet: Wallet,
     keypair: Option<KeyPair>,
-    transactions: Vec<Transaction>,
+    transactions: Ledger,
+    receipts: Vec<PaymentReceipt>,
+    refunds: Vec<Refund>,
+    issued_requests: Vec<PaymentRequest>,
+    incoming_requests: Vec<PaymentRequest>,
+    p2p_sessions: Vec<HandshakeSession>,
+    next_sequence: u64,
+    replay_guard: ReplayGuard,
+    data_dir: Option<PathBuf>,
+    key_exchanges: HashMap<String, KeyExchange>,
+    secure_channels: HashMap<String, SecureSession>,
+    merchant: MerchantState,
+    directory: MerchantDirectory,
+    trusted_issuers: Vec<String>,
+    contacts: ContactBook,
+    mnemonic: Option<String>,
+    pin_hash: Option<String>,
+    failed_pin_attempts: u32,
+    /// Set after a restore until the account history has been reloaded
+    restore_pending: bool,
+    genesis_public_key: Option<String>,
+    key_successions: Vec<KeySuccession>,
+    revocations: RevocationState,
+    budgets: BudgetState,
+    events: Box<dyn EventSink>,
+}
+
+impl Default for BankingEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The deposit `initialize_keys` records for a new wallet, which is not history of its own
+fn is_opening_deposit(tx: &Transaction) -> bool {
+    tx.from_wallet_id == FUNDING_WALLET_ID && tx.tx_type == "deposit"
+}
+
+/// Canonical phrase, wallet ID and first signing key of a recovery phrase
+fn derive_identity(phrase: &str) -> Result<(String, String, KeyPair), String> {
+    let phrase = recovery::normalize_mnemonic(phrase)?;
+    let seed = recovery::seed_from_mnemonic(&phrase)?;
+    let (private_key, public_key) = crypto::keypair_from_seed(&recovery::derive_signing_seed(&seed, 0)?);
+
+    let key_pair = KeyPair {
+        public_key,
+        private_key,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    Ok((phrase, recovery::wallet_id(&seed), key_pair))
 }
 
 impl BankingEngine {
@@ -67,24 +273,633 @@ impl BankingEngine {
                 last_updated: now,
             },
             keypair: None,
-            transactions: Vec::new(),
+            transactions: Ledger::default(),
+            receipts: Vec::new(),
+            refunds: Vec::new(),
+            issued_requests: Vec::new(),
+            incoming_requests: Vec::new(),
+            p2p_sessions: Vec::new(),
+            next_sequence: 0,
+            replay_guard: ReplayGuard::default(),
+            data_dir: None,
+            key_exchanges: HashMap::new(),
+            secure_channels: HashMap::new(),
+            merchant: MerchantState::default(),
+            directory: MerchantDirectory::default(),
+            trusted_issuers: issuer::trusted_issuer_keys(),
+            contacts: ContactBook::default(),
+            mnemonic: None,
+            pin_hash: None,
+            failed_pin_attempts: 0,
+            restore_pending: false,
+            genesis_public_key: None,
+            key_successions: Vec::new(),
+            revocations: RevocationState::default(),
+            budgets: BudgetState::default(),
+            events: Box::new(NoopSink),
+        }
+    }
+
+    /// Point the engine at its data directory and load persisted state
+    pub fn set_data_dir(&mut self, dir: PathBuf) -> Result<(), String> {
+        if let Some(state) = storage::load_json::<WalletState>(&dir.join("wallet.json"))? {
+            self.wallet = state.wallet;
+            self.keypair = state.keypair;
+            self.mnemonic = state.mnemonic;
+            self.pin_hash = state.pin_hash;
+            self.failed_pin_attempts = state.failed_pin_attempts;
+            self.next_sequence = state.next_sequence;
+            self.restore_pending = state.restore_pending;
+            self.genesis_public_key = state.genesis_public_key;
+            self.key_successions = state.key_successions;
+        }
+        let records = storage::load_json_lines(&dir.join(LEDGER_FILE))?;
+        if !records.is_empty() {
+            self.transactions = Ledger::from_log(records);
+        }
+        let receipts = storage::load_json_lines(&dir.join(RECEIPTS_FILE))?;
+        if !receipts.is_empty() {
+            self.receipts = receipts;
+        }
+        if let Some(guard) = storage::load_json(&dir.join("replay_guard.json"))? {
+            self.replay_guard = guard;
+        }
+        if let Some(merchant) = storage::load_json(&dir.join("merchant.json"))? {
+            self.merchant = merchant;
+        }
+        if let Some(directory) = storage::load_json(&dir.join("merchant_directory.json"))? {
+            self.directory = directory;
+        }
+        if let Some(contacts) = storage::load_json(&dir.join("contacts.json"))? {
+            self.contacts = contacts;
+        }
+        if let Some(revocations) = storage::load_json(&dir.join("revocations.json"))? {
+            self.revocations = revocations;
+        }
+        if let Some(budgets) = storage::load_json(&dir.join("budgets.json"))? {
+            self.budgets = budgets;
+        }
+        if let Some(refunds) = storage::load_json(&dir.join("refunds.json"))? {
+            self.refunds = refunds;
+        }
+        if let Some(requests) = storage::load_json(&dir.join("issued_requests.json"))? {
+            self.issued_requests = requests;
+        }
+        if let Some(requests) = storage::load_json(&dir.join("incoming_requests.json"))? {
+            self.incoming_requests = requests;
+        }
+        self.data_dir = Some(dir);
+        Ok(())
+    }
+
+    /// Write one state file; does nothing until a data directory is set
+    fn save_state<T: Serialize>(&self, file_name: &str, value: &T) -> Result<(), String> {
+        match &self.data_dir {
+            Some(dir) => storage::save_json(&dir.join(file_name), value),
+            None => Ok(()),
+        }
+    }
+
+    /// Append the current state of a transaction to the ledger journal
+    fn log_transaction(&self, tx: &Transaction) -> Result<(), String> {
+        match &self.data_dir {
+            Some(dir) => storage::append_json_line(&dir.join(LEDGER_FILE), tx),
+            None => Ok(()),
+        }
+    }
+
+    /// Rewrite the ledger journal from memory, e.g. after the whole ledger was replaced
+    fn save_ledger(&self) -> Result<(), String> {
+        match &self.data_dir {
+            Some(dir) => storage::save_json_lines(&dir.join(LEDGER_FILE), &self.transactions.to_vec()),
+            None => Ok(()),
+        }
+    }
+
+    fn log_receipt(&self, receipt: &PaymentReceipt) -> Result<(), String> {
+        match &self.data_dir {
+            Some(dir) => storage::append_json_line(&dir.join(RECEIPTS_FILE), receipt),
+            None => Ok(()),
+        }
+    }
+
+    fn save_receipts(&self) -> Result<(), String> {
+        match &self.data_dir {
+            Some(dir) => storage::save_json_lines(&dir.join(RECEIPTS_FILE), &self.receipts),
+            None => Ok(()),
+        }
+    }
+
+    /// Journal `tx` and save `wallet`, then apply both; a failed write leaves the
+    /// engine as it was
+    fn commit_wallet_change(&mut self, wallet: Wallet, tx: &Transaction) -> Result<(), String> {
+        self.log_transaction(tx)?;
+        let mut state = self.wallet_state();
+        state.wallet = wallet.clone();
+        self.save_state("wallet.json", &state)?;
+
+        self.wallet = wallet;
+        self.transactions.push(tx.clone());
+        Ok(())
+    }
+
+    /// Add a transaction to the ledger, journaling it first
+    fn record_transaction(&mut self, tx: Transaction) -> Result<(), String> {
+        self.log_transaction(&tx)?;
+        self.transactions.push(tx);
+        Ok(())
+    }
+
+    /// Where a user-named export or backup file lives; never outside the data directory
+    pub fn export_path(&self, file_name: &str) -> Result<PathBuf, String> {
+        let dir = self.data_dir.as_ref().ok_or("Storage not configured")?;
+        storage::export_path(dir, file_name)
+    }
+
+    fn wallet_state(&self) -> WalletState {
+        WalletState {
+            wallet: self.wallet.clone(),
+            keypair: self.keypair.clone(),
+            mnemonic: self.mnemonic.clone(),
+            pin_hash: self.pin_hash.clone(),
+            failed_pin_attempts: self.failed_pin_attempts,
+            next_sequence: self.next_sequence,
+            restore_pending: self.restore_pending,
+            genesis_public_key: self.genesis_public_key.clone(),
+            key_successions: self.key_successions.clone(),
+        }
+    }
+
+    fn save_wallet(&self) -> Result<(), String> {
+        self.save_state("wallet.json", &self.wallet_state())
+    }
+
+    fn save_replay_guard(&self) -> Result<(), String> {
+        self.save_state("replay_guard.json", &self.replay_guard)
+    }
+
+    fn save_merchant(&self) -> Result<(), String> {
+        self.save_state("merchant.json", &self.merchant)
+    }
+
+    fn save_directory(&self) -> Result<(), String> {
+        self.save_state("merchant_directory.json", &self.directory)
+    }
+
+    fn save_contacts(&self) -> Result<(), String> {
+        self.save_state("contacts.json", &self.contacts)
+    }
+
+    fn save_revocations(&self) -> Result<(), String> {
+        self.save_state("revocations.json", &self.revocations)
+    }
+
+    fn save_budgets(&self) -> Result<(), String> {
+        self.save_state("budgets.json", &self.budgets)
+    }
+
+    fn save_refunds(&self) -> Result<(), String> {
+        self.save_state("refunds.json", &self.refunds)
+    }
+
+    fn save_issued_requests(&self) -> Result<(), String> {
+        self.save_state("issued_requests.json", &self.issued_requests)
+    }
+
+    fn save_incoming_requests(&self) -> Result<(), String> {
+        self.save_state("incoming_requests.json", &self.incoming_requests)
+    }
+
+    /// Where state change events go; the app forwards them to the webview
+    pub fn set_event_sink(&mut self, sink: Box<dyn EventSink>) {
+        self.events = sink;
+    }
+
+    fn notify(&self, event: WalletEvent) {
+        self.events.emit(&event);
+    }
+
+    fn notify_balance(&self) {
+        self.notify(WalletEvent::BalanceChanged(self.wallet.clone()));
+    }
+
+    /// Change a transaction's status and report the change
+    fn set_status(&mut self, tx_id: &str, status: &str) -> Result<Transaction, String> {
+        let tx = self.transactions.get_mut(tx_id)
+            .ok_or("Transaction not found")?;
+        let old_status = std::mem::replace(&mut tx.status, status.to_string());
+        let updated = tx.clone();
+        self.log_transaction(&updated)?;
+
+        if old_status != status {
+            self.notify(WalletEvent::TransactionStatusChanged(Box::new(StatusChange {
+                tx_id: updated.id.clone(),
+                old_status,
+                new_status: status.to_string(),
+                transaction: updated.clone(),
+            })));
+        }
+        Ok(updated)
+    }
+
+    fn notify_sync(&self, operation: &str, processed: usize, total: usize) {
+        self.notify(WalletEvent::SyncProgress(SyncProgress {
+            operation: operation.to_string(),
+            processed,
+            total,
+            done: processed == total,
+        }));
+    }
+
+    /// Remember a peer met in a completed exchange (trust on first use)
+    fn observe_peer(&mut self, wallet_id: &str, public_key: &str) -> Result<(), String> {
+        if wallet_id == self.wallet.id {
+            return Ok(());
+        }
+        let warning = self.contacts.observe(wallet_id, public_key);
+        self.save_contacts()?;
+        if let Some(warning) = warning {
+            self.notify(WalletEvent::ContactKeyChanged(warning));
+        }
+        Ok(())
+    }
+
+    /// Trust an additional issuer key, e.g. a test issuer
+    pub fn add_trusted_issuer(&mut self, public_key: &str) {
+        let key = public_key.to_lowercase();
+        if !self.trusted_issuers.contains(&key) {
+            self.trusted_issuers.push(key);
         }
     }
 
+    /// Create the wallet identity from a fresh recovery phrase
     pub fn initialize_keys(&mut self) -> Result<KeyPair, String> {
-        let private_key_seed = Uuid::new_v4().to_string();
-        let private_key = hex::encode(Sha256::digest(private_key_seed.as_bytes()));
-        let public_key_data = format!("pub_{}", private_key);
-        let public_key = hex::encode(Sha256::digest(public_key_data.as_bytes()));
+        if self.keypair.is_some() {
+            return Err("Keys already initialized".to_string());
+        }
+
+        let (phrase, wallet_id, key_pair) = derive_identity(&recovery::generate_mnemonic())?;
+        self.adopt_identity(phrase, wallet_id, key_pair.clone());
+        self.record_opening_deposit()?;
+        self.save_wallet()?;
+        Ok(key_pair)
+    }
+
+    /// Record the starting funds of a new wallet so the ledger adds up to its balance
+    fn record_opening_deposit(&mut self) -> Result<(), String> {
+        let now = Utc::now().to_rfc3339();
+        self.record_transaction(Transaction {
+            id: Uuid::new_v4().to_string(),
+            from_wallet_id: FUNDING_WALLET_ID.to_string(),
+            to_wallet_id: self.wallet.id.clone(),
+            merchant_name: "Opening Deposit".to_string(),
+            amount: self.wallet.total_balance,
+            timestamp: now,
+            signature: String::new(),
+            tx_type: "deposit".to_string(),
+            status: "confirmed".to_string(),
+            request_id: None,
+            sequence: 0,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
+        })
+    }
+
+    fn adopt_identity(&mut self, phrase: String, wallet_id: String, key_pair: KeyPair) {
+        self.wallet.id = wallet_id;
+        self.genesis_public_key = Some(key_pair.public_key.clone());
+        self.key_successions.clear();
+        self.keypair = Some(key_pair);
+        self.mnemonic = Some(phrase);
+    }
 
-        let key_pair = KeyPair {
+    fn current_generation(&self) -> u32 {
+        self.key_successions.last().map(|r| r.generation).unwrap_or(0)
+    }
+
+    /// Every public key this wallet has signed with, oldest first
+    fn own_public_keys(&self) -> Vec<String> {
+        match (&self.genesis_public_key, &self.keypair) {
+            (Some(genesis), _) => succession::key_history(genesis, &self.key_successions),
+            (None, Some(keypair)) => vec![keypair.public_key.clone()],
+            (None, None) => Vec::new(),
+        }
+    }
+
+    /// Own key that signed `tx`, if any. A retired key only vouches for the sequences
+    /// it signed before its succession, so a leaked old key cannot sign new payments.
+    fn own_key_for(&self, tx: &Transaction) -> Result<Option<String>, String> {
+        let data = tx.signing_bytes();
+        for key in self.own_public_keys() {
+            let retired = self.key_successions.iter().find(|r| r.old_public_key == key);
+            if retired.is_some_and(|r| tx.sequence > r.last_sequence) {
+                continue;
+            }
+            if crypto::verify(&key, &data, &tx.signature)? {
+                return Ok(Some(key));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Replace the signing key with the next one, recording a succession signed by both
+    pub fn rotate_keys(&mut self, pin: &str) -> Result<KeySuccession, String> {
+        self.check_pin(pin)?;
+        let old = self.keypair.clone().ok_or("Keys not initialized")?;
+        let generation = self.current_generation() + 1;
+
+        // Keys derived from the recovery phrase stay recoverable after rotation
+        let (private_key, public_key) = match &self.mnemonic {
+            Some(phrase) => {
+                let seed = recovery::seed_from_mnemonic(phrase)?;
+                crypto::keypair_from_seed(&recovery::derive_signing_seed(&seed, generation)?)
+            }
+            None => crypto::generate_keypair(),
+        };
+
+        let now = Utc::now().to_rfc3339();
+        let record = KeySuccession::sign(
+            &self.wallet.id,
+            generation,
+            &old.private_key,
+            &old.public_key,
+            &private_key,
+            &public_key,
+            &now,
+            self.next_sequence,
+        )?;
+
+        if self.genesis_public_key.is_none() {
+            self.genesis_public_key = Some(old.public_key);
+        }
+        self.key_successions.push(record.clone());
+        self.keypair = Some(KeyPair {
+            public_key,
+            private_key,
+            created_at: now,
+        });
+        // Handshakes started with the old identity cannot complete anymore
+        self.key_exchanges.clear();
+        self.save_wallet()?;
+
+        Ok(record)
+    }
+
+    pub fn get_key_chain(&self) -> Result<KeyChain, String> {
+        let current_public_key = self.get_public_key()?;
+        Ok(KeyChain {
+            wallet_id: self.wallet.id.clone(),
+            genesis_public_key: self.genesis_public_key.clone().unwrap_or_else(|| current_public_key.clone()),
+            current_public_key,
+            successions: self.key_successions.clone(),
+        })
+    }
+
+    /// Set or change the PIN protecting the recovery phrase export
+    pub fn set_pin(&mut self, pin: &str, current_pin: Option<&str>) -> Result<(), String> {
+        if self.pin_hash.is_some() {
+            self.check_pin(current_pin.ok_or("Current PIN required")?)?;
+        }
+        self.pin_hash = Some(recovery::hash_pin(pin)?);
+        self.save_wallet()
+    }
+
+    fn check_pin(&mut self, pin: &str) -> Result<(), String> {
+        let pin_hash = self.pin_hash.as_ref().ok_or("PIN not set")?;
+
+        if self.failed_pin_attempts >= recovery::MAX_PIN_ATTEMPTS {
+            return Err("Too many wrong PIN attempts".to_string());
+        }
+
+        if !recovery::verify_pin(pin, pin_hash)? {
+            // Saved so that restarting the app does not reset the counter
+            self.failed_pin_attempts += 1;
+            self.save_wallet()?;
+            return Err("Wrong PIN".to_string());
+        }
+
+        if self.failed_pin_attempts > 0 {
+            self.failed_pin_attempts = 0;
+            self.save_wallet()?;
+        }
+        Ok(())
+    }
+
+    /// Reveal the recovery phrase after checking the PIN
+    pub fn export_seed_phrase(&mut self, pin: &str) -> Result<String, String> {
+        self.check_pin(pin)?;
+        self.mnemonic.clone().ok_or("No recovery phrase for this wallet".to_string())
+    }
+
+    /// Rebuild the wallet identity from its recovery phrase. The ledger starts empty and
+    /// outgoing payments stay blocked until `apply_account_snapshot` reloads the history,
+    /// so sequence numbers already used are never reused. Replacing a wallet protected
+    /// by a PIN requires that PIN.
+    pub fn restore_from_mnemonic(&mut self, phrase: &str, pin: Option<&str>) -> Result<Wallet, String> {
+        let (phrase, wallet_id, key_pair) = derive_identity(phrase)?;
+        if self.pin_hash.is_some() {
+            self.check_pin(pin.ok_or("Current PIN required")?)?;
+        }
+
+        self.adopt_identity(phrase, wallet_id, key_pair);
+
+        let now = Utc::now().to_rfc3339();
+        self.wallet.online_balance = 0;
+        self.wallet.offline_balance = 0;
+        self.wallet.total_balance = 0;
+        self.wallet.created_at = now.clone();
+        self.wallet.last_updated = now;
+
+        self.transactions.clear();
+        self.receipts.clear();
+        self.refunds.clear();
+        self.issued_requests.clear();
+        self.incoming_requests.clear();
+        self.p2p_sessions.clear();
+        self.key_exchanges.clear();
+        self.secure_channels.clear();
+        self.next_sequence = 0;
+        self.pin_hash = None;
+        self.failed_pin_attempts = 0;
+        self.restore_pending = true;
+
+        self.save_wallet()?;
+        self.save_ledger()?;
+        self.save_receipts()?;
+        self.save_refunds()?;
+        self.save_issued_requests()?;
+        self.save_incoming_requests()?;
+        self.notify_balance();
+        Ok(self.wallet.clone())
+    }
+
+    /// After a restore, move to the latest rotated key recorded by the server
+    fn adopt_key_chain(&mut self, records: Vec<KeySuccession>) -> Result<(), String> {
+        let genesis = self.genesis_public_key.clone().ok_or("Keys not initialized")?;
+        let keys = succession::follow_chain(&self.wallet.id, &genesis, &records)?;
+        let head = keys.last().ok_or("Empty succession chain")?;
+        let generation = records.last().map(|r| r.generation).unwrap_or(0);
+
+        let phrase = self.mnemonic.as_ref().ok_or("No recovery phrase for this wallet")?;
+        let seed = recovery::seed_from_mnemonic(phrase)?;
+        let (private_key, public_key) = crypto::keypair_from_seed(&recovery::derive_signing_seed(&seed, generation)?);
+        if &public_key != head {
+            return Err("Recovery phrase does not match the wallet's current key".to_string());
+        }
+
+        self.key_successions = records;
+        self.keypair = Some(KeyPair {
             public_key,
             private_key,
             created_at: Utc::now().to_rfc3339(),
+        });
+        Ok(())
+    }
+
+    /// Encrypted snapshot of the whole wallet, to move it to another device; it carries
+    /// the signing key, so the PIN is checked first
+    pub fn export_backup(&mut self, pin: &str, password: &str) -> Result<Vec<u8>, String> {
+        self.check_pin(pin)?;
+        let keypair = self.keypair.clone().ok_or("Keys not initialized")?;
+
+        let backup = WalletBackup {
+            created_at: Utc::now().to_rfc3339(),
+            wallet: self.wallet.clone(),
+            keypair,
+            mnemonic: self.mnemonic.clone(),
+            pin_hash: self.pin_hash.clone(),
+            transactions: self.transactions.to_vec(),
+            receipts: self.receipts.clone(),
+            refunds: self.refunds.clone(),
+            issued_requests: self.issued_requests.clone(),
+            incoming_requests: self.incoming_requests.clone(),
+            next_sequence: self.next_sequence,
+            replay_guard: self.replay_guard.clone(),
+            contacts: self.contacts.clone(),
+            merchant: self.merchant.clone(),
+            genesis_public_key: self.genesis_public_key.clone(),
+            key_successions: self.key_successions.clone(),
+            budgets: self.budgets.clone(),
         };
 
-        self.keypair = Some(key_pair.clone());
-        Ok(key_pair)
+        backup::encrypt(&backup, password)
+    }
+
+    /// Restore an encrypted backup. Outgoing payments stay blocked until the server
+    /// confirms the account state, since the backup may predate later offline spending.
+    pub fn import_backup(&mut self, data: &[u8], password: &str) -> Result<Wallet, String> {
+        let backup = backup::decrypt(data, password)?;
+
+        if self.keypair.is_some() {
+            if backup.wallet.id == self.wallet.id {
+                if backup.next_sequence < self.next_sequence {
+                    return Err("Backup is older than this wallet's current state".to_string());
+                }
+            } else if self.transactions.iter().any(|t| !is_opening_deposit(t)) {
+                return Err("This device already holds another wallet with history".to_string());
+            }
+        }
+
+        self.wallet = backup.wallet;
+        self.keypair = Some(backup.keypair);
+        self.mnemonic = backup.mnemonic;
+        self.pin_hash = backup.pin_hash;
+        self.failed_pin_attempts = 0;
+        self.transactions = Ledger::from(backup.transactions);
+        self.receipts = backup.receipts;
+        self.refunds = backup.refunds;
+        self.issued_requests = backup.issued_requests;
+        self.incoming_requests = backup.incoming_requests;
+        self.next_sequence = backup.next_sequence;
+        self.replay_guard = backup.replay_guard;
+        self.contacts = backup.contacts;
+        self.merchant = backup.merchant;
+        self.genesis_public_key = backup.genesis_public_key;
+        self.key_successions = backup.key_successions;
+        self.budgets = backup.budgets;
+        self.p2p_sessions.clear();
+        self.key_exchanges.clear();
+        self.secure_channels.clear();
+        self.restore_pending = true;
+
+        self.save_wallet()?;
+        self.save_ledger()?;
+        self.save_replay_guard()?;
+        self.save_contacts()?;
+        self.save_merchant()?;
+        self.save_budgets()?;
+        self.save_receipts()?;
+        self.save_refunds()?;
+        self.save_issued_requests()?;
+        self.save_incoming_requests()?;
+
+        self.notify_balance();
+        Ok(self.wallet.clone())
+    }
+
+    /// Load history and balances from the settlement server after a restore or a
+    /// backup import; the server's balances replace the local ones. Only accepted
+    /// while a restore is pending, and only when signed by a trusted issuer.
+    pub fn apply_account_snapshot(&mut self, snapshot: AccountSnapshot) -> Result<Wallet, String> {
+        if !self.restore_pending {
+            return Err("No restore in progress".to_string());
+        }
+        if snapshot.wallet_id != self.wallet.id {
+            return Err("Snapshot belongs to another wallet".to_string());
+        }
+        if !snapshot.verify(&self.trusted_issuers)? {
+            return Err("Snapshot is not signed by a trusted issuer".to_string());
+        }
+        let total_balance = snapshot.online_balance.checked_add(snapshot.offline_balance)
+            .ok_or("Snapshot balance overflow")?;
+
+        if !snapshot.key_successions.is_empty() {
+            self.adopt_key_chain(snapshot.key_successions.clone())?;
+        }
+
+        for tx in snapshot.transactions.iter().filter(|t| t.from_wallet_id == self.wallet.id) {
+            // Refunds and vault moves are not signed by the payer key
+            if tx.tx_type != "online" && tx.tx_type != "offline" {
+                continue;
+            }
+            if self.own_key_for(tx)?.is_none() {
+                return Err(format!("Transaction {} is not signed by this wallet", tx.id));
+            }
+        }
+
+        let highest_sent = snapshot.transactions.iter()
+            .filter(|t| t.from_wallet_id == self.wallet.id)
+            .map(|t| t.sequence)
+            .max()
+            .unwrap_or(0);
+
+        self.next_sequence = self.next_sequence.max(snapshot.last_sequence).max(highest_sent);
+
+        let total = snapshot.transactions.len();
+        self.notify_sync("account_snapshot", 0, total);
+        for (i, tx) in snapshot.transactions.into_iter().enumerate() {
+            if self.transactions.contains(&tx.id) {
+                self.set_status(&tx.id, &tx.status)?;
+            } else {
+                self.record_transaction(tx)?;
+            }
+            if (i + 1) % SYNC_PROGRESS_STEP == 0 && i + 1 < total {
+                self.notify_sync("account_snapshot", i + 1, total);
+            }
+        }
+        self.wallet.online_balance = snapshot.online_balance;
+        self.wallet.offline_balance = snapshot.offline_balance;
+        self.wallet.total_balance = total_balance;
+        self.wallet.last_updated = Utc::now().to_rfc3339();
+        self.restore_pending = false;
+        self.save_wallet()?;
+
+        if total > 0 {
+            self.notify_sync("account_snapshot", total, total);
+        }
+        self.notify_balance();
+        Ok(self.wallet.clone())
     }
 
     pub fn get_wallet(&self) -> Wallet {
@@ -99,7 +914,7 @@ impl BankingEngine {
     }
 
     pub fn transfer_to_vault(&mut self, amount: u64) -> Result<Wallet, String> {
-        if amount <= 0 {
+        if amount == 0 {
             return Err("Amount must be positive".to_string());
         }
 
@@ -107,11 +922,7 @@ impl BankingEngine {
             return Err("Insufficient online balance".to_string());
         }
 
-        self.wallet.online_balance -= amount;
-        self.wallet.offline_balance += amount;
-        self.wallet.total_balance =
-            self.wallet.online_balance + self.wallet.offline_balance;
-        self.wallet.last_updated = Utc::now().to_rfc3339();
+        let wallet = self.wallet.debited(true, amount)?.credited(false, amount)?;
 
         let tx = Transaction {
             id: Uuid::new_v4().to_string(),
@@ -123,14 +934,21 @@ impl BankingEngine {
             signature: String::new(),
             tx_type: "transfer".to_string(),
             status: "confirmed".to_string(),
+            request_id: None,
+            sequence: 0,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
         };
 
-        self.transactions.push(tx);
+        self.commit_wallet_change(wallet, &tx)?;
+        self.notify(WalletEvent::TransactionCreated(Box::new(tx)));
+        self.notify_balance();
         Ok(self.wallet.clone())
     }
 
     pub fn transfer_from_vault(&mut self, amount: u64) -> Result<Wallet, String> {
-        if amount <= 0 {
+        if amount == 0 {
             return Err("Amount must be positive".to_string());
         }
 
@@ -138,11 +956,7 @@ impl BankingEngine {
             return Err("Insufficient vault balance".to_string());
         }
 
-        self.wallet.offline_balance -= amount;
-        self.wallet.online_balance += amount;
-        self.wallet.total_balance =
-            self.wallet.online_balance + self.wallet.offline_balance;
-        self.wallet.last_updated = Utc::now().to_rfc3339();
+        let wallet = self.wallet.debited(false, amount)?.credited(true, amount)?;
 
         let tx = Transaction {
             id: Uuid::new_v4().to_string(),
@@ -154,9 +968,16 @@ impl BankingEngine {
             signature: String::new(),
             tx_type: "transfer".to_string(),
             status: "confirmed".to_string(),
+            request_id: None,
+            sequence: 0,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
         };
 
-        self.transactions.push(tx);
+        self.commit_wallet_change(wallet, &tx)?;
+        self.notify(WalletEvent::TransactionCreated(Box::new(tx)));
+        self.notify_balance();
         Ok(self.wallet.clone())
     }
 
@@ -165,111 +986,105 @@ impl BankingEngine {
         to_wallet_id: String,
         merchant_name: String,
         amount: u64,
+        category: Option<String>,
+    ) -> Result<Transaction, String> {
+        self.create_signed_transaction(to_wallet_id, merchant_name, amount, "offline", None, category, None)
+    }
+
+    /// Create online transaction (server validated)
+    pub fn create_online_transaction(
+        &mut self,
+        to_wallet_id: String,
+        merchant_name: String,
+        amount: u64,
+        category: Option<String>,
     ) -> Result<Transaction, String> {
-        if amount <= 0 {
+        self.create_signed_transaction(to_wallet_id, merchant_name, amount, "online", None, category, None)
+    }
+
+    /// Debit the balance matching `tx_type` and record a signed pending payment,
+    /// filed under `category` for the budgets and sent over `channel` ("nfc", "ble")
+    #[allow(clippy::too_many_arguments)]
+    fn create_signed_transaction(
+        &mut self,
+        to_wallet_id: String,
+        merchant_name: String,
+        amount: u64,
+        tx_type: &str,
+        request_id: Option<String>,
+        category: Option<String>,
+        channel: Option<String>,
+    ) -> Result<Transaction, String> {
+        let annotations = annotations::build(None, Vec::new(), category)?;
+
+        if amount == 0 {
             return Err("Amount must be positive".to_string());
         }
 
-        if amount > self.wallet.offline_balance {
-            return Err("Insufficient offline balance".to_string());
+        if self.restore_pending {
+            return Err("Wallet restore not finished: sync the account history first".to_string());
         }
 
-        let keypair = self.keypair.as_ref()
-            .ok_or("Keys not initialized")?;
-
-        let tx_id = Uuid::new_v4().to_string();
-        let timestamp = Utc::now().to_rfc3339();
+        let available = match tx_type {
+            "offline" => self.wallet.offline_balance,
+            "online" => self.wallet.online_balance,
+            _ => return Err(format!("Unsupported transaction type: {}", tx_type)),
+        };
 
-        let tx_data = format!(
-            "{}|{}|{}|{}|{}",
-            tx_id, self.wallet.id, to_wallet_id, amount, timestamp
-        );
+        if amount > available {
+            return Err(format!("Insufficient {} balance", tx_type));
+        }
 
-        let signature = self.sign_data(&tx_data, keypair)?;
+        let merchant_check = self.check_merchant(&to_wallet_id, None, &merchant_name)?;
 
-        self.wallet.offline_balance -= amount;
-        self.wallet.total_balance =
-            self.wallet.online_balance + self.wallet.offline_balance;
-        self.wallet.last_updated = Utc::now().to_rfc3339();
+        let keypair = self.keypair.as_ref()
+            .ok_or("Keys not initialized")?;
 
-        let transaction = Transaction {
-            id: tx_id,
+        let mut transaction = Transaction {
+            id: Uuid::new_v4().to_string(),
             from_wallet_id: self.wallet.id.clone(),
             to_wallet_id,
             merchant_name,
             amount,
-            timestamp,
-            signature,
-            tx_type: "offline".to_string(),
-            status: "pending".to_string(),
-        };
-
-        self.transactions.push(transaction.clone());
-        Ok(transaction)
-    }
-
-    /// Create online transaction (server validated)
-    pub fn create_online_transaction(
-        &mut self,
-        to_wallet_id: String,
-        merchant_name: String,
-        amount: u64,
-    ) -> Result<Transaction, String> {
-        if amount <= 0 {
-            return Err("Amount must be positive".to_string());
-        }
-
-        if amount > self.wallet.online_balance {
-            return Err("Insufficient online balance".to_string());
-        }
-
-        let keypair = self.keypair.as_ref()
-            .ok_or("Keys not initialized")?;
-
-        let tx_id = Uuid::new_v4().to_string();
-        let timestamp = Utc::now().to_rfc3339();
-
-        let tx_data = format!(
-            "{}|{}|{}|{}|{}",
-            tx_id, self.wallet.id, to_wallet_id, amount, timestamp
-        );
-
-        let signature = self.sign_data(&tx_data, keypair)?;
-
-        self.wallet.online_balance -= amount;
-        self.wallet.total_balance =
-            self.wallet.online_balance + self.wallet.offline_balance;
-        self.wallet.last_updated = Utc::now().to_rfc3339();
-
-        let transaction = Transaction {
-            id: tx_id,
-            from_wallet_id: self.wallet.id.clone(),
-            to_wallet_id,
-            merchant_name,
-            amount,
-            timestamp,
-            signature,
-            tx_type: "online".to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            signature: String::new(),
+            tx_type: tx_type.to_string(),
             status: "pending".to_string(),
+            request_id,
+            sequence: self.next_sequence + 1,
+            channel,
+            merchant_status: Some(merchant_check.status),
+            annotations,
         };
+        transaction.signature = self.sign_data(&transaction.signing_bytes(), keypair)?;
 
+        // Saved before the engine changes, so a failed write neither debits the balance
+        // nor uses up the sequence number
+        let wallet = self.wallet.debited(tx_type == "online", amount)?;
+        self.log_transaction(&transaction)?;
+        let mut state = self.wallet_state();
+        state.wallet = wallet.clone();
+        state.next_sequence = transaction.sequence;
+        self.save_state("wallet.json", &state)?;
+
+        self.wallet = wallet;
+        self.next_sequence = transaction.sequence;
         self.transactions.push(transaction.clone());
+        self.notify(WalletEvent::TransactionCreated(Box::new(transaction.clone())));
+        self.notify_balance();
+        if transaction.annotations.category.is_some() {
+            self.check_budgets();
+        }
         Ok(transaction)
     }
 
     /// Confirm transaction after server validation
     pub fn confirm_transaction(&mut self, tx_id: String) -> Result<Transaction, String> {
-        let tx = self.transactions.iter_mut()
-            .find(|t| t.id == tx_id)
-            .ok_or("Transaction not found")?;
-
-        tx.status = "confirmed".to_string();
-        Ok(tx.clone())
+        self.set_status(&tx_id, "confirmed")
     }
 
     pub fn cancel_transaction(&mut self, tx_id: String) -> Result<Wallet, String> {
-        let tx = self.transactions.iter_mut()
-            .find(|t| t.id == tx_id)
+        let tx = self.transactions.get(&tx_id)
             .ok_or("Transaction not found")?;
 
         if tx.status == "confirmed" {
@@ -287,12 +1102,108 @@ impl BankingEngine {
             self.wallet.online_balance + self.wallet.offline_balance;
         self.wallet.last_updated = Utc::now().to_rfc3339();
 
-        tx.status = "cancelled".to_string();
+        self.set_status(&tx_id, "cancelled")?;
+        self.save_wallet()?;
+        self.notify_balance();
         Ok(self.wallet.clone())
     }
 
     pub fn get_transactions(&self) -> Vec<Transaction> {
-        self.transactions.clone()
+        self.transactions.to_vec()
+    }
+
+    /// One page of history matching `query`, newest first
+    pub fn query_transactions(&self, query: &TransactionQuery) -> Result<TransactionPage, String> {
+        self.transactions.query(query, &self.wallet.id)
+    }
+
+    /// Replace the local notes, tags and category of a transaction
+    pub fn annotate_transaction(
+        &mut self,
+        tx_id: &str,
+        note: Option<String>,
+        tags: Vec<String>,
+        category: Option<String>,
+    ) -> Result<Transaction, String> {
+        let annotations = annotations::build(note, tags, category)?;
+        let mut annotated = self.transactions.get(tx_id)
+            .cloned()
+            .ok_or("Transaction not found")?;
+        annotated.annotations = annotations;
+
+        // Journaled first: budgets are computed from categories, so they must survive a restart
+        self.log_transaction(&annotated)?;
+        if let Some(tx) = self.transactions.get_mut(tx_id) {
+            tx.annotations = annotated.annotations.clone();
+        }
+
+        self.check_budgets();
+        Ok(annotated)
+    }
+
+    /// Default categories followed by the ones the user made up
+    pub fn get_categories(&self) -> Vec<String> {
+        let mut categories: Vec<String> = annotations::DEFAULT_CATEGORIES.iter()
+            .map(|c| c.to_string())
+            .collect();
+        for tx in self.transactions.iter() {
+            if let Some(category) = &tx.annotations.category {
+                if !categories.contains(category) {
+                    categories.push(category.clone());
+                }
+            }
+        }
+        categories
+    }
+
+    fn spent_this_month(&self) -> HashMap<String, u64> {
+        let start = statement::month_bounds(&budgets::current_month())
+            .map(|(start, _)| start.timestamp_millis())
+            .ok();
+        budgets::spent_by_category(self.transactions.between(start, None).into_iter(), &self.wallet.id)
+    }
+
+    /// Report budget thresholds crossed since the last check
+    fn check_budgets(&mut self) {
+        let spent = self.spent_this_month();
+        let alerts = self.budgets.new_alerts(&budgets::current_month(), &spent);
+        if alerts.is_empty() {
+            return;
+        }
+        for alert in alerts {
+            self.notify(WalletEvent::BudgetAlert(alert));
+        }
+        // Alerts are a side effect of the caller's change, which has already been applied;
+        // if the sent alerts cannot be saved, they may just be repeated after a restart
+        let _ = self.save_budgets();
+    }
+
+    pub fn set_budget(&mut self, category: &str, monthly_limit: u64) -> Result<Budget, String> {
+        let budget = self.budgets.set(category, monthly_limit)?;
+        self.save_budgets()?;
+        self.check_budgets();
+        Ok(budget)
+    }
+
+    pub fn remove_budget(&mut self, category: &str) -> Result<bool, String> {
+        let removed = self.budgets.remove(category);
+        self.save_budgets()?;
+        Ok(removed)
+    }
+
+    pub fn get_budgets(&self) -> Vec<Budget> {
+        self.budgets.budgets()
+    }
+
+    /// Consumption of every budget for the current month
+    pub fn budget_status(&self) -> Vec<BudgetStatus> {
+        self.budgets.status(&budgets::current_month(), &self.spent_this_month())
+    }
+
+    /// Where a budget would stand after paying `amount` in `category`; `None` when the
+    /// category has no budget
+    pub fn preview_budget(&self, category: &str, amount: u64) -> Option<BudgetStatus> {
+        self.budgets.preview(&budgets::current_month(), &self.spent_this_month(), category, amount)
     }
 
     pub fn get_transactions_by_type(&self, tx_type: &str) -> Vec<Transaction> {
@@ -310,86 +1221,2468 @@ impl BankingEngine {
     }
 
     /// Sign data with private key
-    fn sign_data(&self, data: &str, keypair: &KeyPair) -> Result<String, String> {
-        let combined = format!("{}|{}", data, keypair.private_key);
-        let hash = Sha256::digest(combined.as_bytes());
-        Ok(hex::encode(hash))
+    fn sign_data(&self, data: &[u8], keypair: &KeyPair) -> Result<String, String> {
+        crypto::sign(&keypair.private_key, data)
     }
 
-    pub fn verify_signature(
-        &self,
-        data: &str,
-        signature: &str,
-    ) -> Result<bool, String> {
+    /// Whether `tx` carries a valid signature by one of this wallet's keys
+    pub fn verify_signature(&self, tx: &Transaction) -> Result<bool, String> {
+        if self.keypair.is_none() {
+            return Err("Keys not initialized".to_string());
+        }
+
+        // Signatures made before a key rotation stay valid
+        Ok(self.own_key_for(tx)?.is_some())
+    }
+
+    /// Accept a payment signed by another wallet and countersign a receipt for it
+    pub fn receive_payment(
+        &mut self,
+        tx: Transaction,
+        sender_public_key: String,
+        channel: Option<String>,
+    ) -> Result<PaymentReceipt, String> {
         let keypair = self.keypair.as_ref()
             .ok_or("Keys not initialized")?;
 
-        let combined = format!("{}|{}", data, keypair.private_key);
-        let expected_hash = Sha256::digest(combined.as_bytes());
-        let expected_signature = hex::encode(expected_hash);
+        if tx.to_wallet_id != self.wallet.id {
+            return Err("Payment is addressed to another wallet".to_string());
+        }
+
+        if tx.amount == 0 {
+            return Err("Amount must be positive".to_string());
+        }
+
+        if self.transactions.contains(&tx.id) {
+            return Err("Transaction already recorded".to_string());
+        }
+
+        if !crypto::verify(&sender_public_key, &tx.signing_bytes(), &tx.signature)? {
+            return Err("Invalid payer signature".to_string());
+        }
+
+        self.check_payer_key(&tx.from_wallet_id, &sender_public_key)?;
+
+        if let Some(entry) = self.revocations.find(&tx.from_wallet_id, &sender_public_key) {
+            return Err(format!("Payer wallet has been revoked: {}", entry.reason));
+        }
+
+        self.replay_guard.check(&tx.id, &tx.from_wallet_id, tx.sequence)?;
+
+        if let Some(request_id) = &tx.request_id {
+            let request = self.issued_requests.iter()
+                .find(|r| &r.id == request_id)
+                .ok_or("Unknown payment request")?;
+
+            if request.status != "open" {
+                return Err("Payment request already paid".to_string());
+            }
+            if request.is_expired() {
+                return Err("Payment request expired".to_string());
+            }
+            if request.amount != tx.amount {
+                return Err("Amount does not match payment request".to_string());
+            }
+        }
+
+        let receipt = PaymentReceipt::issue(
+            &tx,
+            &sender_public_key,
+            &keypair.public_key,
+            &keypair.private_key,
+        )?;
+
+        // Stage every change and save it before touching the engine, so a failed write
+        // leaves neither a credited balance nor a half-recorded payment behind.
+        // Received funds stay on the channel they arrived through until settlement.
+        let wallet = self.wallet.credited(tx.tx_type == "online", tx.amount)?;
+
+        let mut replay_guard = self.replay_guard.clone();
+        replay_guard.record(&tx.id, &tx.from_wallet_id, tx.sequence);
+
+        let mut received = tx;
+        received.status = "confirmed".to_string();
+        received.channel = channel;
+        received.merchant_status = None;
+        received.annotations = TxAnnotations::default();
+
+        let mut merchant = None;
+        if self.merchant.open_session().is_some() {
+            let mut staged = self.merchant.clone();
+            if let Some(session) = staged.open_session_mut() {
+                session.entries.push(TillEntry {
+                    tx_id: received.id.clone(),
+                    amount: received.amount,
+                    channel: received.channel.clone().unwrap_or_else(|| "unknown".to_string()),
+                    tx_type: received.tx_type.clone(),
+                    received_at: Utc::now().to_rfc3339(),
+                    settled: received.tx_type == "online",
+                });
+            }
+            merchant = Some(staged);
+        }
+
+        let mut issued_requests = None;
+        if let Some(request_id) = &received.request_id {
+            let mut staged = self.issued_requests.clone();
+            if let Some(request) = staged.iter_mut().find(|r| &r.id == request_id) {
+                request.status = "paid".to_string();
+            }
+            issued_requests = Some(staged);
+        }
+
+        // A key change cannot show up here: `check_payer_key` has refused it already
+        let mut contacts = None;
+        if received.from_wallet_id != self.wallet.id {
+            let mut staged = self.contacts.clone();
+            staged.observe(&received.from_wallet_id, &sender_public_key);
+            contacts = Some(staged);
+        }
+
+        if let Some(merchant) = &merchant {
+            self.save_state("merchant.json", merchant)?;
+        }
+        if let Some(contacts) = &contacts {
+            self.save_state("contacts.json", contacts)?;
+        }
+        if let Some(requests) = &issued_requests {
+            self.save_state("issued_requests.json", requests)?;
+        }
+        self.save_state("replay_guard.json", &replay_guard)?;
+        self.log_transaction(&received)?;
+        self.log_receipt(&receipt)?;
+        let mut state = self.wallet_state();
+        state.wallet = wallet.clone();
+        self.save_state("wallet.json", &state)?;
+
+        self.wallet = wallet;
+        self.replay_guard = replay_guard;
+        if let Some(merchant) = merchant {
+            self.merchant = merchant;
+        }
+        if let Some(contacts) = contacts {
+            self.contacts = contacts;
+        }
+        if let Some(requests) = issued_requests {
+            self.issued_requests = requests;
+        }
+        self.transactions.push(received.clone());
+        self.receipts.push(receipt.clone());
 
-        Ok(signature == expected_signature)
+        self.notify(WalletEvent::PaymentReceived(Box::new(PaymentReceived {
+            transaction: received,
+            receipt: receipt.clone(),
+        })));
+        self.notify_balance();
+        Ok(receipt)
     }
 
-    /// Get wallet statistics
-    pub fn get_stats(&self) -> serde_json::Value {
-        let confirmed_count = self.transactions.iter()
-            .filter(|t| t.status == "confirmed")
-            .count();
-        let total_volume: u64 = self.transactions.iter()
-            .filter(|t| t.status == "confirmed")
-            .map(|t| t.amount)
-            .sum();
+    /// A wallet we already know must pay with the key on record; an unknown one is
+    /// trusted on first use. Rotations are applied from succession records beforehand,
+    /// so another key is refused and reported as a contact key change.
+    fn check_payer_key(&self, wallet_id: &str, public_key: &str) -> Result<(), String> {
+        if let Some(contact) = self.contacts.find(wallet_id) {
+            if !contact.public_key.eq_ignore_ascii_case(public_key) {
+                self.notify(WalletEvent::ContactKeyChanged(KeyChangeWarning {
+                    wallet_id: contact.wallet_id.clone(),
+                    nickname: contact.nickname.clone(),
+                    known_key: contact.public_key.clone(),
+                    presented_key: public_key.to_string(),
+                }));
+                return Err("Payer key does not match the contact's key".to_string());
+            }
+        }
+        if let Some(identity) = self.directory.find(wallet_id) {
+            if identity.has_valid_badge(&self.trusted_issuers)?
+                && !identity.public_key.eq_ignore_ascii_case(public_key)
+            {
+                return Err("Payer key does not match the merchant directory".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget replay-tracking entries for received payments the server has settled
+    pub fn settle_received_payments(&mut self, tx_ids: Vec<String>) -> Result<usize, String> {
+        let pruned = self.replay_guard.mark_settled(&tx_ids);
+        self.save_replay_guard()?;
+        self.notify_sync("settlement", tx_ids.len(), tx_ids.len());
+        Ok(pruned)
+    }
+
+    /// Store the receipt returned by a payee and confirm the matching outgoing transaction
+    pub fn import_receipt(&mut self, receipt: PaymentReceipt) -> Result<Transaction, String> {
+        if !receipt.verify()? {
+            return Err("Invalid receipt signature".to_string());
+        }
+
+        let tx = self.transactions.get(&receipt.tx_id)
+            .ok_or("Transaction not found")?;
+
+        if receipt::transaction_hash(tx) != receipt.tx_hash {
+            return Err("Receipt does not match transaction".to_string());
+        }
+
+        // A cancelled payment was credited back and must not come back to life
+        if tx.status != "pending" {
+            return Err(format!("Transaction is {}, not pending", tx.status));
+        }
+
+        let confirmed = self.set_status(&receipt.tx_id, "confirmed")?;
+
+        self.observe_peer(&receipt.payee_wallet_id, &receipt.payee_public_key)?;
+        if !self.receipts.iter().any(|r| r.tx_id == receipt.tx_id) {
+            self.log_receipt(&receipt)?;
+            self.receipts.push(receipt);
+        }
+
+        Ok(confirmed)
+    }
+
+    pub fn get_receipt(&self, tx_id: &str) -> Result<PaymentReceipt, String> {
+        self.receipts.iter()
+            .find(|r| r.tx_id == tx_id)
+            .cloned()
+            .ok_or("Receipt not found".to_string())
+    }
+
+    pub fn get_receipts(&self) -> Vec<PaymentReceipt> {
+        self.receipts.clone()
+    }
+
+    /// Create a signed payment request for a payer to import and pay
+    pub fn create_payment_request(
+        &mut self,
+        merchant_name: String,
+        amount: u64,
+        reference: String,
+        description: Option<String>,
+        expires_in_secs: i64,
+    ) -> Result<PaymentRequest, String> {
+        if amount == 0 {
+            return Err("Amount must be positive".to_string());
+        }
+
+        let keypair = self.keypair.as_ref()
+            .ok_or("Keys not initialized")?;
+
+        let request = PaymentRequest::create(
+            &self.wallet.id,
+            &keypair.public_key,
+            &keypair.private_key,
+            merchant_name,
+            amount,
+            reference,
+            description,
+            expires_in_secs,
+        )?;
+
+        self.issued_requests.push(request.clone());
+        self.save_issued_requests()?;
+        Ok(request)
+    }
+
+    /// Check and keep a payment request received over NFC, BLE or QR
+    pub fn import_payment_request(&mut self, request: PaymentRequest) -> Result<PaymentRequest, String> {
+        if !request.verify()? {
+            return Err("Invalid payment request signature".to_string());
+        }
+
+        if request.is_expired() {
+            return Err("Payment request expired".to_string());
+        }
+
+        if request.merchant_wallet_id == self.wallet.id {
+            return Err("Cannot pay own payment request".to_string());
+        }
+
+        if let Some(existing) = self.incoming_requests.iter().find(|r| r.id == request.id) {
+            return Ok(existing.clone());
+        }
+
+        let mut imported = request;
+        imported.status = "open".to_string();
+        self.incoming_requests.push(imported.clone());
+        self.save_incoming_requests()?;
+        Ok(imported)
+    }
+
+    /// Pay an imported request; the transaction is bound to the request ID
+    pub fn pay_payment_request(
+        &mut self,
+        request_id: String,
+        tx_type: String,
+    ) -> Result<Transaction, String> {
+        let request = self.incoming_requests.iter()
+            .find(|r| r.id == request_id)
+            .cloned()
+            .ok_or("Payment request not found")?;
+
+        if request.status != "open" {
+            return Err("Payment request already paid".to_string());
+        }
+
+        if request.is_expired() {
+            return Err("Payment request expired".to_string());
+        }
+
+        let transaction = self.create_signed_transaction(
+            request.merchant_wallet_id,
+            request.merchant_name,
+            request.amount,
+            &tx_type,
+            Some(request.id),
+            None,
+            None,
+        )?;
+
+        if let Some(r) = self.incoming_requests.iter_mut().find(|r| r.id == request_id) {
+            r.status = "paid".to_string();
+        }
+        self.save_incoming_requests()?;
 
-        serde_json::json!({
-            "total_transactions": self.transactions.len(),
-            "confirmed_transactions": confirmed_count,
-            "total_volume": total_volume,
-            "wallet_id": self.wallet.id,
-            "created_at": self.wallet.created_at,
+        Ok(transaction)
+    }
+
+    pub fn get_payment_requests(&self) -> Vec<PaymentRequest> {
+        self.incoming_requests.clone()
+    }
+
+    pub fn get_issued_payment_requests(&self) -> Vec<PaymentRequest> {
+        self.issued_requests.clone()
+    }
+
+    pub fn get_issued_payment_request(&self, request_id: &str) -> Result<PaymentRequest, String> {
+        self.issued_requests.iter()
+            .find(|r| r.id == request_id)
+            .cloned()
+            .ok_or("Payment request not found".to_string())
+    }
+
+    /// Package one of our outgoing transactions for transfer to the payee
+    pub fn signed_payment(&self, tx_id: &str) -> Result<SignedPayment, String> {
+        let mut transaction = self.transactions.get(tx_id)
+            .filter(|t| t.from_wallet_id == self.wallet.id)
+            .cloned()
+            .ok_or("Transaction not found")?;
+
+        // Local-only fields stay on this device
+        transaction.channel = None;
+        transaction.merchant_status = None;
+        transaction.annotations = TxAnnotations::default();
+
+        // A payment signed before a rotation travels with the key that signed it
+        let sender_public_key = self.own_key_for(&transaction)?
+            .ok_or("Transaction not signed by this wallet")?;
+
+        // Successions up to that key let a payee who knows an older key follow along
+        let successions = match self.key_successions.iter().position(|r| r.new_public_key == sender_public_key) {
+            Some(last) => self.key_successions[..=last].to_vec(),
+            None => Vec::new(),
+        };
+
+        Ok(SignedPayment {
+            transaction,
+            sender_public_key,
+            successions,
         })
     }
-}
 
-lazy_static! {
-    static ref BANKING_ENGINE: Mutex<BankingEngine> = Mutex::new(BankingEngine::new());
-}
+    /// Receive a payment from another device, first following the payer's key
+    /// rotations from the key we have on record
+    pub fn receive_signed_payment(
+        &mut self,
+        payment: SignedPayment,
+        channel: Option<String>,
+    ) -> Result<PaymentReceipt, String> {
+        let payer = payment.transaction.from_wallet_id.clone();
+        let known_key = self.contacts.find(&payer).map(|c| c.public_key.clone());
 
-#[tauri::command]
-fn init_wallet() -> ApiResponse<Wallet> {
-    let mut engine = BANKING_ENGINE.lock().unwrap();
-    if engine.keypair.is_none() {
-        let _ = engine.initialize_keys();
+        if let Some(known_key) = known_key {
+            if known_key != payment.sender_public_key && !payment.successions.is_empty() {
+                let keys = succession::follow_chain(&payer, &known_key, &payment.successions)?;
+                if keys.last() != Some(&payment.sender_public_key) {
+                    return Err("Succession chain does not lead to the payer key".to_string());
+                }
+                self.import_key_succession(&payer, payment.successions)?;
+            }
+        }
+
+        self.receive_payment(payment.transaction, payment.sender_public_key, channel)
     }
 
-    let wallet = engine.get_wallet();
-    ApiResponse {
-        success: true,
-        data: Some(wallet),
-        error: None,
-        timestamp: Utc::now().to_rfc3339(),
+    /// Open a P2P payment session; the returned offer goes to the receiver
+    pub fn p2p_start_payment(
+        &mut self,
+        receiver_wallet_id: String,
+        merchant_name: String,
+        amount: u64,
+    ) -> Result<HandshakeMessage, String> {
+        if amount == 0 {
+            return Err("Amount must be positive".to_string());
+        }
+
+        if amount > self.wallet.offline_balance {
+            return Err("Insufficient offline balance".to_string());
+        }
+
+        let session = HandshakeSession::new_sender(receiver_wallet_id, merchant_name, amount);
+        let offer = HandshakeMessage::Offer {
+            session_id: session.id.clone(),
+            sender_wallet_id: self.wallet.id.clone(),
+            receiver_wallet_id: session.counterparty_wallet_id.clone(),
+            merchant_name: session.merchant_name.clone(),
+            amount,
+            capabilities: PeerCapabilities::local(),
+        };
+
+        self.p2p_sessions.push(session);
+        Ok(offer)
     }
-}
 
-#[tauri::command]
-fn get_wallet() -> ApiResponse<Wallet> {
-    let engine = BANKING_ENGINE.lock().unwrap();
-    let wallet = engine.get_wallet();
-    ApiResponse {
-        success: true,
-        data: Some(wallet),
-        error: None,
-        timestamp: Utc::now().to_rfc3339(),
+    /// Feed a message from the peer into its session and return the reply to send, if any.
+    /// `transport` ("ble", "nfc") is recorded on the payment.
+    pub fn p2p_handle_message(
+        &mut self,
+        message: HandshakeMessage,
+        transport: Option<String>,
+    ) -> Result<Option<HandshakeMessage>, String> {
+        if let HandshakeMessage::Offer {
+            session_id,
+            sender_wallet_id,
+            receiver_wallet_id,
+            merchant_name,
+            amount,
+            capabilities,
+        } = message
+        {
+            if self.p2p_sessions.iter().any(|s| s.id == session_id) {
+                return Err("Session already exists".to_string());
+            }
+
+            let local = PeerCapabilities::local();
+            let protocol = match version::negotiate(&local, &capabilities) {
+                Ok(protocol) => protocol,
+                Err(reason) => return Ok(Some(HandshakeMessage::Reject { session_id, reason })),
+            };
+
+            if receiver_wallet_id != self.wallet.id || amount == 0 {
+                return Ok(Some(HandshakeMessage::Reject {
+                    session_id,
+                    reason: "Offer not acceptable".to_string(),
+                }));
+            }
+
+            let mut session = HandshakeSession::new_receiver(
+                session_id.clone(),
+                sender_wallet_id,
+                merchant_name,
+                amount,
+            );
+            let protocol_version = protocol.version;
+            session.protocol = Some(protocol);
+            self.p2p_sessions.push(session);
+
+            return Ok(Some(HandshakeMessage::Accept {
+                session_id,
+                receiver_wallet_id: self.wallet.id.clone(),
+                capabilities: local,
+                protocol_version,
+            }));
+        }
+
+        let index = self.p2p_sessions.iter()
+            .position(|s| s.id == message.session_id())
+            .ok_or("Unknown P2P session")?;
+
+        {
+            let session = &mut self.p2p_sessions[index];
+            if session.is_expired() {
+                session.interrupt("Timed out");
+            }
+        }
+
+        let session = self.p2p_sessions[index].clone();
+
+        match (session.role, message) {
+            (HandshakeRole::Sender, HandshakeMessage::Accept {
+                session_id,
+                receiver_wallet_id,
+                capabilities,
+                protocol_version,
+            }) => {
+                if session.state != HandshakeState::Offered {
+                    return Ok(Some(HandshakeMessage::Reject {
+                        session_id,
+                        reason: "Session no longer open".to_string(),
+                    }));
+                }
+
+                let protocol = match version::negotiate(&PeerCapabilities::local(), &capabilities) {
+                    Ok(protocol) if protocol.version == protocol_version => protocol,
+                    Ok(_) => {
+                        let reason = "Protocol version mismatch".to_string();
+                        self.p2p_sessions[index].interrupt(&reason);
+                        return Ok(Some(HandshakeMessage::Reject { session_id, reason }));
+                    }
+                    Err(reason) => {
+                        self.p2p_sessions[index].interrupt(&reason);
+                        return Ok(Some(HandshakeMessage::Reject { session_id, reason }));
+                    }
+                };
+                self.p2p_sessions[index].protocol = Some(protocol);
+
+                if receiver_wallet_id != session.counterparty_wallet_id {
+                    self.p2p_sessions[index].interrupt("Unexpected receiver");
+                    return Ok(Some(HandshakeMessage::Reject {
+                        session_id,
+                        reason: "Unexpected receiver".to_string(),
+                    }));
+                }
+
+                // Funds are reserved here and only finalized by the Ack
+                let tx = match self.create_signed_transaction(
+                    session.counterparty_wallet_id.clone(),
+                    session.merchant_name.clone(),
+                    session.amount,
+                    "offline",
+                    None,
+                    None,
+                    transport,
+                ) {
+                    Ok(tx) => tx,
+                    Err(e) => {
+                        self.p2p_sessions[index].interrupt(&e);
+                        return Ok(Some(HandshakeMessage::Reject { session_id, reason: e }));
+                    }
+                };
+
+                let payment = self.signed_payment(&tx.id)?;
+                let session = &mut self.p2p_sessions[index];
+                session.tx_id = Some(tx.id);
+                session.advance(HandshakeState::PaymentSent)?;
+
+                Ok(Some(HandshakeMessage::Payment { session_id, payment }))
+            }
+            (HandshakeRole::Sender, HandshakeMessage::Ack { receipt, .. }) => {
+                // After a Reject the payment was cancelled and credited back
+                if !session.awaits_ack() {
+                    return Err("Session no longer awaits an acknowledgement".to_string());
+                }
+                if session.tx_id.as_deref() != Some(receipt.tx_id.as_str()) {
+                    return Err("Acknowledgement for another transaction".to_string());
+                }
+
+                self.import_receipt(receipt)?;
+                self.p2p_sessions[index].advance(HandshakeState::Completed)?;
+                Ok(None)
+            }
+            (HandshakeRole::Sender, HandshakeMessage::Reject { reason, .. }) => {
+                // An explicit reject means the receiver did not credit the payment
+                if let Some(tx_id) = &session.tx_id {
+                    if session.state == HandshakeState::PaymentSent
+                        || session.state == HandshakeState::Unresolved
+                    {
+                        self.cancel_transaction(tx_id.clone())?;
+                    }
+                }
+                let session = &mut self.p2p_sessions[index];
+                session.state = HandshakeState::Aborted;
+                session.failure = Some(reason);
+                Ok(None)
+            }
+            (HandshakeRole::Receiver, HandshakeMessage::Payment { session_id, payment }) => {
+                // Replayed payment after a lost Ack: answer with the same receipt
+                if session.state == HandshakeState::Completed {
+                    if session.tx_id.as_deref() == Some(payment.transaction.id.as_str()) {
+                        let receipt = self.get_receipt(&payment.transaction.id)?;
+                        return Ok(Some(HandshakeMessage::Ack { session_id, receipt }));
+                    }
+                    return Err("Session already completed".to_string());
+                }
+
+                if session.state != HandshakeState::Accepted {
+                    return Ok(Some(HandshakeMessage::Reject {
+                        session_id,
+                        reason: "Session no longer open".to_string(),
+                    }));
+                }
+
+                // Only channels both devices announced may carry the payment
+                if let (Some(channel), Some(protocol)) = (&transport, &session.protocol) {
+                    if !protocol.allows_channel(channel) {
+                        let reason = format!("Channel {} was not negotiated", channel);
+                        self.p2p_sessions[index].interrupt(&reason);
+                        return Ok(Some(HandshakeMessage::Reject { session_id, reason }));
+                    }
+                }
+
+                let tx = &payment.transaction;
+                if tx.amount != session.amount || tx.from_wallet_id != session.counterparty_wallet_id {
+                    self.p2p_sessions[index].interrupt("Payment does not match offer");
+                    return Ok(Some(HandshakeMessage::Reject {
+                        session_id,
+                        reason: "Payment does not match offer".to_string(),
+                    }));
+                }
+
+                let tx_id = tx.id.clone();
+                let receipt = match self.receive_signed_payment(payment, transport) {
+                    Ok(receipt) => receipt,
+                    Err(e) => {
+                        self.p2p_sessions[index].interrupt(&e);
+                        return Ok(Some(HandshakeMessage::Reject { session_id, reason: e }));
+                    }
+                };
+
+                let session = &mut self.p2p_sessions[index];
+                session.tx_id = Some(tx_id);
+                session.advance(HandshakeState::Completed)?;
+                Ok(Some(HandshakeMessage::Ack { session_id, receipt }))
+            }
+            (HandshakeRole::Receiver, HandshakeMessage::Reject { reason, .. }) => {
+                self.p2p_sessions[index].interrupt(&reason);
+                Ok(None)
+            }
+            _ => Err("Unexpected message for this session".to_string()),
+        }
     }
-}
+
+    /// Resolve sessions whose peer stopped answering; returns the sessions that changed
+    pub fn p2p_expire_sessions(&mut self) -> Vec<HandshakeSession> {
+        let mut expired = Vec::new();
+        for session in self.p2p_sessions.iter_mut() {
+            if !session.is_finished() && session.is_expired() {
+                session.interrupt("Timed out");
+                expired.push(session.clone());
+            }
+        }
+        expired
+    }
+
+    /// Largest message the peer of `session_id` accepts, once the protocol is negotiated
+    pub fn p2p_max_message_bytes(&self, session_id: &str) -> Result<usize, String> {
+        let session = self.p2p_sessions.iter()
+            .find(|s| s.id == session_id)
+            .ok_or("Unknown P2P session")?;
+        Ok(session.protocol.as_ref()
+            .map(|p| p.max_payload_bytes as usize)
+            .unwrap_or(DEFAULT_MAX_MESSAGE_BYTES))
+    }
+
+    pub fn p2p_get_session(&self, session_id: &str) -> Result<HandshakeSession, String> {
+        self.p2p_sessions.iter()
+            .find(|s| s.id == session_id)
+            .cloned()
+            .ok_or("Unknown P2P session".to_string())
+    }
+
+    /// Start an encrypted channel; the hello goes to the peer in the clear
+    pub fn p2p_secure_begin(&mut self, role: SessionRole) -> Result<SecureChannelStart, String> {
+        let keypair = self.keypair.as_ref()
+            .ok_or("Keys not initialized")?;
+
+        let exchange = KeyExchange::new(role, &self.wallet.id, keypair)?;
+        let start = SecureChannelStart {
+            channel_id: Uuid::new_v4().to_string(),
+            hello: exchange.hello().clone(),
+        };
+
+        self.key_exchanges.insert(start.channel_id.clone(), exchange);
+        Ok(start)
+    }
+
+    /// Finish the key exchange with the peer's hello; returns the authenticated peer wallet ID.
+    /// The peer only becomes a contact once a payment exchange with it completes.
+    pub fn p2p_secure_complete(
+        &mut self,
+        channel_id: String,
+        peer_hello: SessionHello,
+    ) -> Result<String, String> {
+        let exchange = self.key_exchanges.remove(&channel_id)
+            .ok_or("Unknown secure channel")?;
+
+        let session = exchange.complete(&peer_hello)?;
+        let peer_wallet_id = session.peer_wallet_id().to_string();
+        self.secure_channels.insert(channel_id, session);
+        Ok(peer_wallet_id)
+    }
+
+    /// Encrypt a handshake message for the peer of `channel_id`
+    pub fn p2p_secure_seal(
+        &mut self,
+        channel_id: &str,
+        message: &HandshakeMessage,
+    ) -> Result<Vec<u8>, String> {
+        let plaintext = serde_json::to_vec(message).map_err(|e| e.to_string())?;
+        self.secure_channels.get_mut(channel_id)
+            .ok_or("Unknown secure channel")?
+            .seal(&plaintext)
+    }
+
+    /// Decrypt a message from the peer and check it is consistent with the proven identity
+    pub fn p2p_secure_open(
+        &mut self,
+        channel_id: &str,
+        frame: &[u8],
+    ) -> Result<HandshakeMessage, String> {
+        let session = self.secure_channels.get_mut(channel_id)
+            .ok_or("Unknown secure channel")?;
+
+        let plaintext = session.open(frame)?;
+        let message: HandshakeMessage = serde_json::from_slice(&plaintext)
+            .map_err(|e| format!("Invalid P2P message: {}", e))?;
+
+        match &message {
+            HandshakeMessage::Offer { sender_wallet_id, .. }
+                if sender_wallet_id != session.peer_wallet_id() =>
+            {
+                Err("Offer sender does not match channel identity".to_string())
+            }
+            HandshakeMessage::Payment { payment, .. }
+                if payment.sender_public_key != session.peer_public_key() =>
+            {
+                Err("Payment key does not match channel identity".to_string())
+            }
+            HandshakeMessage::Ack { receipt, .. }
+                if receipt.payee_public_key != session.peer_public_key() =>
+            {
+                Err("Receipt key does not match channel identity".to_string())
+            }
+            _ => Ok(message),
+        }
+    }
+
+    /// Open a payment session with the peer of `channel_id` and return the sealed offer
+    pub fn p2p_secure_start_payment(
+        &mut self,
+        channel_id: &str,
+        merchant_name: String,
+        amount: u64,
+    ) -> Result<Vec<u8>, String> {
+        let peer_wallet_id = self.secure_channels.get(channel_id)
+            .ok_or("Unknown secure channel")?
+            .peer_wallet_id()
+            .to_string();
+
+        let offer = self.p2p_start_payment(peer_wallet_id, merchant_name, amount)?;
+        self.p2p_secure_seal(channel_id, &offer)
+    }
+
+    /// Decrypt a peer message, run it through the handshake and encrypt the reply
+    pub fn p2p_secure_exchange(
+        &mut self,
+        channel_id: &str,
+        frame: &[u8],
+        transport: Option<String>,
+    ) -> Result<Option<Vec<u8>>, String> {
+        let message = self.p2p_secure_open(channel_id, frame)?;
+        match self.p2p_handle_message(message, transport)? {
+            Some(reply) => self.p2p_secure_seal(channel_id, &reply).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn p2p_secure_close(&mut self, channel_id: &str) {
+        self.key_exchanges.remove(channel_id);
+        self.secure_channels.remove(channel_id);
+    }
+
+    /// Refund all or part of a payment this wallet received
+    pub fn issue_refund(
+        &mut self,
+        original_tx_id: String,
+        amount: u64,
+        reason: String,
+    ) -> Result<Refund, String> {
+        if amount == 0 {
+            return Err("Amount must be positive".to_string());
+        }
+
+        // A refund spends like a payment, so it waits for the restored history too
+        if self.restore_pending {
+            return Err("Wallet restore not finished: sync the account history first".to_string());
+        }
+
+        let keypair = self.keypair.as_ref()
+            .ok_or("Keys not initialized")?;
+
+        let original = self.transactions.get(&original_tx_id)
+            .ok_or("Transaction not found")?;
+
+        if original.to_wallet_id != self.wallet.id || original.from_wallet_id == self.wallet.id {
+            return Err("Only received payments can be refunded".to_string());
+        }
+
+        if original.status != "confirmed" {
+            return Err("Only confirmed payments can be refunded".to_string());
+        }
+
+        let already_refunded = refund::refunded_total(&self.refunds, &original.id)?;
+        if already_refunded.checked_add(amount).ok_or("Refund amount too large")? > original.amount {
+            return Err(format!(
+                "Refund exceeds original amount ({} already refunded of {})",
+                already_refunded, original.amount
+            ));
+        }
+
+        let from_online = original.tx_type == "online";
+        let available = if from_online {
+            self.wallet.online_balance
+        } else {
+            self.wallet.offline_balance
+        };
+        if amount > available {
+            return Err("Insufficient balance for refund".to_string());
+        }
+
+        let refund = Refund::issue(
+            original,
+            amount,
+            reason,
+            &keypair.public_key,
+            &keypair.private_key,
+        )?;
+        let entry = refund.to_transaction(original);
+        let wallet = self.wallet.debited(from_online, amount)?;
+        let mut refunds = self.refunds.clone();
+        refunds.push(refund.clone());
+
+        self.save_state("refunds.json", &refunds)?;
+        self.commit_wallet_change(wallet, &entry)?;
+        self.refunds = refunds;
+        self.notify(WalletEvent::TransactionCreated(Box::new(entry)));
+        self.notify_balance();
+        Ok(refund)
+    }
+
+    /// Credit a refund received from the merchant of one of our payments
+    pub fn apply_refund(&mut self, refund: Refund) -> Result<Transaction, String> {
+        if !refund.verify()? {
+            return Err("Invalid refund signature".to_string());
+        }
+
+        if self.refunds.iter().any(|r| r.id == refund.id) || self.transactions.contains(&refund.id) {
+            return Err("Refund already applied".to_string());
+        }
+
+        let original = self.transactions.get(&refund.original_tx_id)
+            .ok_or("Original transaction not found")?;
+
+        if original.from_wallet_id != self.wallet.id
+            || original.to_wallet_id != refund.refunder_wallet_id
+            || refund.recipient_wallet_id != self.wallet.id
+        {
+            return Err("Refund does not match original transaction".to_string());
+        }
+
+        // A cancelled payment was already credited back
+        if original.status != "confirmed" {
+            return Err("Only confirmed payments can be refunded".to_string());
+        }
+
+        // The refund must come from the payee's key as we know it: from its receipt,
+        // otherwise from our contacts
+        let payee_key = self.receipts.iter()
+            .find(|r| r.tx_id == original.id)
+            .map(|r| r.payee_public_key.clone())
+            .or_else(|| self.contacts.find(&original.to_wallet_id).map(|c| c.public_key.clone()))
+            .ok_or("Unknown payee key: cannot verify the refund")?;
+        if payee_key != refund.refunder_public_key {
+            return Err("Refund signed by a different key than the payee".to_string());
+        }
+
+        let already_refunded = refund::refunded_total(&self.refunds, &original.id)?;
+        if already_refunded.checked_add(refund.amount).ok_or("Refund amount too large")? > original.amount {
+            return Err("Refund exceeds original amount".to_string());
+        }
+
+        let entry = refund.to_transaction(original);
+        let wallet = self.wallet.credited(original.tx_type == "online", refund.amount)?;
+        let mut refunds = self.refunds.clone();
+        refunds.push(refund);
+
+        // Saved before the engine changes, like a received payment
+        self.save_state("refunds.json", &refunds)?;
+        self.commit_wallet_change(wallet, &entry)?;
+        self.refunds = refunds;
+        self.notify(WalletEvent::TransactionCreated(Box::new(entry.clone())));
+        self.notify_balance();
+        Ok(entry)
+    }
+
+    /// Refunds linked to a given original transaction
+    pub fn get_refunds(&self, original_tx_id: &str) -> Vec<Refund> {
+        self.refunds.iter()
+            .filter(|r| r.original_tx_id == original_tx_id)
+            .cloned()
+            .collect()
+    }
+
+    pub fn set_merchant_profile(
+        &mut self,
+        display_name: String,
+        category: String,
+    ) -> Result<MerchantProfile, String> {
+        if display_name.trim().is_empty() {
+            return Err("Merchant name is required".to_string());
+        }
+
+        let created_at = self.merchant.profile.as_ref()
+            .map(|p| p.created_at.clone())
+            .unwrap_or_else(|| Utc::now().to_rfc3339());
+
+        let profile = MerchantProfile {
+            display_name: display_name.trim().to_string(),
+            category,
+            created_at,
+        };
+        self.merchant.profile = Some(profile.clone());
+        self.save_merchant()?;
+        Ok(profile)
+    }
+
+    pub fn get_merchant_profile(&self) -> Option<MerchantProfile> {
+        self.merchant.profile.clone()
+    }
+
+    pub fn open_till_session(&mut self) -> Result<TillSession, String> {
+        if self.merchant.profile.is_none() {
+            return Err("Merchant profile not set".to_string());
+        }
+        if self.merchant.open_session().is_some() {
+            return Err("A till session is already open".to_string());
+        }
+
+        let session = TillSession::open();
+        self.merchant.sessions.push(session.clone());
+        self.save_merchant()?;
+        Ok(session)
+    }
+
+    pub fn current_till_session(&self) -> Result<TillSession, String> {
+        self.merchant.open_session()
+            .cloned()
+            .ok_or("No open till session".to_string())
+    }
+
+    /// Close the open till session and return its Z-report
+    pub fn close_till_session(&mut self) -> Result<ZReport, String> {
+        let merchant_name = self.merchant.profile.as_ref()
+            .map(|p| p.display_name.clone())
+            .ok_or("Merchant profile not set")?;
+        let wallet_id = self.wallet.id.clone();
+
+        let session = self.merchant.open_session_mut()
+            .ok_or("No open till session")?;
+
+        let refunded_amount = self.refunds.iter()
+            .filter(|r| r.refunder_wallet_id == wallet_id)
+            .filter(|r| session.entries.iter().any(|e| e.tx_id == r.original_tx_id))
+            .map(|r| r.amount)
+            .sum();
+
+        let report = session.close(&merchant_name, &wallet_id, refunded_amount)?;
+        self.save_merchant()?;
+        Ok(report)
+    }
+
+    pub fn get_till_sessions(&self) -> Vec<TillSession> {
+        self.merchant.sessions.clone()
+    }
+
+    /// Offline payments of a session still waiting for the server, to upload when online
+    pub fn till_unsettled_payments(&self, session_id: &str) -> Result<Vec<Transaction>, String> {
+        let session = self.merchant.sessions.iter()
+            .find(|s| s.id == session_id)
+            .ok_or("Till session not found")?;
+
+        Ok(session.entries.iter()
+            .filter(|e| !e.settled)
+            .filter_map(|e| self.transactions.get(&e.tx_id))
+            .cloned()
+            .collect())
+    }
+
+    /// Apply the server's settlement of a session's offline payments: the funds move
+    /// from the offline to the online balance. Returns the updated session.
+    pub fn settle_till_session(
+        &mut self,
+        session_id: &str,
+        settled_tx_ids: Vec<String>,
+    ) -> Result<TillSession, String> {
+        let session = self.merchant.sessions.iter_mut()
+            .find(|s| s.id == session_id)
+            .ok_or("Till session not found")?;
+
+        let is_settled_now = |e: &TillEntry| !e.settled && settled_tx_ids.contains(&e.tx_id);
+        let amount: u64 = session.entries.iter()
+            .filter(|e| is_settled_now(e))
+            .map(|e| e.amount)
+            .sum();
+
+        if amount > self.wallet.offline_balance {
+            return Err("Offline balance lower than settled amount".to_string());
+        }
+
+        for entry in session.entries.iter_mut() {
+            if is_settled_now(entry) {
+                entry.settled = true;
+            }
+        }
+        let session = session.clone();
+
+        self.wallet.offline_balance -= amount;
+        self.wallet.online_balance += amount;
+        self.wallet.last_updated = Utc::now().to_rfc3339();
+
+        self.replay_guard.mark_settled(&settled_tx_ids);
+        self.save_replay_guard()?;
+        self.save_merchant()?;
+
+        self.notify_sync("settlement", settled_tx_ids.len(), settled_tx_ids.len());
+        self.save_wallet()?;
+        self.notify_balance();
+        Ok(session)
+    }
+
+    pub fn add_contact(
+        &mut self,
+        nickname: &str,
+        wallet_id: &str,
+        public_key: &str,
+    ) -> Result<Contact, String> {
+        crypto::validate_public_key(public_key)?;
+        let contact = self.contacts.add(nickname, wallet_id, public_key)?;
+        self.save_contacts()?;
+        Ok(contact)
+    }
+
+    pub fn rename_contact(&mut self, wallet_id: &str, nickname: &str) -> Result<Contact, String> {
+        let contact = self.contacts.rename(wallet_id, nickname)?;
+        self.save_contacts()?;
+        Ok(contact)
+    }
+
+    pub fn remove_contact(&mut self, wallet_id: &str) -> Result<bool, String> {
+        let removed = self.contacts.remove(wallet_id);
+        self.save_contacts()?;
+        Ok(removed)
+    }
+
+    pub fn get_contacts(&self) -> Vec<Contact> {
+        self.contacts.contacts()
+    }
+
+    pub fn resolve_contact(&self, nickname: &str) -> Result<Contact, String> {
+        self.contacts.find_by_nickname(nickname)
+            .cloned()
+            .ok_or("Contact not found".to_string())
+    }
+
+    /// Resolve a pending key change: accept the new key or keep the recorded one
+    pub fn resolve_contact_key(&mut self, wallet_id: &str, accept: bool) -> Result<Contact, String> {
+        let contact = if accept {
+            self.contacts.accept_pending_key(wallet_id)?
+        } else {
+            self.contacts.reject_pending_key(wallet_id)?
+        };
+        self.save_contacts()?;
+        Ok(contact)
+    }
+
+    /// Follow a contact's key rotation from the key we know to its current key
+    pub fn import_key_succession(
+        &mut self,
+        wallet_id: &str,
+        records: Vec<KeySuccession>,
+    ) -> Result<Contact, String> {
+        let known_key = self.contacts.find(wallet_id)
+            .map(|c| c.public_key.clone())
+            .ok_or("Contact not found")?;
+
+        let keys = succession::follow_chain(wallet_id, &known_key, &records)?;
+        let contact = self.contacts.rotate_key(wallet_id, &keys)?;
+        self.save_contacts()?;
+        Ok(contact)
+    }
+
+    pub fn get_contact_warnings(&self) -> Vec<KeyChangeWarning> {
+        self.contacts.warnings()
+    }
+
+    /// Replace the revocation list with a full copy from the issuer
+    pub fn import_revocation_list(&mut self, list: RevocationList) -> Result<RevocationStatus, String> {
+        self.revocations.import_list(list, &self.trusted_issuers)?;
+        self.save_revocations()?;
+        Ok(self.revocations.status())
+    }
+
+    /// Apply an incremental update fetched while online
+    pub fn apply_revocation_delta(&mut self, delta: RevocationDelta) -> Result<RevocationStatus, String> {
+        self.revocations.apply_delta(delta, &self.trusted_issuers)?;
+        self.save_revocations()?;
+        Ok(self.revocations.status())
+    }
+
+    /// Version and age of the local revocation list
+    pub fn revocation_status(&self) -> RevocationStatus {
+        self.revocations.status()
+    }
+
+    /// Cache merchant records from the server; records without a trusted badge are dropped
+    pub fn import_merchant_directory(&mut self, identities: Vec<MerchantIdentity>) -> Result<usize, String> {
+        let stored = self.directory.import(identities, &self.trusted_issuers)?;
+        self.save_directory()?;
+        Ok(stored)
+    }
+
+    pub fn get_merchant_directory(&self) -> Vec<MerchantIdentity> {
+        self.directory.merchants()
+    }
+
+    /// Check a payee before paying: verified merchant, unknown wallet, or impersonation.
+    /// Without `public_key`, the key recorded for the contact is compared, if any.
+    pub fn check_merchant(
+        &self,
+        wallet_id: &str,
+        public_key: Option<&str>,
+        merchant_name: &str,
+    ) -> Result<MerchantCheck, String> {
+        let known_key = public_key.or_else(|| self.contacts.find(wallet_id).map(|c| c.public_key.as_str()));
+        self.directory.check(wallet_id, known_key, merchant_name, &self.trusted_issuers)
+    }
+
+    /// Get wallet statistics
+    pub fn get_stats(&self) -> WalletStats {
+        stats::compute(self.transactions.iter(), &self.wallet.id, &self.wallet.created_at)
+    }
+
+    /// History between two inclusive RFC 3339 dates, oldest first, as CSV or JSON
+    pub fn export_history(
+        &self,
+        format: ExportFormat,
+        from: Option<String>,
+        to: Option<String>,
+    ) -> Result<String, String> {
+        let from_ms = history::parse_bound(&from)?;
+        let until_ms = history::parse_bound(&to)?.map(|ms| ms + 1);
+        let rows: Vec<ExportRow> = self.transactions.between(from_ms, until_ms)
+            .into_iter()
+            .map(|tx| ExportRow::from_transaction(tx, &self.wallet.id))
+            .collect();
+
+        match format {
+            ExportFormat::Csv => Ok(statement::to_csv(&rows)),
+            ExportFormat::Json => {
+                let export = HistoryExport {
+                    wallet_id: self.wallet.id.clone(),
+                    from,
+                    to,
+                    generated_at: Utc::now().to_rfc3339(),
+                    rows,
+                };
+                serde_json::to_string_pretty(&export).map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Statement for a calendar month ("YYYY-MM", UTC)
+    pub fn monthly_statement(&self, month: &str) -> Result<MonthlyStatement, String> {
+        let account_name = self.merchant.profile.as_ref().map(|p| p.display_name.clone());
+
+        statement::monthly_statement(
+            &self.wallet.id,
+            account_name,
+            month,
+            self.wallet.total_balance,
+            &self.transactions.between(None, None),
+            &self.contacts,
+        )
+    }
+}
+
+lazy_static! {
+    static ref BANKING_ENGINE: Mutex<BankingEngine> = Mutex::new(BankingEngine::new());
+    static ref DEVICE_TRACKER: Mutex<DeviceTracker> = Mutex::new(DeviceTracker::new());
+    static ref BLE_CHANNELS: Mutex<HashMap<String, FrameChannel>> = Mutex::new(HashMap::new());
+    static ref CAPABILITY_PROVIDER: Mutex<Box<dyn CapabilityProvider>> =
+        Mutex::new(capabilities::default_provider());
+}
+
+/// Replace the hardware capability source, e.g. with a fixed provider in tests
+pub fn set_capability_provider(provider: Box<dyn CapabilityProvider>) {
+    *CAPABILITY_PROVIDER.lock().unwrap() = provider;
+}
+
+/// Forwards engine events to the webview under their event names
+struct TauriEventSink(AppHandle);
+
+impl EventSink for TauriEventSink {
+    fn emit(&self, event: &WalletEvent) {
+        // A closed window just misses the event; the next refresh catches up
+        let _ = match event {
+            WalletEvent::BalanceChanged(wallet) => self.0.emit(event.name(), wallet),
+            WalletEvent::TransactionCreated(tx) => self.0.emit(event.name(), tx),
+            WalletEvent::TransactionStatusChanged(change) => self.0.emit(event.name(), change),
+            WalletEvent::PaymentReceived(payment) => self.0.emit(event.name(), payment),
+            WalletEvent::SyncProgress(progress) => self.0.emit(event.name(), progress),
+            WalletEvent::BudgetAlert(alert) => self.0.emit(event.name(), alert),
+            WalletEvent::ContactKeyChanged(warning) => self.0.emit(event.name(), warning),
+        };
+    }
+}
+
+#[tauri::command]
+fn init_wallet() -> ApiResponse<Wallet> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    if engine.keypair.is_none() {
+        let _ = engine.initialize_keys();
+    }
+
+    let wallet = engine.get_wallet();
+    ApiResponse {
+        success: true,
+        data: Some(wallet),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+#[tauri::command]
+fn get_wallet() -> ApiResponse<Wallet> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    let wallet = engine.get_wallet();
+    ApiResponse {
+        success: true,
+        data: Some(wallet),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+#[tauri::command]
+fn initialize_keys() -> ApiResponse<KeyPair> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.initialize_keys() {
+        Ok(keys) => ApiResponse {
+            success: true,
+            data: Some(keys),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn get_public_key() -> ApiResponse<String> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    match engine.get_public_key() {
+        Ok(key) => ApiResponse {
+            success: true,
+            data: Some(key),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn transfer_to_vault(amount: u64) -> ApiResponse<Wallet> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.transfer_to_vault(amount) {
+        Ok(wallet) => ApiResponse {
+            success: true,
+            data: Some(wallet),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn transfer_from_vault(amount: u64) -> ApiResponse<Wallet> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.transfer_from_vault(amount) {
+        Ok(wallet) => ApiResponse {
+            success: true,
+            data: Some(wallet),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn create_offline_transaction(
+    to_wallet_id: String,
+    merchant_name: String,
+    amount: u64,
+    category: Option<String>,
+) -> ApiResponse<Transaction> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.create_offline_transaction(to_wallet_id, merchant_name, amount, category) {
+        Ok(tx) => ApiResponse {
+            success: true,
+            data: Some(tx),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn create_online_transaction(
+    to_wallet_id: String,
+    merchant_name: String,
+    amount: u64,
+    category: Option<String>,
+) -> ApiResponse<Transaction> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.create_online_transaction(to_wallet_id, merchant_name, amount, category) {
+        Ok(tx) => ApiResponse {
+            success: true,
+            data: Some(tx),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn confirm_transaction(tx_id: String) -> ApiResponse<Transaction> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.confirm_transaction(tx_id) {
+        Ok(tx) => ApiResponse {
+            success: true,
+            data: Some(tx),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn cancel_transaction(tx_id: String) -> ApiResponse<Wallet> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.cancel_transaction(tx_id) {
+        Ok(wallet) => ApiResponse {
+            success: true,
+            data: Some(wallet),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn get_transactions() -> ApiResponse<Vec<Transaction>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    let transactions = engine.get_transactions();
+    ApiResponse {
+        success: true,
+        data: Some(transactions),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+/// History page with filters and cursor pagination
+#[tauri::command]
+fn query_transactions(query: TransactionQuery) -> ApiResponse<TransactionPage> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    match engine.query_transactions(&query) {
+        Ok(page) => ApiResponse {
+            success: true,
+            data: Some(page),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn annotate_transaction(
+    tx_id: String,
+    note: Option<String>,
+    tags: Vec<String>,
+    category: Option<String>,
+) -> ApiResponse<Transaction> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.annotate_transaction(&tx_id, note, tags, category) {
+        Ok(tx) => ApiResponse {
+            success: true,
+            data: Some(tx),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn get_transaction_categories() -> ApiResponse<Vec<String>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    ApiResponse {
+        success: true,
+        data: Some(engine.get_categories()),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+#[tauri::command]
+fn get_wallet_stats() -> ApiResponse<WalletStats> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    let stats = engine.get_stats();
+    ApiResponse {
+        success: true,
+        data: Some(stats),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Write the history between two dates to a file of the exports folder, as CSV or JSON
+#[tauri::command]
+fn export_history(
+    format: ExportFormat,
+    from: Option<String>,
+    to: Option<String>,
+    file_name: String,
+) -> ApiResponse<String> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    let result = engine.export_path(&file_name).and_then(|path| {
+        let content = engine.export_history(format, from, to)?;
+        storage::save_bytes(&path, content.as_bytes())?;
+        Ok(path)
+    });
+
+    match result {
+        Ok(path) => ApiResponse {
+            success: true,
+            data: Some(path.display().to_string()),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn get_monthly_statement(month: String) -> ApiResponse<MonthlyStatement> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    match engine.monthly_statement(&month) {
+        Ok(statement) => ApiResponse {
+            success: true,
+            data: Some(statement),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn verify_tx_signature(tx: Transaction) -> ApiResponse<bool> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    match engine.verify_signature(&tx) {
+        Ok(valid) => ApiResponse {
+            success: true,
+            data: Some(valid),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn receive_payment(
+    tx: Transaction,
+    sender_public_key: String,
+    channel: Option<String>,
+) -> ApiResponse<PaymentReceipt> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.receive_payment(tx, sender_public_key, channel) {
+        Ok(receipt) => ApiResponse {
+            success: true,
+            data: Some(receipt),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn import_receipt(receipt: PaymentReceipt) -> ApiResponse<Transaction> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.import_receipt(receipt) {
+        Ok(tx) => ApiResponse {
+            success: true,
+            data: Some(tx),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn get_receipt(tx_id: String) -> ApiResponse<PaymentReceipt> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    match engine.get_receipt(&tx_id) {
+        Ok(receipt) => ApiResponse {
+            success: true,
+            data: Some(receipt),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn get_receipts() -> ApiResponse<Vec<PaymentReceipt>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    let receipts = engine.get_receipts();
+    ApiResponse {
+        success: true,
+        data: Some(receipts),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Verify a receipt without touching engine state
+#[tauri::command]
+fn verify_receipt(receipt: PaymentReceipt) -> ApiResponse<bool> {
+    match receipt.verify() {
+        Ok(valid) => ApiResponse {
+            success: true,
+            data: Some(valid),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn issue_refund(
+    original_tx_id: String,
+    amount: u64,
+    reason: String,
+) -> ApiResponse<Refund> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.issue_refund(original_tx_id, amount, reason) {
+        Ok(refund) => ApiResponse {
+            success: true,
+            data: Some(refund),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn apply_refund(refund: Refund) -> ApiResponse<Transaction> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.apply_refund(refund) {
+        Ok(tx) => ApiResponse {
+            success: true,
+            data: Some(tx),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn get_refunds(original_tx_id: String) -> ApiResponse<Vec<Refund>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    let refunds = engine.get_refunds(&original_tx_id);
+    ApiResponse {
+        success: true,
+        data: Some(refunds),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+#[tauri::command]
+fn create_payment_request(
+    merchant_name: String,
+    amount: u64,
+    reference: String,
+    description: Option<String>,
+    expires_in_secs: i64,
+) -> ApiResponse<PaymentRequest> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.create_payment_request(merchant_name, amount, reference, description, expires_in_secs) {
+        Ok(request) => ApiResponse {
+            success: true,
+            data: Some(request),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn import_payment_request(request: PaymentRequest) -> ApiResponse<PaymentRequest> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.import_payment_request(request) {
+        Ok(request) => ApiResponse {
+            success: true,
+            data: Some(request),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn pay_payment_request(request_id: String, tx_type: String) -> ApiResponse<Transaction> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.pay_payment_request(request_id, tx_type) {
+        Ok(tx) => ApiResponse {
+            success: true,
+            data: Some(tx),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn get_payment_requests() -> ApiResponse<Vec<PaymentRequest>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    let requests = engine.get_payment_requests();
+    ApiResponse {
+        success: true,
+        data: Some(requests),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+#[tauri::command]
+fn get_issued_payment_requests() -> ApiResponse<Vec<PaymentRequest>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    let requests = engine.get_issued_payment_requests();
+    ApiResponse {
+        success: true,
+        data: Some(requests),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+#[tauri::command]
+fn qr_encode_payment_request(request_id: String) -> ApiResponse<Vec<String>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    let frames = engine.get_issued_payment_request(&request_id)
+        .and_then(|r| p2p::qr::encode(&P2PPayload::PaymentRequest(r), p2p::qr::DEFAULT_FRAME_BYTES));
+    match frames {
+        Ok(frames) => ApiResponse {
+            success: true,
+            data: Some(frames),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn qr_encode_payment(tx_id: String) -> ApiResponse<Vec<String>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    let frames = engine.signed_payment(&tx_id)
+        .and_then(|p| p2p::qr::encode(&P2PPayload::SignedPayment(p), p2p::qr::DEFAULT_FRAME_BYTES));
+    match frames {
+        Ok(frames) => ApiResponse {
+            success: true,
+            data: Some(frames),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Decode scanned QR frames into the payload the NFC/BLE paths exchange
+#[tauri::command]
+fn qr_decode(frames: Vec<String>) -> ApiResponse<P2PPayload> {
+    match p2p::qr::decode(&frames) {
+        Ok(payload) => ApiResponse {
+            success: true,
+            data: Some(payload),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn p2p_start_payment(
+    receiver_wallet_id: String,
+    merchant_name: String,
+    amount: u64,
+) -> ApiResponse<HandshakeMessage> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.p2p_start_payment(receiver_wallet_id, merchant_name, amount) {
+        Ok(offer) => ApiResponse {
+            success: true,
+            data: Some(offer),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Protocol versions and capabilities this build advertises to peers
+#[tauri::command]
+fn p2p_capabilities() -> ApiResponse<PeerCapabilities> {
+    ApiResponse {
+        success: true,
+        data: Some(PeerCapabilities::local()),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+#[tauri::command]
+fn p2p_get_session(session_id: String) -> ApiResponse<HandshakeSession> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    match engine.p2p_get_session(&session_id) {
+        Ok(session) => ApiResponse {
+            success: true,
+            data: Some(session),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn p2p_expire_sessions() -> ApiResponse<Vec<HandshakeSession>> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    let expired = engine.p2p_expire_sessions();
+    ApiResponse {
+        success: true,
+        data: Some(expired),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+#[tauri::command]
+fn settle_received_payments(tx_ids: Vec<String>) -> ApiResponse<usize> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.settle_received_payments(tx_ids) {
+        Ok(pruned) => ApiResponse {
+            success: true,
+            data: Some(pruned),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Canonical hex payload of an outgoing payment, for NFC tags and BLE writes
+#[tauri::command]
+fn encode_signed_payment(tx_id: String) -> ApiResponse<String> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    match engine.signed_payment(&tx_id).and_then(|p| p.to_wire()) {
+        Ok(wire) => ApiResponse {
+            success: true,
+            data: Some(hex::encode(wire)),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn decode_signed_payment(payload: String) -> ApiResponse<SignedPayment> {
+    let decoded = hex::decode(payload.trim())
+        .map_err(|_| "Invalid payload encoding".to_string())
+        .and_then(|bytes| SignedPayment::from_wire(&bytes));
+    match decoded {
+        Ok(payment) => ApiResponse {
+            success: true,
+            data: Some(payment),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn p2p_secure_begin(role: SessionRole) -> ApiResponse<SecureChannelStart> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.p2p_secure_begin(role) {
+        Ok(start) => ApiResponse {
+            success: true,
+            data: Some(start),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn p2p_secure_complete(channel_id: String, peer_hello: SessionHello) -> ApiResponse<String> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.p2p_secure_complete(channel_id, peer_hello) {
+        Ok(peer_wallet_id) => ApiResponse {
+            success: true,
+            data: Some(peer_wallet_id),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Encrypt a handshake message; the hex frame is what goes over BLE/NFC
+#[tauri::command]
+fn p2p_secure_seal(channel_id: String, message: HandshakeMessage) -> ApiResponse<String> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.p2p_secure_seal(&channel_id, &message) {
+        Ok(frame) => ApiResponse {
+            success: true,
+            data: Some(hex::encode(frame)),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Handle an encrypted frame from the peer and return the encrypted reply, if any
+#[tauri::command]
+fn p2p_secure_exchange(
+    channel_id: String,
+    frame: String,
+    transport: Option<String>,
+) -> ApiResponse<Option<String>> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    let result = hex::decode(frame.trim())
+        .map_err(|_| "Invalid frame encoding".to_string())
+        .and_then(|bytes| engine.p2p_secure_exchange(&channel_id, &bytes, transport));
+    match result {
+        Ok(reply) => ApiResponse {
+            success: true,
+            data: Some(reply.map(hex::encode)),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn p2p_secure_close(channel_id: String) -> ApiResponse<bool> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    engine.p2p_secure_close(&channel_id);
+    ApiResponse {
+        success: true,
+        data: Some(true),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+// ========== RECOVERY COMMANDS ==========
+
+#[tauri::command]
+fn set_pin(pin: String, current_pin: Option<String>) -> ApiResponse<bool> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.set_pin(&pin, current_pin.as_deref()).map(|_| true) {
+        Ok(done) => ApiResponse {
+            success: true,
+            data: Some(done),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Reveal the recovery phrase; requires the PIN
+#[tauri::command]
+fn export_seed_phrase(pin: String) -> ApiResponse<String> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.export_seed_phrase(&pin) {
+        Ok(phrase) => ApiResponse {
+            success: true,
+            data: Some(phrase),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Regenerate the wallet keys from a recovery phrase on a new device
+#[tauri::command]
+fn restore_from_mnemonic(phrase: String, pin: Option<String>) -> ApiResponse<Wallet> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.restore_from_mnemonic(&phrase, pin.as_deref()) {
+        Ok(wallet) => ApiResponse {
+            success: true,
+            data: Some(wallet),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Reload history and balances fetched from the settlement server after a restore
+#[tauri::command]
+fn apply_account_snapshot(snapshot: AccountSnapshot) -> ApiResponse<Wallet> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.apply_account_snapshot(snapshot) {
+        Ok(wallet) => ApiResponse {
+            success: true,
+            data: Some(wallet),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Write an encrypted backup of the whole wallet to a file of the exports folder
+#[tauri::command]
+fn export_backup(pin: String, password: String, file_name: String) -> ApiResponse<String> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    let result = engine.export_path(&file_name).and_then(|path| {
+        storage::save_bytes(&path, &engine.export_backup(&pin, &password)?)?;
+        Ok(path)
+    });
+
+    match result {
+        Ok(path) => ApiResponse {
+            success: true,
+            data: Some(path.display().to_string()),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Restore a backup file from the exports folder; payments resume after the account snapshot sync
+#[tauri::command]
+fn import_backup(file_name: String, password: String) -> ApiResponse<Wallet> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    let result = engine.export_path(&file_name)
+        .and_then(|path| storage::load_bytes(&path))
+        .and_then(|data| engine.import_backup(&data, &password));
+
+    match result {
+        Ok(wallet) => ApiResponse {
+            success: true,
+            data: Some(wallet),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Switch to a new signing key; the old key signs the succession record
+#[tauri::command]
+fn rotate_keys(pin: String) -> ApiResponse<KeySuccession> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.rotate_keys(&pin) {
+        Ok(record) => ApiResponse {
+            success: true,
+            data: Some(record),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Key history to publish to peers and the server
+#[tauri::command]
+fn get_key_chain() -> ApiResponse<KeyChain> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    match engine.get_key_chain() {
+        Ok(chain) => ApiResponse {
+            success: true,
+            data: Some(chain),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+// ========== CONTACT COMMANDS ==========
+
+#[tauri::command]
+fn add_contact(nickname: String, wallet_id: String, public_key: String) -> ApiResponse<Contact> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.add_contact(&nickname, &wallet_id, &public_key) {
+        Ok(contact) => ApiResponse {
+            success: true,
+            data: Some(contact),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn rename_contact(wallet_id: String, nickname: String) -> ApiResponse<Contact> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.rename_contact(&wallet_id, &nickname) {
+        Ok(contact) => ApiResponse {
+            success: true,
+            data: Some(contact),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn remove_contact(wallet_id: String) -> ApiResponse<bool> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.remove_contact(&wallet_id) {
+        Ok(removed) => ApiResponse {
+            success: true,
+            data: Some(removed),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn get_contacts() -> ApiResponse<Vec<Contact>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    ApiResponse {
+        success: true,
+        data: Some(engine.get_contacts()),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Look up the wallet behind a nickname before paying
+#[tauri::command]
+fn resolve_contact(nickname: String) -> ApiResponse<Contact> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    match engine.resolve_contact(&nickname) {
+        Ok(contact) => ApiResponse {
+            success: true,
+            data: Some(contact),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Accept or reject the new key a known contact presented
+#[tauri::command]
+fn resolve_contact_key(wallet_id: String, accept: bool) -> ApiResponse<Contact> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.resolve_contact_key(&wallet_id, accept) {
+        Ok(contact) => ApiResponse {
+            success: true,
+            data: Some(contact),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Known contacts that presented a different key
+#[tauri::command]
+fn get_contact_warnings() -> ApiResponse<Vec<KeyChangeWarning>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    ApiResponse {
+        success: true,
+        data: Some(engine.get_contact_warnings()),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Accept a contact's new key when a valid succession chain links it to the known one
+#[tauri::command]
+fn import_key_succession(wallet_id: String, records: Vec<KeySuccession>) -> ApiResponse<Contact> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.import_key_succession(&wallet_id, records) {
+        Ok(contact) => ApiResponse {
+            success: true,
+            data: Some(contact),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+// ========== BUDGET COMMANDS ==========
+
+#[tauri::command]
+fn set_budget(category: String, monthly_limit: u64) -> ApiResponse<Budget> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.set_budget(&category, monthly_limit) {
+        Ok(budget) => ApiResponse {
+            success: true,
+            data: Some(budget),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn remove_budget(category: String) -> ApiResponse<bool> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.remove_budget(&category) {
+        Ok(removed) => ApiResponse {
+            success: true,
+            data: Some(removed),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+#[tauri::command]
+fn get_budgets() -> ApiResponse<Vec<Budget>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    ApiResponse {
+        success: true,
+        data: Some(engine.get_budgets()),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Current month's consumption of every budget
+#[tauri::command]
+fn get_budget_status() -> ApiResponse<Vec<BudgetStatus>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    ApiResponse {
+        success: true,
+        data: Some(engine.budget_status()),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Budget position if a payment of `amount` were made in `category`
+#[tauri::command]
+fn preview_budget(category: String, amount: u64) -> ApiResponse<Option<BudgetStatus>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    ApiResponse {
+        success: true,
+        data: Some(engine.preview_budget(&category, amount)),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+// ========== REVOCATION COMMANDS ==========
 
 #[tauri::command]
-fn initialize_keys() -> ApiResponse<KeyPair> {
+fn import_revocation_list(list: RevocationList) -> ApiResponse<RevocationStatus> {
     let mut engine = BANKING_ENGINE.lock().unwrap();
-    match engine.initialize_keys() {
-        Ok(keys) => ApiResponse {
+    match engine.import_revocation_list(list) {
+        Ok(status) => ApiResponse {
             success: true,
-            data: Some(keys),
+            data: Some(status),
             error: None,
             timestamp: Utc::now().to_rfc3339(),
         },
@@ -402,13 +3695,14 @@ fn initialize_keys() -> ApiResponse<KeyPair> {
     }
 }
 
+/// Apply a revocation update; a version gap means the full list must be fetched
 #[tauri::command]
-fn get_public_key() -> ApiResponse<String> {
-    let engine = BANKING_ENGINE.lock().unwrap();
-    match engine.get_public_key() {
-        Ok(key) => ApiResponse {
+fn apply_revocation_delta(delta: RevocationDelta) -> ApiResponse<RevocationStatus> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.apply_revocation_delta(delta) {
+        Ok(status) => ApiResponse {
             success: true,
-            data: Some(key),
+            data: Some(status),
             error: None,
             timestamp: Utc::now().to_rfc3339(),
         },
@@ -421,13 +3715,27 @@ fn get_public_key() -> ApiResponse<String> {
     }
 }
 
+/// How fresh the offline protection against revoked wallets is
 #[tauri::command]
-fn transfer_to_vault(amount: u64) -> ApiResponse<Wallet> {
+fn revocation_status() -> ApiResponse<RevocationStatus> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    ApiResponse {
+        success: true,
+        data: Some(engine.revocation_status()),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+// ========== MERCHANT COMMANDS ==========
+
+#[tauri::command]
+fn set_merchant_profile(display_name: String, category: String) -> ApiResponse<MerchantProfile> {
     let mut engine = BANKING_ENGINE.lock().unwrap();
-    match engine.transfer_to_vault(amount) {
-        Ok(wallet) => ApiResponse {
+    match engine.set_merchant_profile(display_name, category) {
+        Ok(profile) => ApiResponse {
             success: true,
-            data: Some(wallet),
+            data: Some(profile),
             error: None,
             timestamp: Utc::now().to_rfc3339(),
         },
@@ -441,12 +3749,23 @@ fn transfer_to_vault(amount: u64) -> ApiResponse<Wallet> {
 }
 
 #[tauri::command]
-fn transfer_from_vault(amount: u64) -> ApiResponse<Wallet> {
+fn get_merchant_profile() -> ApiResponse<Option<MerchantProfile>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    ApiResponse {
+        success: true,
+        data: Some(engine.get_merchant_profile()),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+#[tauri::command]
+fn open_till_session() -> ApiResponse<TillSession> {
     let mut engine = BANKING_ENGINE.lock().unwrap();
-    match engine.transfer_from_vault(amount) {
-        Ok(wallet) => ApiResponse {
+    match engine.open_till_session() {
+        Ok(session) => ApiResponse {
             success: true,
-            data: Some(wallet),
+            data: Some(session),
             error: None,
             timestamp: Utc::now().to_rfc3339(),
         },
@@ -460,16 +3779,12 @@ fn transfer_from_vault(amount: u64) -> ApiResponse<Wallet> {
 }
 
 #[tauri::command]
-fn create_offline_transaction(
-    to_wallet_id: String,
-    merchant_name: String,
-    amount: u64,
-) -> ApiResponse<Transaction> {
-    let mut engine = BANKING_ENGINE.lock().unwrap();
-    match engine.create_offline_transaction(to_wallet_id, merchant_name, amount) {
-        Ok(tx) => ApiResponse {
+fn get_current_till_session() -> ApiResponse<TillSession> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    match engine.current_till_session() {
+        Ok(session) => ApiResponse {
             success: true,
-            data: Some(tx),
+            data: Some(session),
             error: None,
             timestamp: Utc::now().to_rfc3339(),
         },
@@ -482,17 +3797,14 @@ fn create_offline_transaction(
     }
 }
 
+/// Close the till and return the Z-report
 #[tauri::command]
-fn create_online_transaction(
-    to_wallet_id: String,
-    merchant_name: String,
-    amount: u64,
-) -> ApiResponse<Transaction> {
+fn close_till_session() -> ApiResponse<ZReport> {
     let mut engine = BANKING_ENGINE.lock().unwrap();
-    match engine.create_online_transaction(to_wallet_id, merchant_name, amount) {
-        Ok(tx) => ApiResponse {
+    match engine.close_till_session() {
+        Ok(report) => ApiResponse {
             success: true,
-            data: Some(tx),
+            data: Some(report),
             error: None,
             timestamp: Utc::now().to_rfc3339(),
         },
@@ -506,12 +3818,24 @@ fn create_online_transaction(
 }
 
 #[tauri::command]
-fn confirm_transaction(tx_id: String) -> ApiResponse<Transaction> {
-    let mut engine = BANKING_ENGINE.lock().unwrap();
-    match engine.confirm_transaction(tx_id) {
-        Ok(tx) => ApiResponse {
+fn get_till_sessions() -> ApiResponse<Vec<TillSession>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    ApiResponse {
+        success: true,
+        data: Some(engine.get_till_sessions()),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Offline payments of a session to upload for settlement once online
+#[tauri::command]
+fn get_till_unsettled_payments(session_id: String) -> ApiResponse<Vec<Transaction>> {
+    let engine = BANKING_ENGINE.lock().unwrap();
+    match engine.till_unsettled_payments(&session_id) {
+        Ok(payments) => ApiResponse {
             success: true,
-            data: Some(tx),
+            data: Some(payments),
             error: None,
             timestamp: Utc::now().to_rfc3339(),
         },
@@ -524,13 +3848,14 @@ fn confirm_transaction(tx_id: String) -> ApiResponse<Transaction> {
     }
 }
 
+/// Apply the server's settlement of a session's offline payments
 #[tauri::command]
-fn cancel_transaction(tx_id: String) -> ApiResponse<Wallet> {
+fn settle_till_session(session_id: String, settled_tx_ids: Vec<String>) -> ApiResponse<TillSession> {
     let mut engine = BANKING_ENGINE.lock().unwrap();
-    match engine.cancel_transaction(tx_id) {
-        Ok(wallet) => ApiResponse {
+    match engine.settle_till_session(&session_id, settled_tx_ids) {
+        Ok(session) => ApiResponse {
             success: true,
-            data: Some(wallet),
+            data: Some(session),
             error: None,
             timestamp: Utc::now().to_rfc3339(),
         },
@@ -543,40 +3868,49 @@ fn cancel_transaction(tx_id: String) -> ApiResponse<Wallet> {
     }
 }
 
+/// Cache verified merchant records fetched from the server
 #[tauri::command]
-fn get_transactions() -> ApiResponse<Vec<Transaction>> {
-    let engine = BANKING_ENGINE.lock().unwrap();
-    let transactions = engine.get_transactions();
-    ApiResponse {
-        success: true,
-        data: Some(transactions),
-        error: None,
-        timestamp: Utc::now().to_rfc3339(),
+fn import_merchant_directory(identities: Vec<MerchantIdentity>) -> ApiResponse<usize> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    match engine.import_merchant_directory(identities) {
+        Ok(stored) => ApiResponse {
+            success: true,
+            data: Some(stored),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
     }
 }
 
 #[tauri::command]
-fn get_wallet_stats() -> ApiResponse<serde_json::Value> {
+fn get_merchant_directory() -> ApiResponse<Vec<MerchantIdentity>> {
     let engine = BANKING_ENGINE.lock().unwrap();
-    let stats = engine.get_stats();
     ApiResponse {
         success: true,
-        data: Some(stats),
+        data: Some(engine.get_merchant_directory()),
         error: None,
         timestamp: Utc::now().to_rfc3339(),
     }
 }
 
+/// Check a payee before paying; warns about unverified or impersonating merchants
 #[tauri::command]
-fn verify_tx_signature(
-    data: String,
-    signature: String,
-) -> ApiResponse<bool> {
+fn check_merchant(
+    wallet_id: String,
+    public_key: Option<String>,
+    merchant_name: String,
+) -> ApiResponse<MerchantCheck> {
     let engine = BANKING_ENGINE.lock().unwrap();
-    match engine.verify_signature(&data, &signature) {
-        Ok(valid) => ApiResponse {
+    match engine.check_merchant(&wallet_id, public_key.as_deref(), &merchant_name) {
+        Ok(check) => ApiResponse {
             success: true,
-            data: Some(valid),
+            data: Some(check),
             error: None,
             timestamp: Utc::now().to_rfc3339(),
         },
@@ -602,12 +3936,10 @@ pub struct P2PTransaction {
     pub status: String,
 }
 
-/// NFC: Check if NFC is available on device
+/// NFC: Check if NFC is present and switched on, as reported by the platform
 #[tauri::command]
 async fn nfc_is_available() -> ApiResponse<bool> {
-    // On Android/iOS: tauri-plugin-nfc will check availability
-    // For now, return true assuming mobile platform
-    let available = cfg!(target_os = "android") || cfg!(target_os = "ios");
+    let available = CAPABILITY_PROVIDER.lock().unwrap().capabilities().nfc_available();
 
     ApiResponse {
         success: true,
@@ -617,14 +3949,49 @@ async fn nfc_is_available() -> ApiResponse<bool> {
     }
 }
 
+/// Hardware state and the P2P channels usable right now
+#[tauri::command]
+fn device_capabilities() -> ApiResponse<DeviceCapabilities> {
+    let capabilities = CAPABILITY_PROVIDER.lock().unwrap().capabilities();
+
+    ApiResponse {
+        success: true,
+        data: Some(capabilities),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Called by the native layer at startup and whenever NFC / Bluetooth state changes
+#[tauri::command]
+fn report_device_status(status: HardwareStatus) -> ApiResponse<DeviceCapabilities> {
+    let mut provider = CAPABILITY_PROVIDER.lock().unwrap();
+    provider.report(status);
+
+    ApiResponse {
+        success: true,
+        data: Some(provider.capabilities()),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Signed payment ready to be written to an NFC tag or sent to a reader
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NfcPayment {
+    pub transaction: Transaction,
+    /// Hex of the canonical `SignedPayment` encoding
+    pub payload: String,
+}
+
+/// NFC: create a signed offline payment and encode it for the NFC plugin
 #[tauri::command]
 async fn nfc_send_transaction(
     receiver_id: String,
+    merchant_name: Option<String>,
     amount: u64,
-) -> ApiResponse<String> {
-    let engine = BANKING_ENGINE.lock().unwrap();
-
-    if amount < 100 || amount > 1_000_000 {
+) -> ApiResponse<NfcPayment> {
+    if !(100..=1_000_000).contains(&amount) {
         return ApiResponse {
             success: false,
             data: None,
@@ -633,85 +4000,276 @@ async fn nfc_send_transaction(
         };
     }
 
-    if amount > engine.wallet.offline_balance {
-        return ApiResponse {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    let payment = engine
+        .create_signed_transaction(
+            receiver_id,
+            merchant_name.unwrap_or_default(),
+            amount,
+            "offline",
+            None,
+            None,
+            Some("nfc".to_string()),
+        )
+        .and_then(|tx| {
+            let wire = engine.signed_payment(&tx.id)?.to_wire()?;
+            Ok(NfcPayment {
+                transaction: tx,
+                payload: hex::encode(wire),
+            })
+        });
+
+    match payment {
+        Ok(payment) => ApiResponse {
+            success: true,
+            data: Some(payment),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
             success: false,
             data: None,
-            error: Some("Solde offline insuffisant".to_string()),
+            error: Some(e),
             timestamp: Utc::now().to_rfc3339(),
-        };
+        },
+    }
+}
+
+/// NFC: receive a signed payment read from a tag or a payer's phone
+#[tauri::command]
+async fn nfc_receive_transaction(payload: String) -> ApiResponse<P2PTransaction> {
+    let mut engine = BANKING_ENGINE.lock().unwrap();
+    let received = hex::decode(payload.trim())
+        .map_err(|_| "Invalid NFC payload".to_string())
+        .and_then(|wire| SignedPayment::from_wire(&wire))
+        .and_then(|payment| {
+            let tx = payment.transaction.clone();
+            engine.receive_signed_payment(payment, Some("nfc".to_string()))?;
+            Ok(P2PTransaction {
+                id: tx.id,
+                sender_wallet_id: tx.from_wallet_id,
+                receiver_wallet_id: tx.to_wallet_id,
+                amount: tx.amount,
+                signature: tx.signature,
+                timestamp: tx.timestamp,
+                status: "confirmed".to_string(),
+            })
+        });
+
+    match received {
+        Ok(tx) => ApiResponse {
+            success: true,
+            data: Some(tx),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
     }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BluetoothDevice {
+    pub id: String,
+    pub name: String,
+    pub rssi: i32,
+    pub merchant_id: Option<String>,
+    pub smoothed_rssi: f64,
+    pub distance_m: f64,
+    pub proximity: Proximity,
+    pub last_seen_ms: i64,
+}
 
-    // Create transaction to write to NFC tag
-    let tx_id = Uuid::new_v4().to_string();
-    let wallet = &engine.wallet;
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdvertisingPayload {
+    pub service_uuid: String,
+    pub merchant_id: String,
+    pub service_data: Vec<u8>,
+}
 
-    // Payload to be written to NFC (handled by frontend plugin)
-    let nfc_payload = serde_json::json!({
-        "tx_id": tx_id.clone(),
-        "sender": wallet.id,
-        "receiver": receiver_id,
-        "amount": amount,
-        "timestamp": Utc::now().to_rfc3339(),
-        "signature": format!("sig_{}", hex::encode(Sha256::digest(format!("{}{}{}", wallet.id, receiver_id, amount).as_bytes())))
-    }).to_string();
+/// Bluetooth: Fluxa devices currently in range, nearest first
+#[tauri::command]
+async fn bluetooth_scan_devices() -> ApiResponse<Vec<BluetoothDevice>> {
+    let devices = DEVICE_TRACKER.lock().unwrap().devices();
 
     ApiResponse {
         success: true,
-        data: Some(format!("NFC transaction prepared: {} | Payload size: {} bytes", tx_id, nfc_payload.len())),
+        data: Some(devices),
         error: None,
         timestamp: Utc::now().to_rfc3339(),
     }
 }
 
+/// Bluetooth: feed advertisements from the platform scanner (tauri-plugin-blec)
 #[tauri::command]
-async fn nfc_receive_transaction() -> ApiResponse<P2PTransaction> {
-    let tx = P2PTransaction {
-        id: Uuid::new_v4().to_string(),
-        sender_wallet_id: "wallet_sender".to_string(),
-        receiver_wallet_id: "wallet_receiver".to_string(),
-        amount: 5000,
-        signature: "sig_placeholder".to_string(),
-        timestamp: Utc::now().to_rfc3339(),
-        status: "pending".to_string(),
-    };
+async fn bluetooth_ingest_advertisements(
+    advertisements: Vec<Advertisement>,
+) -> ApiResponse<Vec<BluetoothDevice>> {
+    let mut tracker = DEVICE_TRACKER.lock().unwrap();
+    for ad in &advertisements {
+        tracker.ingest(ad);
+    }
 
     ApiResponse {
         success: true,
-        data: Some(tx),
+        data: Some(tracker.devices()),
         error: None,
         timestamp: Utc::now().to_rfc3339(),
     }
 }
 
+/// Bluetooth: service data to advertise so payers can find this wallet
+#[tauri::command]
+async fn bluetooth_advertising_payload(display_name: String) -> ApiResponse<AdvertisingPayload> {
+    let public_key = BANKING_ENGINE.lock().unwrap().get_public_key();
+    let payload = public_key.and_then(|key| {
+        let merchant_id = p2p::discovery::short_merchant_id(&key);
+        let service_data = p2p::discovery::encode_service_data(&merchant_id, &display_name)?;
+        Ok(AdvertisingPayload {
+            service_uuid: p2p::discovery::FLUXA_SERVICE_UUID.to_string(),
+            merchant_id,
+            service_data,
+        })
+    });
+
+    match payload {
+        Ok(payload) => ApiResponse {
+            success: true,
+            data: Some(payload),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
-pub struct BluetoothDevice {
-    pub id: String,
-    pub name: String,
-    pub rssi: i32,
+pub struct BleFrameResult {
+    /// Hex of the reassembled message, once complete
+    pub message: Option<String>,
+    /// Hex frames to write back to the peer
+    pub replies: Vec<String>,
 }
 
-/// Bluetooth: Scan for nearby devices
+/// Bluetooth: split a hex message into frames sized for the connection MTU
 #[tauri::command]
-async fn bluetooth_scan_devices() -> ApiResponse<Vec<BluetoothDevice>> {
-    // Simulated BLE scan on mobile
-    // In production: use native Bluetooth APIs (Android: BluetoothAdapter, iOS: CBCentralManager)
-    let devices = vec![
-        BluetoothDevice {
-            id: "device_001".to_string(),
-            name: "📱 Téléphone d'Amenan".to_string(),
-            rssi: -45,
+async fn ble_frame_send(
+    device_id: String,
+    payload: String,
+    mtu: usize,
+    session_id: Option<String>,
+) -> ApiResponse<Vec<String>> {
+    let frames = hex::decode(payload.trim())
+        .map_err(|_| "Invalid payload encoding".to_string())
+        .and_then(|data| ble_frames(&device_id, &data, mtu, session_id.as_deref()));
+
+    match frames {
+        Ok(frames) => ApiResponse {
+            success: true,
+            data: Some(frames),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
         },
-        BluetoothDevice {
-            id: "device_002".to_string(),
-            name: "📱 iPhone de Kofi".to_string(),
-            rssi: -62,
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    }
+}
+
+/// Message size limit for BLE frames: the one negotiated for `session_id`, else the default
+fn ble_message_limit(session_id: Option<&str>) -> Result<usize, String> {
+    match session_id {
+        Some(session_id) => BANKING_ENGINE.lock().unwrap().p2p_max_message_bytes(session_id),
+        None => Ok(DEFAULT_MAX_MESSAGE_BYTES),
+    }
+}
+
+/// Split a message into hex-encoded BLE frames on the channel for `device_id`
+fn ble_frames(device_id: &str, data: &[u8], mtu: usize, session_id: Option<&str>) -> Result<Vec<String>, String> {
+    let limit = ble_message_limit(session_id)?;
+    let mut channels = BLE_CHANNELS.lock().unwrap();
+    if !channels.contains_key(device_id) {
+        channels.insert(device_id.to_string(), FrameChannel::new(mtu, limit)?);
+    }
+    let channel = channels.get_mut(device_id).unwrap();
+    channel.set_mtu(mtu)?;
+    channel.set_max_message_bytes(limit);
+    Ok(channel.send(data)?.into_iter().map(hex::encode).collect())
+}
+
+/// Bluetooth: feed one received frame; returns the message when complete plus frames to answer with
+#[tauri::command]
+async fn ble_frame_receive(
+    device_id: String,
+    frame: String,
+    session_id: Option<String>,
+) -> ApiResponse<BleFrameResult> {
+    let outcome = ble_message_limit(session_id.as_deref()).and_then(|limit| {
+        let raw = hex::decode(frame.trim())
+            .map_err(|_| "Invalid frame encoding".to_string())?;
+        let mut channels = BLE_CHANNELS.lock().unwrap();
+        if !channels.contains_key(&device_id) {
+            channels.insert(device_id.clone(), FrameChannel::new(MIN_MTU, limit)?);
+        }
+        let channel = channels.get_mut(&device_id).unwrap();
+        channel.set_max_message_bytes(limit);
+        channel.receive(&raw)
+    });
+
+    match outcome {
+        Ok(outcome) => ApiResponse {
+            success: true,
+            data: Some(BleFrameResult {
+                message: outcome.message.map(hex::encode),
+                replies: outcome.replies.into_iter().map(hex::encode).collect(),
+            }),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e),
+            timestamp: Utc::now().to_rfc3339(),
         },
-    ];
+    }
+}
+
+/// Bluetooth: after a quiet period, frames requesting the fragments still missing
+#[tauri::command]
+async fn ble_frame_timeout(device_id: String) -> ApiResponse<Vec<String>> {
+    let channels = BLE_CHANNELS.lock().unwrap();
+    let frames = channels.get(&device_id)
+        .map(|c| c.on_timeout().into_iter().map(hex::encode).collect())
+        .unwrap_or_default();
 
     ApiResponse {
         success: true,
-        data: Some(devices),
+        data: Some(frames),
+        error: None,
+        timestamp: Utc::now().to_rfc3339(),
+    }
+}
+
+#[tauri::command]
+async fn ble_frame_close(device_id: String) -> ApiResponse<bool> {
+    let removed = BLE_CHANNELS.lock().unwrap().remove(&device_id).is_some();
+
+    ApiResponse {
+        success: true,
+        data: Some(removed),
         error: None,
         timestamp: Utc::now().to_rfc3339(),
     }
@@ -727,16 +4285,16 @@ async fn bluetooth_connect(_device_id: String) -> ApiResponse<bool> {
     }
 }
 
+/// Bluetooth: start a payment over an established secure channel; returns the frames to write
 #[tauri::command]
 async fn bluetooth_send_transaction(
     device_id: String,
-    _receiver_id: String,
+    channel_id: String,
+    merchant_name: Option<String>,
     amount: u64,
-) -> ApiResponse<String> {
-    let engine = BANKING_ENGINE.lock().unwrap();
-    let wallet = &engine.wallet;
-
-    if amount < 100 || amount > 1_000_000 {
+    mtu: usize,
+) -> ApiResponse<Vec<String>> {
+    if !(100..=1_000_000).contains(&amount) {
         return ApiResponse {
             success: false,
             data: None,
@@ -745,29 +4303,25 @@ async fn bluetooth_send_transaction(
         };
     }
 
-    if amount > wallet.offline_balance {
-        return ApiResponse {
+    let sealed = {
+        let mut engine = BANKING_ENGINE.lock().unwrap();
+        engine.p2p_secure_start_payment(&channel_id, merchant_name.unwrap_or_default(), amount)
+    };
+
+    // The offer goes out before any protocol is negotiated, so the default limit applies
+    match sealed.and_then(|frame| ble_frames(&device_id, &frame, mtu, None)) {
+        Ok(frames) => ApiResponse {
+            success: true,
+            data: Some(frames),
+            error: None,
+            timestamp: Utc::now().to_rfc3339(),
+        },
+        Err(e) => ApiResponse {
             success: false,
             data: None,
-            error: Some("Solde offline insuffisant".to_string()),
+            error: Some(e),
             timestamp: Utc::now().to_rfc3339(),
-        };
-    }
-
-    let tx_id = Uuid::new_v4().to_string();
-    let _payload = serde_json::json!({
-        "tx_id": tx_id.clone(),
-        "sender": wallet.id,
-        "receiver": device_id,
-        "amount": amount,
-        "timestamp": Utc::now().to_rfc3339(),
-    }).to_string();
-
-    ApiResponse {
-        success: true,
-        data: Some(format!("BLE transaction sent: {}", tx_id)),
-        error: None,
-        timestamp: Utc::now().to_rfc3339(),
+        },
     }
 }
 
@@ -777,10 +4331,25 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_blec::init())
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            let mut engine = BANKING_ENGINE.lock().unwrap();
+            engine.set_data_dir(data_dir)?;
+            engine.set_event_sink(Box::new(TauriEventSink(app.handle().clone())));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             init_wallet,
             get_wallet,
             initialize_keys,
+            set_pin,
+            export_seed_phrase,
+            restore_from_mnemonic,
+            apply_account_snapshot,
+            export_backup,
+            import_backup,
+            rotate_keys,
+            get_key_chain,
             get_public_key,
             transfer_to_vault,
             transfer_from_vault,
@@ -789,13 +4358,81 @@ pub fn run() {
             confirm_transaction,
             cancel_transaction,
             get_transactions,
+            query_transactions,
+            annotate_transaction,
+            get_transaction_categories,
+            set_budget,
+            remove_budget,
+            get_budgets,
+            get_budget_status,
+            preview_budget,
             get_wallet_stats,
+            export_history,
+            get_monthly_statement,
             verify_tx_signature,
+            receive_payment,
+            settle_received_payments,
+            import_receipt,
+            get_receipt,
+            get_receipts,
+            verify_receipt,
+            issue_refund,
+            apply_refund,
+            get_refunds,
+            create_payment_request,
+            import_payment_request,
+            pay_payment_request,
+            get_payment_requests,
+            get_issued_payment_requests,
+            qr_encode_payment_request,
+            qr_encode_payment,
+            qr_decode,
+            encode_signed_payment,
+            decode_signed_payment,
+            p2p_start_payment,
+            p2p_capabilities,
+            p2p_get_session,
+            p2p_expire_sessions,
+            p2p_secure_begin,
+            p2p_secure_complete,
+            p2p_secure_seal,
+            p2p_secure_exchange,
+            p2p_secure_close,
+            add_contact,
+            rename_contact,
+            remove_contact,
+            get_contacts,
+            resolve_contact,
+            resolve_contact_key,
+            import_key_succession,
+            get_contact_warnings,
+            set_merchant_profile,
+            get_merchant_profile,
+            open_till_session,
+            get_current_till_session,
+            close_till_session,
+            get_till_sessions,
+            get_till_unsettled_payments,
+            settle_till_session,
+            import_merchant_directory,
+            get_merchant_directory,
+            check_merchant,
+            import_revocation_list,
+            apply_revocation_delta,
+            revocation_status,
             nfc_send_transaction,
             nfc_receive_transaction,
             nfc_is_available,
+            device_capabilities,
+            report_device_status,
             bluetooth_scan_devices,
+            bluetooth_ingest_advertisements,
+            bluetooth_advertising_payload,
             bluetooth_connect,
+            ble_frame_send,
+            ble_frame_receive,
+            ble_frame_timeout,
+            ble_frame_close,
             bluetooth_send_transaction,
         ])
         .run(tauri::generate_context!())
@@ -0,0 +1,240 @@
+// Mode marchand : profil, sessions de caisse et rapport Z de fin de session
+//
+// Une session de caisse regroupe les paiements reçus entre son ouverture et sa
+// fermeture. À la fermeture, un rapport Z fige les totaux (par canal, online/offline,
+// remboursements). Les paiements reçus hors ligne restent "non réglés" jusqu'à ce que
+// le serveur les confirme ; le règlement bascule alors les fonds vers le solde online.
+
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerchantProfile {
+    pub display_name: String,
+    pub category: String,
+    pub created_at: String,
+}
+
+/// One payment received while a till session was open
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TillEntry {
+    pub tx_id: String,
+    pub amount: u64,
+    /// "nfc", "ble", "qr", or "unknown" when the caller did not say
+    pub channel: String,
+    pub tx_type: String,
+    pub received_at: String,
+    pub settled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelTotal {
+    pub channel: String,
+    pub count: usize,
+    pub amount: u64,
+}
+
+/// End-of-session summary, frozen when the till is closed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZReport {
+    pub session_id: String,
+    pub merchant_name: String,
+    pub wallet_id: String,
+    pub opened_at: String,
+    pub closed_at: String,
+    pub payment_count: usize,
+    pub total_amount: u64,
+    pub by_channel: Vec<ChannelTotal>,
+    pub online_amount: u64,
+    pub offline_amount: u64,
+    pub refunded_amount: u64,
+    /// Received amount still waiting for server settlement when the report was built
+    pub unsettled_amount: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TillSession {
+    pub id: String,
+    pub opened_at: String,
+    pub closed_at: Option<String>,
+    /// "open" or "closed"
+    pub status: String,
+    pub entries: Vec<TillEntry>,
+    pub report: Option<ZReport>,
+}
+
+impl TillSession {
+    pub fn open() -> Self {
+        TillSession {
+            id: Uuid::new_v4().to_string(),
+            opened_at: Utc::now().to_rfc3339(),
+            closed_at: None,
+            status: "open".to_string(),
+            entries: Vec::new(),
+            report: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.status == "open"
+    }
+
+    pub fn total_amount(&self) -> u64 {
+        self.entries.iter().map(|e| e.amount).sum()
+    }
+
+    /// Totals per channel, in the order channels were first used
+    pub fn totals_by_channel(&self) -> Vec<ChannelTotal> {
+        let mut totals: Vec<ChannelTotal> = Vec::new();
+        for entry in &self.entries {
+            match totals.iter_mut().find(|t| t.channel == entry.channel) {
+                Some(total) => {
+                    total.count += 1;
+                    total.amount += entry.amount;
+                }
+                None => totals.push(ChannelTotal {
+                    channel: entry.channel.clone(),
+                    count: 1,
+                    amount: entry.amount,
+                }),
+            }
+        }
+        totals
+    }
+
+    pub fn unsettled_amount(&self) -> u64 {
+        self.entries.iter().filter(|e| !e.settled).map(|e| e.amount).sum()
+    }
+
+    /// Close the session and build its Z-report
+    pub fn close(
+        &mut self,
+        merchant_name: &str,
+        wallet_id: &str,
+        refunded_amount: u64,
+    ) -> Result<ZReport, String> {
+        if !self.is_open() {
+            return Err("Till session already closed".to_string());
+        }
+
+        let closed_at = Utc::now().to_rfc3339();
+        let amount_of = |tx_type: &str| -> u64 {
+            self.entries.iter().filter(|e| e.tx_type == tx_type).map(|e| e.amount).sum()
+        };
+
+        let report = ZReport {
+            session_id: self.id.clone(),
+            merchant_name: merchant_name.to_string(),
+            wallet_id: wallet_id.to_string(),
+            opened_at: self.opened_at.clone(),
+            closed_at: closed_at.clone(),
+            payment_count: self.entries.len(),
+            total_amount: self.total_amount(),
+            by_channel: self.totals_by_channel(),
+            online_amount: amount_of("online"),
+            offline_amount: amount_of("offline"),
+            refunded_amount,
+            unsettled_amount: self.unsettled_amount(),
+        };
+
+        self.status = "closed".to_string();
+        self.closed_at = Some(closed_at);
+        self.report = Some(report.clone());
+        Ok(report)
+    }
+}
+
+/// Merchant data persisted next to the wallet
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MerchantState {
+    pub profile: Option<MerchantProfile>,
+    pub sessions: Vec<TillSession>,
+}
+
+impl MerchantState {
+    pub fn open_session(&self) -> Option<&TillSession> {
+        self.sessions.iter().find(|s| s.is_open())
+    }
+
+    pub fn open_session_mut(&mut self) -> Option<&mut TillSession> {
+        self.sessions.iter_mut().find(|s| s.is_open())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    fn entry(tx_id: &str, amount: u64, channel: &str, tx_type: &str) -> TillEntry {
+        TillEntry {
+            tx_id: tx_id.to_string(),
+            amount,
+            channel: channel.to_string(),
+            tx_type: tx_type.to_string(),
+            received_at: Utc::now().to_rfc3339(),
+            settled: tx_type == "online",
+        }
+    }
+
+    #[test]
+    fn z_report_totals_by_channel_and_type() {
+        let mut session = TillSession::open();
+        session.entries = vec![
+            entry("a", 500, "nfc", "offline"),
+            entry("b", 300, "ble", "offline"),
+            entry("c", 200, "nfc", "online"),
+        ];
+
+        let report = session.close("Boutique Awa", "wallet-m", 100).unwrap();
+        assert_eq!(report.payment_count, 3);
+        assert_eq!(report.total_amount, 1000);
+        assert_eq!(report.online_amount, 200);
+        assert_eq!(report.offline_amount, 800);
+        assert_eq!(report.unsettled_amount, 800);
+        assert_eq!(report.refunded_amount, 100);
+        assert_eq!(
+            report.by_channel,
+            vec![
+                ChannelTotal { channel: "nfc".to_string(), count: 2, amount: 700 },
+                ChannelTotal { channel: "ble".to_string(), count: 1, amount: 300 },
+            ]
+        );
+    }
+
+    #[test]
+    fn session_closes_once() {
+        let mut state = MerchantState::default();
+        state.sessions.push(TillSession::open());
+        assert!(state.open_session().is_some());
+
+        let session = state.open_session_mut().unwrap();
+        session.close("Boutique Awa", "wallet-m", 0).unwrap();
+        assert!(session.close("Boutique Awa", "wallet-m", 0).is_err());
+        assert!(state.open_session().is_none());
+        assert!(state.sessions[0].report.is_some());
+    }
+
+    #[test]
+    fn handshake_payment_records_its_transport() {
+        let (mut sender, mut receiver) = (wallet(), wallet());
+        receiver.set_merchant_profile("Boutique Awa".to_string(), "food".to_string()).unwrap();
+        receiver.open_till_session().unwrap();
+
+        let offer = sender.p2p_start_payment(receiver.get_wallet().id, "Boutique Awa".to_string(), 400).unwrap();
+        let accept = receiver.p2p_handle_message(offer, Some("ble".to_string())).unwrap().unwrap();
+        let payment = sender.p2p_handle_message(accept, Some("ble".to_string())).unwrap().unwrap();
+        receiver.p2p_handle_message(payment, Some("ble".to_string())).unwrap().unwrap();
+
+        let till = receiver.current_till_session().unwrap();
+        assert_eq!(till.entries.len(), 1);
+        assert_eq!(till.entries[0].channel, "ble");
+        let received = receiver.transactions.get(&till.entries[0].tx_id).unwrap();
+        assert_eq!(received.channel.as_deref(), Some("ble"));
+
+        let report = receiver.close_till_session().unwrap();
+        assert_eq!(report.by_channel[0].channel, "ble");
+        assert_eq!(report.total_amount, 400);
+    }
+}
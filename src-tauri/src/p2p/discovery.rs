@@ -0,0 +1,289 @@
+// Découverte BLE des appareils Fluxa à proximité
+//
+// Les marchands annoncent le service Fluxa avec des "service data" :
+//   version (1) | identifiant court du marchand (8) | longueur du nom (1) | nom (UTF-8)
+// Le suivi lisse le RSSI (moyenne mobile exponentielle) pour estimer la distance affichée
+// sur le radar, et fusionne les annonces d'un même marchand d'une fenêtre de scan à
+// l'autre, même si l'adresse BLE change (adresses privées tournantes).
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use chrono::Utc;
+
+/// 128-bit UUID of the Fluxa payment GATT service
+pub const FLUXA_SERVICE_UUID: &str = "7d2e0001-5a1f-4c3b-9f6e-8b0c4a2d1e7f";
+const SERVICE_DATA_VERSION: u8 = 1;
+const SHORT_ID_LEN: usize = 8;
+/// Longest display name advertised, to keep the payload small
+const MAX_NAME_LEN: usize = 20;
+
+/// Weight of the newest reading in the smoothed RSSI
+const RSSI_SMOOTHING: f64 = 0.3;
+/// Expected RSSI at one metre, typical for phones
+const TX_POWER_AT_1M: f64 = -59.0;
+const PATH_LOSS_EXPONENT: f64 = 2.0;
+/// Devices not heard from for this long drop off the radar
+pub const DEVICE_TTL_MS: i64 = 15_000;
+
+/// Raw advertisement as reported by the platform scanner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Advertisement {
+    pub address: String,
+    pub local_name: Option<String>,
+    pub rssi: i32,
+    #[serde(default)]
+    pub service_uuids: Vec<String>,
+    #[serde(default)]
+    pub service_data: HashMap<String, Vec<u8>>,
+    /// Capture time; defaults to the time of ingestion
+    pub seen_at_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FluxaServiceData {
+    pub merchant_id: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Proximity {
+    Immediate,
+    Near,
+    Far,
+}
+
+/// Short merchant identifier advertised over BLE, derived from the wallet public key
+pub fn short_merchant_id(public_key: &str) -> String {
+    hex::encode(&Sha256::digest(public_key.as_bytes())[..SHORT_ID_LEN])
+}
+
+/// Build our advertisement service data; the name is truncated to fit
+pub fn encode_service_data(merchant_id: &str, display_name: &str) -> Result<Vec<u8>, String> {
+    let id = hex::decode(merchant_id).map_err(|_| "Invalid merchant ID".to_string())?;
+    if id.len() != SHORT_ID_LEN {
+        return Err("Invalid merchant ID length".to_string());
+    }
+
+    let mut name_end = display_name.len().min(MAX_NAME_LEN);
+    while !display_name.is_char_boundary(name_end) {
+        name_end -= 1;
+    }
+    let name = &display_name.as_bytes()[..name_end];
+
+    let mut data = Vec::with_capacity(2 + SHORT_ID_LEN + name.len());
+    data.push(SERVICE_DATA_VERSION);
+    data.extend_from_slice(&id);
+    data.push(name.len() as u8);
+    data.extend_from_slice(name);
+    Ok(data)
+}
+
+pub fn decode_service_data(data: &[u8]) -> Option<FluxaServiceData> {
+    let (&version, rest) = data.split_first()?;
+    if version != SERVICE_DATA_VERSION || rest.len() < SHORT_ID_LEN + 1 {
+        return None;
+    }
+
+    let (id, rest) = rest.split_at(SHORT_ID_LEN);
+    let (&name_len, rest) = rest.split_first()?;
+    let name = rest.get(..name_len as usize)?;
+
+    Some(FluxaServiceData {
+        merchant_id: hex::encode(id),
+        display_name: String::from_utf8_lossy(name).into_owned(),
+    })
+}
+
+fn is_fluxa_uuid(uuid: &str) -> bool {
+    uuid.eq_ignore_ascii_case(FLUXA_SERVICE_UUID)
+}
+
+pub fn estimate_distance_m(rssi: f64) -> f64 {
+    10f64.powf((TX_POWER_AT_1M - rssi) / (10.0 * PATH_LOSS_EXPONENT))
+}
+
+pub fn proximity_for(rssi: f64) -> Proximity {
+    if rssi >= -55.0 {
+        Proximity::Immediate
+    } else if rssi >= -70.0 {
+        Proximity::Near
+    } else {
+        Proximity::Far
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TrackedDevice {
+    address: String,
+    merchant_id: Option<String>,
+    name: String,
+    last_rssi: i32,
+    smoothed_rssi: f64,
+    last_seen_ms: i64,
+}
+
+/// Keeps Fluxa devices seen across scan windows
+#[derive(Default)]
+pub struct DeviceTracker {
+    devices: HashMap<String, TrackedDevice>,
+}
+
+impl DeviceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one advertisement; non-Fluxa devices are ignored. Returns whether it was kept.
+    pub fn ingest(&mut self, ad: &Advertisement) -> bool {
+        let service_data = ad.service_data.iter()
+            .find(|(uuid, _)| is_fluxa_uuid(uuid))
+            .and_then(|(_, data)| decode_service_data(data));
+
+        let advertises_service = ad.service_uuids.iter().any(|u| is_fluxa_uuid(u));
+        if service_data.is_none() && !advertises_service {
+            return false;
+        }
+
+        let seen_at = ad.seen_at_ms.unwrap_or_else(|| Utc::now().timestamp_millis());
+        let merchant_id = service_data.as_ref().map(|d| d.merchant_id.clone());
+        let name = service_data.as_ref()
+            .map(|d| d.display_name.clone())
+            .filter(|n| !n.is_empty())
+            .or_else(|| ad.local_name.clone())
+            .unwrap_or_else(|| ad.address.clone());
+
+        // Merchants are keyed by their advertised ID so rotating addresses merge
+        let key = match &merchant_id {
+            Some(id) => format!("m:{}", id),
+            None => format!("a:{}", ad.address),
+        };
+
+        match self.devices.get_mut(&key) {
+            Some(device) => {
+                // A stale reading says little about where the device is now
+                device.smoothed_rssi = if seen_at.saturating_sub(device.last_seen_ms) > DEVICE_TTL_MS {
+                    ad.rssi as f64
+                } else {
+                    RSSI_SMOOTHING * ad.rssi as f64 + (1.0 - RSSI_SMOOTHING) * device.smoothed_rssi
+                };
+                device.address = ad.address.clone();
+                device.name = name;
+                device.last_rssi = ad.rssi;
+                device.last_seen_ms = device.last_seen_ms.max(seen_at);
+            }
+            None => {
+                self.devices.insert(key, TrackedDevice {
+                    address: ad.address.clone(),
+                    merchant_id,
+                    name,
+                    last_rssi: ad.rssi,
+                    smoothed_rssi: ad.rssi as f64,
+                    last_seen_ms: seen_at,
+                });
+            }
+        }
+
+        true
+    }
+
+    /// Devices heard within the TTL, nearest first
+    pub fn devices_at(&mut self, now_ms: i64) -> Vec<crate::BluetoothDevice> {
+        self.devices.retain(|_, d| now_ms.saturating_sub(d.last_seen_ms) <= DEVICE_TTL_MS);
+
+        let mut devices: Vec<crate::BluetoothDevice> = self.devices.values()
+            .map(|d| crate::BluetoothDevice {
+                id: d.address.clone(),
+                name: d.name.clone(),
+                rssi: d.last_rssi,
+                merchant_id: d.merchant_id.clone(),
+                smoothed_rssi: d.smoothed_rssi,
+                distance_m: estimate_distance_m(d.smoothed_rssi),
+                proximity: proximity_for(d.smoothed_rssi),
+                last_seen_ms: d.last_seen_ms,
+            })
+            .collect();
+
+        devices.sort_by(|a, b| b.smoothed_rssi.total_cmp(&a.smoothed_rssi));
+        devices
+    }
+
+    pub fn devices(&mut self) -> Vec<crate::BluetoothDevice> {
+        self.devices_at(Utc::now().timestamp_millis())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Advertisements recorded from two merchant phones and a headset
+    const RECORDED: &str = include_str!("testdata/advertisements.json");
+
+    fn recorded() -> Vec<Advertisement> {
+        serde_json::from_str(RECORDED).unwrap()
+    }
+
+    #[test]
+    fn service_data_round_trip() {
+        let id = short_merchant_id("8a88e3dd7409f195fd52db2d3cba5d72ca6709bf1d94121bf3748801b40f6f5c");
+        let data = encode_service_data(&id, "Boutique Chez Amenan et Fils").unwrap();
+        let decoded = decode_service_data(&data).unwrap();
+        assert_eq!(decoded.merchant_id, id);
+        assert_eq!(decoded.display_name, "Boutique Chez Amenan");
+
+        assert!(decode_service_data(&data[..5]).is_none());
+        assert!(encode_service_data("abcd", "Shop").is_err());
+    }
+
+    #[test]
+    fn name_is_cut_on_a_char_boundary() {
+        let id = "0011223344556677";
+        let data = encode_service_data(id, "Café Épicerie Fine Étoile").unwrap();
+        let name = decode_service_data(&data).unwrap().display_name;
+        assert!(name.len() <= MAX_NAME_LEN);
+        assert!("Café Épicerie Fine Étoile".starts_with(&name));
+    }
+
+    #[test]
+    fn recorded_scan_keeps_fluxa_devices_only() {
+        let mut tracker = DeviceTracker::new();
+        let kept = recorded().iter().filter(|ad| tracker.ingest(ad)).count();
+        assert_eq!(kept, 5);
+
+        let devices = tracker.devices_at(1_700_000_004_000);
+        assert_eq!(devices.len(), 2);
+        // The merchant changed address between scans but stays one device
+        assert_eq!(devices[0].merchant_id.as_deref(), Some("0011223344556677"));
+        assert_eq!(devices[0].name, "Chez Amenan");
+        assert_eq!(devices[0].id, "5E:21:9A:00:10:02");
+        assert!(devices[0].smoothed_rssi > devices[1].smoothed_rssi);
+        assert_eq!(devices[1].merchant_id, None);
+        assert_eq!(devices[1].name, "Fluxa POS");
+    }
+
+    #[test]
+    fn rssi_is_smoothed_and_stale_devices_expire() {
+        let mut tracker = DeviceTracker::new();
+        let mut ad = recorded().remove(0);
+        ad.rssi = -50;
+        tracker.ingest(&ad);
+        ad.rssi = -80;
+        ad.seen_at_ms = ad.seen_at_ms.map(|t| t + 1000);
+        tracker.ingest(&ad);
+
+        let seen = ad.seen_at_ms.unwrap();
+        let device = tracker.devices_at(seen).remove(0);
+        assert_eq!(device.rssi, -80);
+        assert!((device.smoothed_rssi - (-59.0)).abs() < 1e-9);
+        assert_eq!(device.proximity, Proximity::Near);
+
+        // Out-of-order timestamps must not underflow
+        ad.seen_at_ms = Some(i64::MIN);
+        tracker.ingest(&ad);
+        assert_eq!(tracker.devices_at(i64::MIN).len(), 1);
+
+        assert!(tracker.devices_at(seen + DEVICE_TTL_MS + 1).is_empty());
+    }
+}
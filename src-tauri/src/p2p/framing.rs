@@ -0,0 +1,550 @@
+// Découpage fiable des messages P2P pour les écritures BLE
+//
+// Un message est découpé en trames qui tiennent dans le MTU négocié. Chaque trame :
+//   type (1) | id du message (2) | corps | CRC32 (4)
+// avec pour corps :
+//   - Data : index (2) | total (2) | fragment
+//   - Nack : liste des index manquants (2 chacun)
+//   - Ack  : vide
+// Le récepteur accuse réception d'un message complet (Ack) ou réclame les fragments
+// manquants (Nack) quand l'appelant lui signale un délai dépassé ; l'émetteur garde
+// les trames d'un message jusqu'à son Ack pour pouvoir les retransmettre.
+//
+// Ce module ne dépend pas de tauri-plugin-blec : il ne manipule que des octets.
+
+use std::collections::HashMap;
+
+use super::transport::Transport;
+
+const KIND_DATA: u8 = 0;
+const KIND_NACK: u8 = 1;
+const KIND_ACK: u8 = 2;
+const HEADER_LEN: usize = 3;
+const DATA_HEADER_LEN: usize = HEADER_LEN + 4;
+const CRC_LEN: usize = 4;
+/// ATT header bytes taken out of every BLE write
+const ATT_OVERHEAD: usize = 3;
+/// Smallest MTU every BLE stack supports
+pub const MIN_MTU: usize = 23;
+/// Default cap on a reassembled message
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 64 * 1024;
+/// Messages a channel keeps in flight per direction (being reassembled, or awaiting an Ack)
+pub const MAX_IN_FLIGHT: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Data {
+        message_id: u16,
+        index: u16,
+        total: u16,
+        payload: Vec<u8>,
+    },
+    Nack {
+        message_id: u16,
+        missing: Vec<u16>,
+    },
+    Ack {
+        message_id: u16,
+    },
+}
+
+impl Frame {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Frame::Data { message_id, index, total, payload } => {
+                out.push(KIND_DATA);
+                out.extend_from_slice(&message_id.to_be_bytes());
+                out.extend_from_slice(&index.to_be_bytes());
+                out.extend_from_slice(&total.to_be_bytes());
+                out.extend_from_slice(payload);
+            }
+            Frame::Nack { message_id, missing } => {
+                out.push(KIND_NACK);
+                out.extend_from_slice(&message_id.to_be_bytes());
+                for index in missing {
+                    out.extend_from_slice(&index.to_be_bytes());
+                }
+            }
+            Frame::Ack { message_id } => {
+                out.push(KIND_ACK);
+                out.extend_from_slice(&message_id.to_be_bytes());
+            }
+        }
+        let crc = crc32fast::hash(&out);
+        out.extend_from_slice(&crc.to_be_bytes());
+        out
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Frame, String> {
+        if data.len() < HEADER_LEN + CRC_LEN {
+            return Err("Frame too short".to_string());
+        }
+
+        let (body, crc) = data.split_at(data.len() - CRC_LEN);
+        if crc32fast::hash(body).to_be_bytes() != crc {
+            return Err("Frame CRC mismatch".to_string());
+        }
+
+        let u16_at = |i: usize| u16::from_be_bytes([body[i], body[i + 1]]);
+        let message_id = u16_at(1);
+
+        match body[0] {
+            KIND_DATA => {
+                if body.len() < DATA_HEADER_LEN {
+                    return Err("Data frame too short".to_string());
+                }
+                let index = u16_at(3);
+                let total = u16_at(5);
+                if total == 0 || index >= total {
+                    return Err("Invalid fragment index".to_string());
+                }
+                Ok(Frame::Data {
+                    message_id,
+                    index,
+                    total,
+                    payload: body[DATA_HEADER_LEN..].to_vec(),
+                })
+            }
+            KIND_NACK => {
+                let list = &body[HEADER_LEN..];
+                if list.len() % 2 != 0 {
+                    return Err("Malformed NACK frame".to_string());
+                }
+                let missing = list.chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+                Ok(Frame::Nack { message_id, missing })
+            }
+            KIND_ACK if body.len() == HEADER_LEN => Ok(Frame::Ack { message_id }),
+            kind => Err(format!("Unknown frame type: {}", kind)),
+        }
+    }
+}
+
+/// Fragment payload bytes available in one write for a given MTU
+pub fn chunk_size(mtu: usize) -> Result<usize, String> {
+    if mtu < MIN_MTU {
+        return Err(format!("MTU too small: {} (minimum {})", mtu, MIN_MTU));
+    }
+    Ok(mtu - ATT_OVERHEAD - DATA_HEADER_LEN - CRC_LEN)
+}
+
+/// Split a message into encoded data frames
+pub fn fragment(
+    message_id: u16,
+    data: &[u8],
+    mtu: usize,
+    max_message_bytes: usize,
+) -> Result<Vec<Vec<u8>>, String> {
+    if data.len() > max_message_bytes {
+        return Err(format!(
+            "Message too large: {} bytes (limit {})",
+            data.len(),
+            max_message_bytes
+        ));
+    }
+
+    let size = chunk_size(mtu)?;
+    let chunks: Vec<&[u8]> = if data.is_empty() {
+        vec![&[]]
+    } else {
+        data.chunks(size).collect()
+    };
+
+    if chunks.len() > u16::MAX as usize {
+        return Err("Message needs too many frames".to_string());
+    }
+
+    let total = chunks.len() as u16;
+    Ok(chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            Frame::Data {
+                message_id,
+                index: index as u16,
+                total,
+                payload: chunk.to_vec(),
+            }
+            .encode()
+        })
+        .collect())
+}
+
+struct Partial {
+    chunks: Vec<Option<Vec<u8>>>,
+    bytes: usize,
+}
+
+/// Result of feeding one incoming frame
+#[derive(Debug, Default)]
+pub struct FrameOutcome {
+    /// Complete message, once every fragment has arrived
+    pub message: Option<Vec<u8>>,
+    /// Frames to write back to the peer (acks, retransmissions)
+    pub replies: Vec<Vec<u8>>,
+}
+
+/// Framing state for one peer connection, in both directions
+pub struct FrameChannel {
+    mtu: usize,
+    max_message_bytes: usize,
+    next_message_id: u16,
+    unacked: HashMap<u16, Vec<Vec<u8>>>,
+    incoming: HashMap<u16, Partial>,
+    completed: Vec<u16>,
+}
+
+impl FrameChannel {
+    pub fn new(mtu: usize, max_message_bytes: usize) -> Result<Self, String> {
+        chunk_size(mtu)?;
+        Ok(FrameChannel {
+            mtu,
+            max_message_bytes,
+            // A fresh channel must not reuse ids the peer still remembers as completed
+            next_message_id: rand::random(),
+            unacked: HashMap::new(),
+            incoming: HashMap::new(),
+            completed: Vec::new(),
+        })
+    }
+
+    /// Update the MTU after a new negotiation; applies to messages sent from now on
+    pub fn set_mtu(&mut self, mtu: usize) -> Result<(), String> {
+        chunk_size(mtu)?;
+        self.mtu = mtu;
+        Ok(())
+    }
+
+    /// Apply the message size limit negotiated for the session
+    pub fn set_max_message_bytes(&mut self, max_message_bytes: usize) {
+        self.max_message_bytes = max_message_bytes;
+    }
+
+    /// Frames to write for a new message; they are kept until the peer acknowledges it
+    pub fn send(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        if self.unacked.len() >= MAX_IN_FLIGHT {
+            return Err("Too many unacknowledged messages".to_string());
+        }
+
+        let message_id = self.next_message_id;
+        let frames = fragment(message_id, data, self.mtu, self.max_message_bytes)?;
+        self.next_message_id = self.next_message_id.wrapping_add(1);
+        self.unacked.insert(message_id, frames.clone());
+        Ok(frames)
+    }
+
+    pub fn receive(&mut self, raw: &[u8]) -> Result<FrameOutcome, String> {
+        let mut outcome = FrameOutcome::default();
+
+        match Frame::decode(raw)? {
+            Frame::Data { message_id, index, total, payload } => {
+                // Duplicate fragments of a finished message mean our Ack was lost
+                if self.completed.contains(&message_id) {
+                    outcome.replies.push(Frame::Ack { message_id }.encode());
+                    return Ok(outcome);
+                }
+
+                // Every fragment but an empty message's carries at least one byte
+                if total as usize > self.max_message_bytes.max(1) {
+                    return Err("Incoming message exceeds size limit".to_string());
+                }
+
+                if !self.incoming.contains_key(&message_id) && self.incoming.len() >= MAX_IN_FLIGHT {
+                    return Err("Too many incoming messages in flight".to_string());
+                }
+
+                let partial = self.incoming.entry(message_id).or_insert_with(|| Partial {
+                    chunks: vec![None; total as usize],
+                    bytes: 0,
+                });
+
+                if partial.chunks.len() != total as usize {
+                    self.incoming.remove(&message_id);
+                    return Err("Inconsistent fragment count".to_string());
+                }
+
+                let slot = &mut partial.chunks[index as usize];
+                if slot.is_none() {
+                    partial.bytes += payload.len();
+                    *slot = Some(payload);
+                }
+
+                if partial.bytes > self.max_message_bytes {
+                    self.incoming.remove(&message_id);
+                    return Err("Incoming message exceeds size limit".to_string());
+                }
+
+                let complete = partial.chunks.iter().all(|c| c.is_some());
+                if let Some(partial) = complete.then(|| self.incoming.remove(&message_id)).flatten() {
+                    let message: Vec<u8> = partial.chunks.into_iter().flatten().flatten().collect();
+
+                    self.completed.push(message_id);
+                    if self.completed.len() > 32 {
+                        self.completed.remove(0);
+                    }
+
+                    outcome.replies.push(Frame::Ack { message_id }.encode());
+                    outcome.message = Some(message);
+                }
+            }
+            Frame::Nack { message_id, missing } => {
+                if let Some(frames) = self.unacked.get(&message_id) {
+                    outcome.replies.extend(
+                        missing.iter().filter_map(|i| frames.get(*i as usize)).cloned(),
+                    );
+                }
+            }
+            Frame::Ack { message_id } => {
+                self.unacked.remove(&message_id);
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    /// Called when the link went quiet: ask for the fragments still missing
+    pub fn on_timeout(&self) -> Vec<Vec<u8>> {
+        self.incoming
+            .iter()
+            .map(|(message_id, partial)| {
+                let missing = partial.chunks.iter()
+                    .enumerate()
+                    .filter(|(_, c)| c.is_none())
+                    .map(|(i, _)| i as u16)
+                    .collect();
+                Frame::Nack { message_id: *message_id, missing }.encode()
+            })
+            .collect()
+    }
+
+    /// Resend everything not yet acknowledged, e.g. after a reconnect
+    pub fn pending_frames(&self) -> Vec<Vec<u8>> {
+        let mut ids: Vec<&u16> = self.unacked.keys().collect();
+        ids.sort();
+        ids.into_iter().flat_map(|id| self.unacked[id].clone()).collect()
+    }
+}
+
+/// Message transport over a frame-level link (one BLE write per frame)
+pub struct FramedTransport<T: Transport> {
+    link: T,
+    channel: FrameChannel,
+}
+
+impl<T: Transport> FramedTransport<T> {
+    pub fn new(link: T, mtu: usize, max_message_bytes: usize) -> Result<Self, String> {
+        Ok(FramedTransport {
+            link,
+            channel: FrameChannel::new(mtu, max_message_bytes)?,
+        })
+    }
+
+    /// Ask the peer for missing fragments after a quiet period
+    pub fn on_timeout(&mut self) -> Result<(), String> {
+        for frame in self.channel.on_timeout() {
+            self.link.send(&frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transport> Transport for FramedTransport<T> {
+    fn send(&mut self, data: &[u8]) -> Result<(), String> {
+        for frame in self.channel.send(data)? {
+            self.link.send(&frame)?;
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Result<Option<Vec<u8>>, String> {
+        while let Some(raw) = self.link.recv()? {
+            // A corrupted frame is dropped; the sender retransmits it on NACK
+            let outcome = match self.channel.receive(&raw) {
+                Ok(outcome) => outcome,
+                Err(_) => continue,
+            };
+            for reply in &outcome.replies {
+                self.link.send(reply)?;
+            }
+            if outcome.message.is_some() {
+                return Ok(outcome.message);
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p2p::transport::MemoryTransport;
+
+    fn data_frames(frames: &[Vec<u8>]) -> Vec<Frame> {
+        frames.iter().map(|f| Frame::decode(f).unwrap()).collect()
+    }
+
+    fn message_id_of(raw: &[u8]) -> u16 {
+        match Frame::decode(raw).unwrap() {
+            Frame::Data { message_id, .. }
+            | Frame::Nack { message_id, .. }
+            | Frame::Ack { message_id } => message_id,
+        }
+    }
+
+    #[test]
+    fn frames_roundtrip() {
+        let frames = [
+            Frame::Data { message_id: 7, index: 1, total: 3, payload: vec![1, 2, 3] },
+            Frame::Nack { message_id: 7, missing: vec![0, 2] },
+            Frame::Ack { message_id: 7 },
+        ];
+        for frame in frames {
+            assert_eq!(Frame::decode(&frame.encode()).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn corrupted_frame_is_rejected() {
+        let mut raw = Frame::Ack { message_id: 1 }.encode();
+        raw[1] ^= 0xff;
+        assert!(Frame::decode(&raw).is_err());
+    }
+
+    #[test]
+    fn fragments_fit_the_mtu() {
+        let data = vec![0xab; 100];
+        let frames = fragment(1, &data, MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        assert!(frames.iter().all(|f| f.len() <= MIN_MTU - ATT_OVERHEAD));
+        assert_eq!(frames.len(), data.len().div_ceil(chunk_size(MIN_MTU).unwrap()));
+    }
+
+    #[test]
+    fn oversized_message_is_refused() {
+        assert!(fragment(1, &[0; 11], 64, 10).is_err());
+        assert!(FrameChannel::new(MIN_MTU - 1, 10).is_err());
+    }
+
+    #[test]
+    fn out_of_order_fragments_reassemble_and_ack() {
+        let mut sender = FrameChannel::new(MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        let mut receiver = FrameChannel::new(MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        let data: Vec<u8> = (0..60).collect();
+
+        let mut frames = sender.send(&data).unwrap();
+        frames.reverse();
+        let mut message = None;
+        let mut replies = Vec::new();
+        for frame in &frames {
+            let outcome = receiver.receive(frame).unwrap();
+            message = message.or(outcome.message);
+            replies.extend(outcome.replies);
+        }
+        assert_eq!(message, Some(data));
+
+        assert!(!sender.pending_frames().is_empty());
+        for reply in replies {
+            sender.receive(&reply).unwrap();
+        }
+        assert!(sender.pending_frames().is_empty());
+    }
+
+    #[test]
+    fn nack_retransmits_missing_fragments() {
+        let mut sender = FrameChannel::new(MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        let mut receiver = FrameChannel::new(MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        let data: Vec<u8> = (0..40).collect();
+        let frames = sender.send(&data).unwrap();
+
+        // Le deuxième fragment se perd
+        for (i, frame) in frames.iter().enumerate() {
+            if i != 1 {
+                assert!(receiver.receive(frame).unwrap().message.is_none());
+            }
+        }
+
+        let nacks = receiver.on_timeout();
+        assert_eq!(nacks.len(), 1);
+        let resent = sender.receive(&nacks[0]).unwrap().replies;
+        assert_eq!(data_frames(&resent), data_frames(&frames[1..2]));
+
+        let outcome = receiver.receive(&resent[0]).unwrap();
+        assert_eq!(outcome.message, Some(data));
+    }
+
+    #[test]
+    fn duplicate_after_completion_is_acked_not_redelivered() {
+        let mut sender = FrameChannel::new(MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        let mut receiver = FrameChannel::new(MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        let frames = sender.send(b"pay").unwrap();
+
+        assert!(receiver.receive(&frames[0]).unwrap().message.is_some());
+        let again = receiver.receive(&frames[0]).unwrap();
+        assert!(again.message.is_none());
+        let message_id = message_id_of(&frames[0]);
+        assert_eq!(data_frames(&again.replies), vec![Frame::Ack { message_id }]);
+    }
+
+    #[test]
+    fn unacked_messages_are_capped() {
+        let mut sender = FrameChannel::new(MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        for _ in 0..MAX_IN_FLIGHT {
+            sender.send(b"pay").unwrap();
+        }
+        assert!(sender.send(b"pay").is_err());
+    }
+
+    #[test]
+    fn incoming_partial_messages_are_capped() {
+        let mut receiver = FrameChannel::new(MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        let first_fragment = |message_id| {
+            Frame::Data { message_id, index: 0, total: 2, payload: vec![1] }.encode()
+        };
+
+        for message_id in 0..MAX_IN_FLIGHT as u16 {
+            receiver.receive(&first_fragment(message_id)).unwrap();
+        }
+        assert!(receiver.receive(&first_fragment(MAX_IN_FLIGHT as u16)).is_err());
+
+        // Un message déjà en cours peut toujours se terminer
+        let last = Frame::Data { message_id: 0, index: 1, total: 2, payload: vec![2] }.encode();
+        assert_eq!(receiver.receive(&last).unwrap().message, Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn new_channels_do_not_restart_at_the_same_id() {
+        let first_id = || {
+            let mut channel = FrameChannel::new(MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+            message_id_of(&channel.send(b"pay").unwrap()[0])
+        };
+        let ids: std::collections::HashSet<u16> = (0..8).map(|_| first_id()).collect();
+        assert!(ids.len() > 1);
+    }
+
+    #[test]
+    fn negotiated_limit_applies_to_both_directions() {
+        let mut sender = FrameChannel::new(MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        let mut receiver = FrameChannel::new(MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        let frames = sender.send(&[7u8; 100]).unwrap();
+
+        sender.set_max_message_bytes(64);
+        receiver.set_max_message_bytes(64);
+        assert!(sender.send(&[7u8; 100]).is_err());
+        assert!(sender.send(&[7u8; 64]).is_ok());
+        assert!(frames.iter().any(|f| receiver.receive(f).is_err()));
+    }
+
+    #[test]
+    fn framed_transport_carries_messages_over_memory_link() {
+        let (a, b) = MemoryTransport::pair();
+        let mut alice = FramedTransport::new(a, MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        let mut bob = FramedTransport::new(b, MIN_MTU, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        let data: Vec<u8> = (0..200).map(|i| i as u8).collect();
+
+        alice.send(&data).unwrap();
+        assert_eq!(bob.recv().unwrap(), Some(data));
+
+        // L'Ack renvoyé par bob libère le message côté alice
+        assert_eq!(alice.recv().unwrap(), None);
+        assert!(alice.channel.pending_frames().is_empty());
+    }
+}
@@ -0,0 +1,293 @@
+// Protocole de paiement en deux phases entre deux téléphones :
+//   Offer -> Accept -> Payment (signé par le payeur) -> Ack (reçu contresigné par le destinataire)
+//
+// Le payeur ne considère le débit comme définitif qu'à réception de l'Ack.
+// Une session interrompue se résout toujours de la même façon des deux côtés :
+// - avant l'envoi du paiement, rien n'a été débité ni crédité : la session est annulée ;
+// - après l'envoi du paiement sans Ack, le payeur garde la transaction en attente
+//   ("unresolved") jusqu'au règlement serveur, et le destinataire renvoie le même Ack
+//   si le payeur rejoue le paiement.
+
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use super::version::{NegotiatedProtocol, PeerCapabilities};
+use super::SignedPayment;
+use crate::PaymentReceipt;
+
+/// Time allowed for the peer to answer each step
+pub const STEP_TIMEOUT_SECS: i64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HandshakeMessage {
+    Offer {
+        session_id: String,
+        sender_wallet_id: String,
+        receiver_wallet_id: String,
+        merchant_name: String,
+        amount: u64,
+        #[serde(default)]
+        capabilities: PeerCapabilities,
+    },
+    Accept {
+        session_id: String,
+        receiver_wallet_id: String,
+        #[serde(default)]
+        capabilities: PeerCapabilities,
+        /// Version the receiver picked; the sender must reach the same result
+        #[serde(default)]
+        protocol_version: u16,
+    },
+    Reject {
+        session_id: String,
+        reason: String,
+    },
+    Payment {
+        session_id: String,
+        payment: SignedPayment,
+    },
+    Ack {
+        session_id: String,
+        receipt: PaymentReceipt,
+    },
+}
+
+impl HandshakeMessage {
+    pub fn session_id(&self) -> &str {
+        match self {
+            HandshakeMessage::Offer { session_id, .. }
+            | HandshakeMessage::Accept { session_id, .. }
+            | HandshakeMessage::Reject { session_id, .. }
+            | HandshakeMessage::Payment { session_id, .. }
+            | HandshakeMessage::Ack { session_id, .. } => session_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandshakeRole {
+    Sender,
+    Receiver,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandshakeState {
+    /// Sender sent an offer and waits for Accept
+    Offered,
+    /// Receiver accepted and waits for the signed payment
+    Accepted,
+    /// Sender sent the signed payment and waits for Ack
+    PaymentSent,
+    Completed,
+    /// Ended before any funds moved
+    Aborted,
+    /// Sender sent the payment but never got the Ack; settled by the server
+    Unresolved,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeSession {
+    pub id: String,
+    pub role: HandshakeRole,
+    pub state: HandshakeState,
+    pub counterparty_wallet_id: String,
+    pub merchant_name: String,
+    pub amount: u64,
+    pub tx_id: Option<String>,
+    pub created_at: String,
+    pub deadline: String,
+    pub failure: Option<String>,
+    pub protocol: Option<NegotiatedProtocol>,
+}
+
+fn deadline_from_now() -> String {
+    (Utc::now() + Duration::seconds(STEP_TIMEOUT_SECS)).to_rfc3339()
+}
+
+impl HandshakeSession {
+    pub fn new_sender(receiver_wallet_id: String, merchant_name: String, amount: u64) -> Self {
+        HandshakeSession {
+            id: Uuid::new_v4().to_string(),
+            role: HandshakeRole::Sender,
+            state: HandshakeState::Offered,
+            counterparty_wallet_id: receiver_wallet_id,
+            merchant_name,
+            amount,
+            tx_id: None,
+            created_at: Utc::now().to_rfc3339(),
+            deadline: deadline_from_now(),
+            failure: None,
+            protocol: None,
+        }
+    }
+
+    pub fn new_receiver(
+        session_id: String,
+        sender_wallet_id: String,
+        merchant_name: String,
+        amount: u64,
+    ) -> Self {
+        HandshakeSession {
+            id: session_id,
+            role: HandshakeRole::Receiver,
+            state: HandshakeState::Accepted,
+            counterparty_wallet_id: sender_wallet_id,
+            merchant_name,
+            amount,
+            tx_id: None,
+            created_at: Utc::now().to_rfc3339(),
+            deadline: deadline_from_now(),
+            failure: None,
+            protocol: None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.state,
+            HandshakeState::Completed | HandshakeState::Aborted | HandshakeState::Unresolved
+        )
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match DateTime::parse_from_rfc3339(&self.deadline) {
+            Ok(deadline) => deadline.with_timezone(&Utc) <= Utc::now(),
+            Err(_) => true,
+        }
+    }
+
+    /// Move to `next`, rejecting transitions the protocol does not allow
+    pub fn advance(&mut self, next: HandshakeState) -> Result<(), String> {
+        use HandshakeState::*;
+
+        // A late Ack still completes an unresolved payment
+        let allowed = matches!(
+            (self.role, self.state, next),
+            (HandshakeRole::Sender, Offered, PaymentSent)
+                | (HandshakeRole::Sender, PaymentSent, Completed)
+                | (HandshakeRole::Sender, Unresolved, Completed)
+                | (HandshakeRole::Receiver, Accepted, Completed)
+        );
+
+        if !allowed {
+            return Err(format!(
+                "Unexpected handshake step: {:?} -> {:?}",
+                self.state, next
+            ));
+        }
+
+        self.state = next;
+        self.deadline = deadline_from_now();
+        Ok(())
+    }
+
+    /// Whether an Ack may still complete the session: the payment was sent and
+    /// neither side cancelled it
+    pub fn awaits_ack(&self) -> bool {
+        self.role == HandshakeRole::Sender
+            && matches!(self.state, HandshakeState::PaymentSent | HandshakeState::Unresolved)
+    }
+
+    /// Resolve an interrupted session; see the module notes for the rules
+    pub fn interrupt(&mut self, reason: &str) {
+        if self.is_finished() {
+            return;
+        }
+
+        self.state = match (self.role, self.state) {
+            (HandshakeRole::Sender, HandshakeState::PaymentSent) => HandshakeState::Unresolved,
+            _ => HandshakeState::Aborted,
+        };
+        self.failure = Some(reason.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    #[test]
+    fn sender_flow_and_late_ack() {
+        let mut session = HandshakeSession::new_sender("wallet-b".to_string(), "Shop".to_string(), 500);
+        assert!(!session.awaits_ack());
+        assert!(session.advance(HandshakeState::Completed).is_err());
+
+        session.advance(HandshakeState::PaymentSent).unwrap();
+        assert!(session.awaits_ack());
+
+        session.interrupt("Timed out");
+        assert_eq!(session.state, HandshakeState::Unresolved);
+        assert!(session.awaits_ack());
+        session.advance(HandshakeState::Completed).unwrap();
+        assert!(!session.awaits_ack());
+    }
+
+    #[test]
+    fn interrupt_before_payment_aborts() {
+        let mut sender = HandshakeSession::new_sender("wallet-b".to_string(), "Shop".to_string(), 500);
+        sender.interrupt("Cancelled");
+        assert_eq!(sender.state, HandshakeState::Aborted);
+        assert!(!sender.awaits_ack());
+
+        let mut receiver = HandshakeSession::new_receiver("s-1".to_string(), "wallet-a".to_string(), "Shop".to_string(), 500);
+        receiver.interrupt("Timed out");
+        assert_eq!(receiver.state, HandshakeState::Aborted);
+        assert!(receiver.advance(HandshakeState::Completed).is_err());
+
+        // Finished sessions keep their outcome
+        receiver.interrupt("again");
+        assert_eq!(receiver.failure.as_deref(), Some("Timed out"));
+    }
+
+    #[test]
+    fn messages_round_trip_as_tagged_json() {
+        let message = HandshakeMessage::Reject {
+            session_id: "s-1".to_string(),
+            reason: "no".to_string(),
+        };
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["type"], "reject");
+        let back: HandshakeMessage = serde_json::from_value(json).unwrap();
+        assert_eq!(back.session_id(), "s-1");
+    }
+
+    #[test]
+    fn handshake_completes_payment() {
+        let (mut sender, mut receiver) = (wallet(), wallet());
+        let ack = handshake_until_ack(&mut sender, &mut receiver, 500);
+        let session_id = ack.session_id().to_string();
+        assert!(sender.p2p_handle_message(ack, None).unwrap().is_none());
+
+        let session = sender.p2p_get_session(&session_id).unwrap();
+        assert_eq!(session.state, HandshakeState::Completed);
+        assert_eq!(sender.get_wallet().offline_balance, 15000 - 500);
+        assert_eq!(receiver.get_wallet().offline_balance, 15000 + 500);
+        let tx = sender.transactions.get(session.tx_id.as_deref().unwrap()).unwrap();
+        assert_eq!(tx.status, "confirmed");
+    }
+
+    #[test]
+    fn late_ack_after_reject_does_not_confirm() {
+        let (mut sender, mut receiver) = (wallet(), wallet());
+        let ack = handshake_until_ack(&mut sender, &mut receiver, 500);
+        let session_id = ack.session_id().to_string();
+
+        sender.p2p_handle_message(HandshakeMessage::Reject {
+            session_id: session_id.clone(),
+            reason: "Cancelled".to_string(),
+        }, None).unwrap();
+        assert_eq!(sender.get_wallet().offline_balance, 15000);
+
+        assert!(sender.p2p_handle_message(ack, None).is_err());
+        let session = sender.p2p_get_session(&session_id).unwrap();
+        assert_eq!(session.state, HandshakeState::Aborted);
+        let tx = sender.transactions.get(session.tx_id.as_deref().unwrap()).unwrap();
+        assert_eq!(tx.status, "cancelled");
+        assert_eq!(sender.get_wallet().offline_balance, 15000);
+    }
+}
@@ -0,0 +1,156 @@
+// Types échangés entre appareils, quel que soit le canal (NFC, Bluetooth, QR)
+
+use serde::{Deserialize, Serialize};
+
+use crate::succession::KeySuccession;
+use crate::{codec, PaymentRequest, Transaction};
+
+pub mod discovery;
+pub mod framing;
+pub mod handshake;
+pub mod qr;
+pub mod session;
+pub mod transport;
+pub mod version;
+
+/// A payer-signed transaction together with the key needed to check it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedPayment {
+    pub transaction: Transaction,
+    pub sender_public_key: String,
+    /// Key successions leading to `sender_public_key`, oldest first
+    #[serde(default)]
+    pub successions: Vec<KeySuccession>,
+}
+
+impl SignedPayment {
+    /// Compact canonical encoding used for NFC and BLE transfers
+    pub fn to_wire(&self) -> Result<Vec<u8>, String> {
+        let signature = hex::decode(&self.transaction.signature)
+            .map_err(|_| "Invalid signature encoding".to_string())?;
+        let public_key = hex::decode(&self.sender_public_key)
+            .map_err(|_| "Invalid public key encoding".to_string())?;
+
+        let mut encoder = codec::Encoder::new("payment")
+            .bytes(&self.transaction.signing_bytes())
+            .bytes(&signature)
+            .bytes(&public_key)
+            .u64(self.successions.len() as u64);
+        for record in &self.successions {
+            let old_signature = hex::decode(&record.old_key_signature)
+                .map_err(|_| "Invalid signature encoding".to_string())?;
+            let new_signature = hex::decode(&record.new_key_signature)
+                .map_err(|_| "Invalid signature encoding".to_string())?;
+            encoder = encoder
+                .bytes(&record.signing_bytes())
+                .bytes(&old_signature)
+                .bytes(&new_signature);
+        }
+        Ok(encoder.finish())
+    }
+
+    pub fn from_wire(data: &[u8]) -> Result<Self, String> {
+        let mut decoder = codec::Decoder::new(data, "payment")?;
+        let mut transaction = Transaction::from_signing_bytes(decoder.bytes()?)?;
+        transaction.signature = hex::encode(decoder.bytes()?);
+        let sender_public_key = hex::encode(decoder.bytes()?);
+
+        // Each record needs several bytes, so a bogus count runs out of data first
+        let count = decoder.u64()?;
+        let mut successions = Vec::new();
+        for _ in 0..count {
+            let mut record = KeySuccession::from_signing_bytes(decoder.bytes()?)?;
+            record.old_key_signature = hex::encode(decoder.bytes()?);
+            record.new_key_signature = hex::encode(decoder.bytes()?);
+            successions.push(record);
+        }
+        decoder.finish()?;
+
+        Ok(SignedPayment {
+            transaction,
+            sender_public_key,
+            successions,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "body", rename_all = "snake_case")]
+pub enum P2PPayload {
+    PaymentRequest(PaymentRequest),
+    SignedPayment(SignedPayment),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::annotations::TxAnnotations;
+    use crate::crypto;
+
+    fn signed() -> SignedPayment {
+        let (private_key, public_key) = crypto::generate_keypair();
+        let mut transaction = Transaction {
+            id: "tx-1".to_string(),
+            from_wallet_id: "wallet-a".to_string(),
+            to_wallet_id: "wallet-b".to_string(),
+            merchant_name: "Shop".to_string(),
+            amount: 1500,
+            timestamp: "2026-10-01T10:00:00+00:00".to_string(),
+            signature: String::new(),
+            tx_type: "offline".to_string(),
+            status: String::new(),
+            request_id: Some("req-1".to_string()),
+            sequence: 3,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
+        };
+        transaction.signature = crypto::sign(&private_key, &transaction.signing_bytes()).unwrap();
+        SignedPayment {
+            transaction,
+            sender_public_key: public_key,
+            successions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn wire_round_trip_keeps_signature_valid() {
+        let payment = signed();
+        let decoded = SignedPayment::from_wire(&payment.to_wire().unwrap()).unwrap();
+
+        assert_eq!(decoded.sender_public_key, payment.sender_public_key);
+        assert_eq!(decoded.transaction.signature, payment.transaction.signature);
+        assert_eq!(decoded.transaction.request_id.as_deref(), Some("req-1"));
+        let bytes = decoded.transaction.signing_bytes();
+        assert!(crypto::verify(&decoded.sender_public_key, &bytes, &decoded.transaction.signature).unwrap());
+    }
+
+    #[test]
+    fn wire_rejects_other_domains_and_bad_hex() {
+        let payment = signed();
+        assert!(SignedPayment::from_wire(&payment.transaction.signing_bytes()).is_err());
+
+        let mut bad = payment.clone();
+        bad.sender_public_key = "zz".to_string();
+        assert!(bad.to_wire().is_err());
+    }
+
+    #[test]
+    fn payment_travels_as_canonical_wire_encoding() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 700, None).unwrap();
+        assert!(payer.verify_signature(&tx).unwrap());
+        assert!(!payee.verify_signature(&tx).unwrap());
+
+        let wire = payer.signed_payment(&tx.id).unwrap().to_wire().unwrap();
+        let payment = SignedPayment::from_wire(&wire).unwrap();
+        let receipt = payee.receive_payment(payment.transaction, payment.sender_public_key, Some("nfc".to_string())).unwrap();
+        assert!(receipt.verify().unwrap());
+        assert_eq!(payee.transactions.get(&tx.id).unwrap().channel.as_deref(), Some("nfc"));
+
+        let mut tampered = tx.clone();
+        tampered.amount += 1;
+        assert!(!payer.verify_signature(&tampered).unwrap());
+    }
+}
@@ -0,0 +1,318 @@
+// Canal QR : encodage compact et vérifié des charges utiles P2P, découpable en plusieurs images
+//
+// Chaque image est "FLX:" suivi du Base45 (RFC 9285) d'une trame binaire :
+//   version (1) | message id (4) | index (1) | total (1) | données | checksum (4)
+// Le Base45 reste dans le mode alphanumérique des QR codes, plus dense que le mode octet.
+
+use sha2::{Digest, Sha256};
+
+use super::P2PPayload;
+
+const PREFIX: &str = "FLX:";
+const FORMAT_VERSION: u8 = 1;
+const HEADER_LEN: usize = 7;
+const CHECKSUM_LEN: usize = 4;
+const BASE45_ALPHABET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Default data bytes per frame, small enough for a medium-ECC QR code
+pub const DEFAULT_FRAME_BYTES: usize = 600;
+
+fn checksum(data: &[u8]) -> [u8; 4] {
+    let hash = Sha256::digest(data);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+pub fn base45_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len() / 2 * 3 + 2);
+    for chunk in data.chunks(2) {
+        let mut n = match chunk {
+            [a, b] => (*a as usize) * 256 + *b as usize,
+            [a] => *a as usize,
+            _ => unreachable!(),
+        };
+        let digits = if chunk.len() == 2 { 3 } else { 2 };
+        for _ in 0..digits {
+            out.push(BASE45_ALPHABET[n % 45] as char);
+            n /= 45;
+        }
+    }
+    out
+}
+
+pub fn base45_decode(text: &str) -> Result<Vec<u8>, String> {
+    let values = text
+        .bytes()
+        .map(|c| {
+            BASE45_ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .ok_or_else(|| format!("Invalid Base45 character: {}", c as char))
+        })
+        .collect::<Result<Vec<usize>, String>>()?;
+
+    let mut out = Vec::with_capacity(values.len() / 3 * 2 + 1);
+    for group in values.chunks(3) {
+        match group {
+            [c, d, e] => {
+                let n = c + d * 45 + e * 45 * 45;
+                if n > 0xFFFF {
+                    return Err("Invalid Base45 group".to_string());
+                }
+                out.push((n >> 8) as u8);
+                out.push((n & 0xFF) as u8);
+            }
+            [c, d] => {
+                let n = c + d * 45;
+                if n > 0xFF {
+                    return Err("Invalid Base45 group".to_string());
+                }
+                out.push(n as u8);
+            }
+            _ => return Err("Truncated Base45 payload".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+/// Encode a payload into one or more QR frames of at most `max_frame_bytes` data bytes each
+pub fn encode(payload: &P2PPayload, max_frame_bytes: usize) -> Result<Vec<String>, String> {
+    if max_frame_bytes == 0 {
+        return Err("Frame size must be positive".to_string());
+    }
+
+    let message = serde_json::to_vec(payload).map_err(|e| e.to_string())?;
+    let msg_id = checksum(&message);
+    let chunks: Vec<&[u8]> = message.chunks(max_frame_bytes).collect();
+
+    if chunks.len() > u8::MAX as usize {
+        return Err("Payload too large for QR transfer".to_string());
+    }
+
+    let total = chunks.len() as u8;
+    let frames = chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let mut frame = Vec::with_capacity(HEADER_LEN + chunk.len() + CHECKSUM_LEN);
+            frame.push(FORMAT_VERSION);
+            frame.extend_from_slice(&msg_id);
+            frame.push(index as u8);
+            frame.push(total);
+            frame.extend_from_slice(chunk);
+            let sum = checksum(&frame);
+            frame.extend_from_slice(&sum);
+            format!("{}{}", PREFIX, base45_encode(&frame))
+        })
+        .collect();
+
+    Ok(frames)
+}
+
+struct Frame {
+    msg_id: [u8; 4],
+    index: u8,
+    total: u8,
+    data: Vec<u8>,
+}
+
+fn parse_frame(text: &str) -> Result<Frame, String> {
+    let body = text
+        .trim()
+        .strip_prefix(PREFIX)
+        .ok_or("Not a Fluxa QR code")?;
+    let bytes = base45_decode(body)?;
+
+    if bytes.len() < HEADER_LEN + CHECKSUM_LEN {
+        return Err("QR frame too short".to_string());
+    }
+
+    let (content, sum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    if checksum(content) != sum {
+        return Err("QR frame checksum mismatch".to_string());
+    }
+
+    if content[0] != FORMAT_VERSION {
+        return Err(format!("Unsupported QR format version: {}", content[0]));
+    }
+
+    let frame = Frame {
+        msg_id: [content[1], content[2], content[3], content[4]],
+        index: content[5],
+        total: content[6],
+        data: content[HEADER_LEN..].to_vec(),
+    };
+
+    if frame.total == 0 || frame.index >= frame.total {
+        return Err("Invalid QR frame index".to_string());
+    }
+
+    Ok(frame)
+}
+
+/// Collects animated QR frames in any order until the message is complete
+#[derive(Default)]
+pub struct QrAssembler {
+    msg_id: Option<[u8; 4]>,
+    parts: Vec<Option<Vec<u8>>>,
+}
+
+impl QrAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a scanned frame; returns the payload once every frame has been seen.
+    /// Frames from another message restart the assembly.
+    pub fn push(&mut self, text: &str) -> Result<Option<P2PPayload>, String> {
+        let frame = parse_frame(text)?;
+
+        if self.msg_id != Some(frame.msg_id) || self.parts.len() != frame.total as usize {
+            self.msg_id = Some(frame.msg_id);
+            self.parts = vec![None; frame.total as usize];
+        }
+
+        self.parts[frame.index as usize] = Some(frame.data);
+
+        if self.parts.iter().any(|p| p.is_none()) {
+            return Ok(None);
+        }
+
+        let message: Vec<u8> = self.parts.iter().flatten().flatten().copied().collect();
+        if Some(checksum(&message)) != self.msg_id {
+            self.msg_id = None;
+            self.parts.clear();
+            return Err("QR message checksum mismatch".to_string());
+        }
+
+        let payload = serde_json::from_slice(&message)
+            .map_err(|e| format!("Invalid QR payload: {}", e))?;
+        Ok(Some(payload))
+    }
+
+    /// (frames received, frames expected)
+    pub fn progress(&self) -> (usize, usize) {
+        (self.parts.iter().filter(|p| p.is_some()).count(), self.parts.len())
+    }
+}
+
+/// Decode a complete set of scanned frames
+pub fn decode(frames: &[String]) -> Result<P2PPayload, String> {
+    let mut assembler = QrAssembler::new();
+    for frame in frames {
+        if let Some(payload) = assembler.push(frame)? {
+            return Ok(payload);
+        }
+    }
+
+    let (received, expected) = assembler.progress();
+    Err(format!("Incomplete QR message ({}/{} frames)", received, expected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::TxAnnotations;
+    use crate::p2p::SignedPayment;
+    use crate::Transaction;
+
+    fn payload(merchant_name: &str) -> P2PPayload {
+        P2PPayload::SignedPayment(SignedPayment {
+            transaction: Transaction {
+                id: "tx-1".to_string(),
+                from_wallet_id: "wallet-a".to_string(),
+                to_wallet_id: "wallet-b".to_string(),
+                merchant_name: merchant_name.to_string(),
+                amount: 1500,
+                timestamp: "2026-10-01T10:00:00+00:00".to_string(),
+                signature: "00".repeat(64),
+                tx_type: "offline".to_string(),
+                status: "pending".to_string(),
+                request_id: None,
+                sequence: 1,
+                channel: None,
+                merchant_status: None,
+                annotations: TxAnnotations::default(),
+            },
+            sender_public_key: "11".repeat(32),
+            successions: Vec::new(),
+        })
+    }
+
+    fn merchant_of(payload: &P2PPayload) -> &str {
+        match payload {
+            P2PPayload::SignedPayment(p) => &p.transaction.merchant_name,
+            P2PPayload::PaymentRequest(r) => &r.merchant_name,
+        }
+    }
+
+    #[test]
+    fn base45_matches_rfc_9285_examples() {
+        for (plain, encoded) in [("AB", "BB8"), ("Hello!!", "%69 VD92EX0"), ("base-45", "UJCLQE7W581"), ("ietf!", "QED8WEX0")] {
+            assert_eq!(base45_encode(plain.as_bytes()), encoded);
+            assert_eq!(base45_decode(encoded).unwrap(), plain.as_bytes());
+        }
+        assert_eq!(base45_encode(&[]), "");
+    }
+
+    #[test]
+    fn base45_rejects_malformed_input() {
+        // GGW vaut 65536, hors de la plage d'un groupe de deux octets
+        assert!(base45_decode("GGW").is_err());
+        assert!(base45_decode("bb8").is_err());
+        assert!(base45_decode("B").is_err());
+        assert!(base45_decode(":Z").is_err());
+    }
+
+    #[test]
+    fn frames_reassemble_in_any_order() {
+        let frames = encode(&payload(&"Kiosque ".repeat(40)), 64).unwrap();
+        assert!(frames.len() > 3);
+        assert!(frames.iter().all(|f| f.starts_with(PREFIX)));
+
+        let mut assembler = QrAssembler::new();
+        for frame in frames.iter().rev().skip(1) {
+            assert!(assembler.push(frame).unwrap().is_none());
+        }
+        assert_eq!(assembler.progress(), (frames.len() - 1, frames.len()));
+        let decoded = assembler.push(&frames[frames.len() - 1]).unwrap().unwrap();
+        assert_eq!(merchant_of(&decoded), "Kiosque ".repeat(40));
+    }
+
+    #[test]
+    fn corrupted_frames_are_refused() {
+        let frames = encode(&payload("Shop"), 4 * DEFAULT_FRAME_BYTES).unwrap();
+        assert_eq!(frames.len(), 1);
+
+        let mut damaged = frames[0].clone().into_bytes();
+        let last = damaged.len() - 4;
+        damaged[last] = if damaged[last] == b'0' { b'1' } else { b'0' };
+        assert!(decode(&[String::from_utf8(damaged).unwrap()]).is_err());
+        assert!(decode(&["HC1:BB8".to_string()]).is_err());
+        assert!(decode(&frames).is_ok());
+    }
+
+    #[test]
+    fn another_message_restarts_the_assembly() {
+        let first = encode(&payload(&"A".repeat(300)), 64).unwrap();
+        let second = encode(&payload(&"B".repeat(300)), 64).unwrap();
+
+        let mut assembler = QrAssembler::new();
+        assembler.push(&first[0]).unwrap();
+        assembler.push(&second[0]).unwrap();
+        assert_eq!(assembler.progress(), (1, second.len()));
+
+        let mut decoded = None;
+        for frame in &second[1..] {
+            decoded = assembler.push(frame).unwrap();
+        }
+        assert_eq!(merchant_of(&decoded.unwrap()), "B".repeat(300));
+    }
+
+    #[test]
+    fn incomplete_or_empty_sets_fail() {
+        let frames = encode(&payload(&"A".repeat(300)), 64).unwrap();
+        let err = decode(&frames[..2]).unwrap_err();
+        assert!(err.contains(&format!("2/{}", frames.len())));
+        assert!(encode(&payload("Shop"), 0).is_err());
+    }
+}
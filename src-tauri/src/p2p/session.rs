@@ -0,0 +1,389 @@
+// Canal P2P chiffré et authentifié
+//
+// Chaque appareil génère une clé X25519 éphémère et l'envoie dans un "hello" signé
+// avec sa clé d'identité Ed25519. Le secret Diffie-Hellman, dérivé par HKDF-SHA256
+// avec l'empreinte des deux hellos, donne une clé ChaCha20-Poly1305 par direction.
+// Chaque message scellé porte un compteur (nonce) qui ne peut qu'augmenter, ce qui
+// rejette les messages rejoués ou réordonnés à l'intérieur d'une session.
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use super::transport::Transport;
+use crate::{codec, crypto, KeyPair};
+
+const KDF_INFO: &[u8] = b"fluxa p2p session v1";
+const COUNTER_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionRole {
+    Initiator,
+    Responder,
+}
+
+impl SessionRole {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SessionRole::Initiator => "initiator",
+            SessionRole::Responder => "responder",
+        }
+    }
+}
+
+/// First message of a secure session, signed by the sender's wallet identity key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionHello {
+    pub role: SessionRole,
+    pub wallet_id: String,
+    pub identity_public_key: String,
+    pub ephemeral_public_key: String,
+    pub signature: String,
+}
+
+impl SessionHello {
+    fn signing_bytes(&self) -> Vec<u8> {
+        codec::Encoder::new("session-hello")
+            .str(self.role.as_str())
+            .str(&self.wallet_id)
+            .str(&self.identity_public_key)
+            .str(&self.ephemeral_public_key)
+            .finish()
+    }
+
+    pub fn verify(&self) -> Result<bool, String> {
+        crypto::verify(&self.identity_public_key, &self.signing_bytes(), &self.signature)
+    }
+}
+
+/// Our half of a key exchange, waiting for the peer's hello
+pub struct KeyExchange {
+    secret: EphemeralSecret,
+    hello: SessionHello,
+}
+
+impl KeyExchange {
+    pub fn new(role: SessionRole, wallet_id: &str, keypair: &KeyPair) -> Result<Self, String> {
+        let secret = EphemeralSecret::random_from_rng(rand::rngs::OsRng);
+        let public = PublicKey::from(&secret);
+
+        let mut hello = SessionHello {
+            role,
+            wallet_id: wallet_id.to_string(),
+            identity_public_key: keypair.public_key.clone(),
+            ephemeral_public_key: hex::encode(public.as_bytes()),
+            signature: String::new(),
+        };
+        hello.signature = crypto::sign(&keypair.private_key, &hello.signing_bytes())?;
+
+        Ok(KeyExchange { secret, hello })
+    }
+
+    pub fn hello(&self) -> &SessionHello {
+        &self.hello
+    }
+
+    /// Check the peer's hello and derive the session keys
+    pub fn complete(self, peer: &SessionHello) -> Result<SecureSession, String> {
+        if peer.role == self.hello.role {
+            return Err("Both peers claim the same session role".to_string());
+        }
+
+        if !peer.verify()? {
+            return Err("Invalid peer identity signature".to_string());
+        }
+
+        let peer_bytes: [u8; 32] = hex::decode(&peer.ephemeral_public_key)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or("Invalid peer ephemeral key")?;
+
+        let shared = self.secret.diffie_hellman(&PublicKey::from(peer_bytes));
+        if !shared.was_contributory() {
+            return Err("Weak peer ephemeral key".to_string());
+        }
+
+        let (initiator, responder) = match self.hello.role {
+            SessionRole::Initiator => (&self.hello, peer),
+            SessionRole::Responder => (peer, &self.hello),
+        };
+        let transcript: [u8; 32] = Sha256::digest(
+            codec::Encoder::new("session-transcript")
+                .bytes(&initiator.signing_bytes())
+                .bytes(initiator.signature.as_bytes())
+                .bytes(&responder.signing_bytes())
+                .bytes(responder.signature.as_bytes())
+                .finish(),
+        )
+        .into();
+
+        let mut okm = [0u8; 64];
+        Hkdf::<Sha256>::new(Some(&transcript), shared.as_bytes())
+            .expand(KDF_INFO, &mut okm)
+            .map_err(|_| "Key derivation failed".to_string())?;
+
+        let mut i2r = [0u8; 32];
+        let mut r2i = [0u8; 32];
+        i2r.copy_from_slice(&okm[..32]);
+        r2i.copy_from_slice(&okm[32..]);
+        let (send_key, recv_key) = match self.hello.role {
+            SessionRole::Initiator => (i2r, r2i),
+            SessionRole::Responder => (r2i, i2r),
+        };
+
+        Ok(SecureSession {
+            send_cipher: ChaCha20Poly1305::new(&Key::from(send_key)),
+            recv_cipher: ChaCha20Poly1305::new(&Key::from(recv_key)),
+            send_counter: 0,
+            next_recv_counter: 0,
+            transcript,
+            peer_wallet_id: peer.wallet_id.clone(),
+            peer_public_key: peer.identity_public_key.clone(),
+        })
+    }
+}
+
+/// Established session; every P2P message goes through `seal` / `open`
+pub struct SecureSession {
+    send_cipher: ChaCha20Poly1305,
+    recv_cipher: ChaCha20Poly1305,
+    send_counter: u64,
+    next_recv_counter: u64,
+    transcript: [u8; 32],
+    peer_wallet_id: String,
+    peer_public_key: String,
+}
+
+fn nonce_for(counter: u64) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    nonce
+}
+
+impl SecureSession {
+    pub fn peer_wallet_id(&self) -> &str {
+        &self.peer_wallet_id
+    }
+
+    /// Ed25519 identity key the peer proved it holds
+    pub fn peer_public_key(&self) -> &str {
+        &self.peer_public_key
+    }
+
+    /// Encrypt a message: counter (8 bytes, big-endian) followed by the AEAD ciphertext
+    pub fn seal(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let counter = self.send_counter;
+        self.send_counter = counter.checked_add(1).ok_or("Session exhausted")?;
+
+        let ciphertext = self.send_cipher
+            .encrypt(
+                &Nonce::from(nonce_for(counter)),
+                Payload { msg: plaintext, aad: &self.transcript },
+            )
+            .map_err(|_| "Encryption failed".to_string())?;
+
+        let mut frame = Vec::with_capacity(COUNTER_LEN + ciphertext.len());
+        frame.extend_from_slice(&counter.to_be_bytes());
+        frame.extend_from_slice(&ciphertext);
+        Ok(frame)
+    }
+
+    /// Decrypt and authenticate a sealed message, rejecting replays
+    pub fn open(&mut self, frame: &[u8]) -> Result<Vec<u8>, String> {
+        if frame.len() < COUNTER_LEN {
+            return Err("Sealed message too short".to_string());
+        }
+
+        let (counter_bytes, ciphertext) = frame.split_at(COUNTER_LEN);
+        let mut buf = [0u8; COUNTER_LEN];
+        buf.copy_from_slice(counter_bytes);
+        let counter = u64::from_be_bytes(buf);
+
+        if counter < self.next_recv_counter {
+            return Err("Replayed or out-of-order message".to_string());
+        }
+
+        let plaintext = self.recv_cipher
+            .decrypt(
+                &Nonce::from(nonce_for(counter)),
+                Payload { msg: ciphertext, aad: &self.transcript },
+            )
+            .map_err(|_| "Message authentication failed".to_string())?;
+
+        self.next_recv_counter = counter.checked_add(1).ok_or("Session exhausted")?;
+        Ok(plaintext)
+    }
+
+    pub fn send<T: Transport>(&mut self, transport: &mut T, plaintext: &[u8]) -> Result<(), String> {
+        let frame = self.seal(plaintext)?;
+        transport.send(&frame)
+    }
+
+    pub fn recv<T: Transport>(&mut self, transport: &mut T) -> Result<Option<Vec<u8>>, String> {
+        match transport.recv()? {
+            Some(frame) => self.open(&frame).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::p2p::handshake::{HandshakeMessage, HandshakeState};
+    use crate::p2p::transport::{MemoryTransport, Transport};
+
+    fn identity() -> KeyPair {
+        let (private_key, public_key) = crypto::generate_keypair();
+        KeyPair {
+            public_key,
+            private_key,
+            created_at: String::new(),
+        }
+    }
+
+    fn established() -> (SecureSession, SecureSession) {
+        let alice = KeyExchange::new(SessionRole::Initiator, "wallet-a", &identity()).unwrap();
+        let bob = KeyExchange::new(SessionRole::Responder, "wallet-b", &identity()).unwrap();
+        let alice_hello = alice.hello().clone();
+        let bob_hello = bob.hello().clone();
+        (alice.complete(&bob_hello).unwrap(), bob.complete(&alice_hello).unwrap())
+    }
+
+    #[test]
+    fn sessions_talk_over_memory_transport() {
+        let (mut alice, mut bob) = established();
+        let (mut alice_link, mut bob_link) = MemoryTransport::pair();
+
+        assert_eq!(alice.peer_wallet_id(), "wallet-b");
+        assert_eq!(bob.peer_wallet_id(), "wallet-a");
+
+        alice.send(&mut alice_link, b"offer").unwrap();
+        assert_eq!(bob.recv(&mut bob_link).unwrap(), Some(b"offer".to_vec()));
+
+        bob.send(&mut bob_link, b"accept").unwrap();
+        assert_eq!(alice.recv(&mut alice_link).unwrap(), Some(b"accept".to_vec()));
+        assert_eq!(alice.recv(&mut alice_link).unwrap(), None);
+    }
+
+    #[test]
+    fn frames_are_not_plaintext() {
+        let (mut alice, _) = established();
+        let frame = alice.seal(b"secret amount 1500").unwrap();
+        assert!(!frame.windows(6).any(|w| w == b"secret"));
+    }
+
+    #[test]
+    fn replayed_frame_is_rejected() {
+        let (mut alice, mut bob) = established();
+        let frame = alice.seal(b"pay").unwrap();
+        bob.open(&frame).unwrap();
+        assert!(bob.open(&frame).is_err());
+    }
+
+    #[test]
+    fn tampered_frame_is_rejected() {
+        let (mut alice, mut bob) = established();
+        let mut frame = alice.seal(b"pay").unwrap();
+        let last = frame.len() - 1;
+        frame[last] ^= 1;
+        assert!(bob.open(&frame).is_err());
+    }
+
+    #[test]
+    fn frame_from_another_session_is_rejected() {
+        let (mut alice, _) = established();
+        let (_, mut other) = established();
+        let frame = alice.seal(b"pay").unwrap();
+        assert!(other.open(&frame).is_err());
+    }
+
+    #[test]
+    fn forged_hello_is_rejected() {
+        let alice = KeyExchange::new(SessionRole::Initiator, "wallet-a", &identity()).unwrap();
+        let bob = KeyExchange::new(SessionRole::Responder, "wallet-b", &identity()).unwrap();
+        let mut forged = bob.hello().clone();
+        forged.wallet_id = "wallet-c".to_string();
+        assert!(alice.complete(&forged).is_err());
+    }
+
+    #[test]
+    fn same_role_is_rejected() {
+        let alice = KeyExchange::new(SessionRole::Initiator, "wallet-a", &identity()).unwrap();
+        let bob = KeyExchange::new(SessionRole::Initiator, "wallet-b", &identity()).unwrap();
+        let bob_hello = bob.hello().clone();
+        assert!(alice.complete(&bob_hello).is_err());
+    }
+
+    #[test]
+    fn exhausted_counter_is_an_error() {
+        let (mut alice, _) = established();
+        alice.send_counter = u64::MAX;
+        assert!(alice.seal(b"last").is_err());
+    }
+
+    #[test]
+    fn payment_runs_over_secure_channel_and_memory_transport() {
+        let (mut sender, mut receiver) = (wallet(), wallet());
+        let (sender_channel, receiver_channel) = secure_pair(&mut sender, &mut receiver);
+        let (mut sender_link, mut receiver_link) = MemoryTransport::pair();
+
+        let offer = sender.p2p_secure_start_payment(&sender_channel, "Shop".to_string(), 700).unwrap();
+        sender_link.send(&offer).unwrap();
+
+        // Chaque côté répond au message reçu jusqu'à ce qu'il n'y ait plus rien à envoyer
+        loop {
+            let mut progressed = false;
+            if let Some(frame) = receiver_link.recv().unwrap() {
+                if let Some(reply) = receiver.p2p_secure_exchange(&receiver_channel, &frame, Some("ble".to_string())).unwrap() {
+                    receiver_link.send(&reply).unwrap();
+                }
+                progressed = true;
+            }
+            if let Some(frame) = sender_link.recv().unwrap() {
+                if let Some(reply) = sender.p2p_secure_exchange(&sender_channel, &frame, Some("ble".to_string())).unwrap() {
+                    sender_link.send(&reply).unwrap();
+                }
+                progressed = true;
+            }
+            if !progressed {
+                break;
+            }
+        }
+
+        assert_eq!(sender.get_wallet().offline_balance, 15000 - 700);
+        assert_eq!(receiver.get_wallet().offline_balance, 15000 + 700);
+        assert!(sender.p2p_sessions.iter().all(|s| s.state == HandshakeState::Completed));
+
+        // Le canal est enregistré dès la création du paiement émis
+        let tx_id = sender.p2p_sessions[0].tx_id.clone().unwrap();
+        assert_eq!(sender.transactions.get(&tx_id).unwrap().channel.as_deref(), Some("ble"));
+    }
+
+    #[test]
+    fn secure_offer_goes_to_the_channel_peer() {
+        let (mut sender, mut receiver) = (wallet(), wallet());
+        let (sender_channel, receiver_channel) = secure_pair(&mut sender, &mut receiver);
+
+        let offer = sender.p2p_secure_start_payment(&sender_channel, "Shop".to_string(), 700).unwrap();
+        match receiver.p2p_secure_open(&receiver_channel, &offer).unwrap() {
+            HandshakeMessage::Offer { receiver_wallet_id, .. } => {
+                assert_eq!(receiver_wallet_id, receiver.get_wallet().id);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn secure_payment_needs_an_established_channel() {
+        let mut sender = wallet();
+        let start = sender.p2p_secure_begin(SessionRole::Initiator).unwrap();
+        assert!(sender.p2p_secure_start_payment(&start.channel_id, "Shop".to_string(), 700).is_err());
+        assert!(sender.p2p_sessions.is_empty());
+    }
+}
@@ -0,0 +1,72 @@
+// Abstraction du lien physique (BLE, NFC...) sur lequel circulent les messages P2P
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// A bidirectional, message-oriented link to one peer
+pub trait Transport {
+    fn send(&mut self, data: &[u8]) -> Result<(), String>;
+    /// Next message from the peer, or `None` if nothing has arrived yet
+    fn recv(&mut self) -> Result<Option<Vec<u8>>, String>;
+}
+
+type Queue = Arc<Mutex<VecDeque<Vec<u8>>>>;
+
+/// In-process transport linking two endpoints, used on desktop builds and for simulations
+pub struct MemoryTransport {
+    inbox: Queue,
+    outbox: Queue,
+}
+
+impl MemoryTransport {
+    /// Two connected endpoints: what one sends, the other receives
+    pub fn pair() -> (MemoryTransport, MemoryTransport) {
+        let a: Queue = Arc::default();
+        let b: Queue = Arc::default();
+        (
+            MemoryTransport { inbox: a.clone(), outbox: b.clone() },
+            MemoryTransport { inbox: b, outbox: a },
+        )
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&mut self, data: &[u8]) -> Result<(), String> {
+        self.outbox
+            .lock()
+            .map_err(|_| "Transport poisoned".to_string())?
+            .push_back(data.to_vec());
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.inbox
+            .lock()
+            .map_err(|_| "Transport poisoned".to_string())?
+            .pop_front())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pair_delivers_in_order_to_the_other_end() {
+        let (mut a, mut b) = MemoryTransport::pair();
+        a.send(b"one").unwrap();
+        a.send(b"two").unwrap();
+
+        assert_eq!(a.recv().unwrap(), None);
+        assert_eq!(b.recv().unwrap(), Some(b"one".to_vec()));
+        assert_eq!(b.recv().unwrap(), Some(b"two".to_vec()));
+        assert_eq!(b.recv().unwrap(), None);
+    }
+
+    #[test]
+    fn pair_is_bidirectional() {
+        let (mut a, mut b) = MemoryTransport::pair();
+        b.send(b"reply").unwrap();
+        assert_eq!(a.recv().unwrap(), Some(b"reply".to_vec()));
+    }
+}
@@ -0,0 +1,186 @@
+// Négociation de version du protocole P2P et des capacités entre deux appareils
+
+use serde::{Deserialize, Serialize};
+
+/// Highest P2P protocol version this build speaks
+pub const PROTOCOL_VERSION: u16 = 1;
+/// Oldest P2P protocol version this build still accepts
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
+/// Largest single P2P message this build accepts, in bytes
+pub const MAX_PAYLOAD_BYTES: u32 = 64 * 1024;
+
+/// What a device supports; exchanged in the first handshake message.
+/// A peer that sends none predates negotiation and is reported as version 0.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerCapabilities {
+    pub min_version: u16,
+    pub max_version: u16,
+    pub channels: Vec<String>,
+    pub currencies: Vec<String>,
+    pub max_payload_bytes: u32,
+}
+
+impl PeerCapabilities {
+    pub fn local() -> Self {
+        PeerCapabilities {
+            min_version: MIN_PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+            channels: vec!["nfc".to_string(), "ble".to_string(), "qr".to_string()],
+            currencies: vec!["XOF".to_string()],
+            max_payload_bytes: MAX_PAYLOAD_BYTES,
+        }
+    }
+}
+
+/// Parameters both devices agreed on for a session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NegotiatedProtocol {
+    pub version: u16,
+    pub channels: Vec<String>,
+    pub currencies: Vec<String>,
+    pub max_payload_bytes: u32,
+}
+
+impl NegotiatedProtocol {
+    pub fn allows_channel(&self, channel: &str) -> bool {
+        self.channels.iter().any(|c| c == channel)
+    }
+}
+
+/// Pick the highest version both sides support, failing with a readable reason otherwise
+pub fn negotiate(
+    local: &PeerCapabilities,
+    remote: &PeerCapabilities,
+) -> Result<NegotiatedProtocol, String> {
+    if remote.max_version < local.min_version {
+        return Err(format!(
+            "Peer too old: it supports up to protocol v{}, this app needs at least v{}",
+            remote.max_version, local.min_version
+        ));
+    }
+
+    if remote.min_version > local.max_version {
+        return Err(format!(
+            "Peer too new: it needs at least protocol v{}, this app supports up to v{}",
+            remote.min_version, local.max_version
+        ));
+    }
+
+    let currencies: Vec<String> = local.currencies.iter()
+        .filter(|c| remote.currencies.contains(c))
+        .cloned()
+        .collect();
+    if currencies.is_empty() {
+        return Err("No currency in common with peer".to_string());
+    }
+
+    let channels: Vec<String> = local.channels.iter()
+        .filter(|c| remote.channels.contains(c))
+        .cloned()
+        .collect();
+    if channels.is_empty() {
+        return Err("No channel in common with peer".to_string());
+    }
+
+    Ok(NegotiatedProtocol {
+        version: local.max_version.min(remote.max_version),
+        channels,
+        currencies,
+        max_payload_bytes: local.max_payload_bytes.min(remote.max_payload_bytes),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::p2p::framing::DEFAULT_MAX_MESSAGE_BYTES;
+    use crate::p2p::handshake::{HandshakeMessage, HandshakeState};
+    use crate::BankingEngine;
+
+    fn peer(min_version: u16, max_version: u16, channels: &[&str]) -> PeerCapabilities {
+        PeerCapabilities {
+            min_version,
+            max_version,
+            channels: channels.iter().map(|c| c.to_string()).collect(),
+            currencies: vec!["XOF".to_string()],
+            max_payload_bytes: 4096,
+        }
+    }
+
+    #[test]
+    fn agrees_on_the_common_subset() {
+        let local = PeerCapabilities::local();
+        let protocol = negotiate(&local, &peer(0, 9, &["ble", "usb"])).unwrap();
+        assert_eq!(protocol.version, PROTOCOL_VERSION);
+        assert_eq!(protocol.channels, ["ble"]);
+        assert_eq!(protocol.currencies, ["XOF"]);
+        assert_eq!(protocol.max_payload_bytes, 4096);
+        assert!(protocol.allows_channel("ble"));
+        assert!(!protocol.allows_channel("nfc"));
+    }
+
+    #[test]
+    fn rejects_peers_outside_the_version_range() {
+        let local = PeerCapabilities::local();
+        assert!(negotiate(&local, &peer(0, 0, &["ble"])).unwrap_err().contains("too old"));
+        assert!(negotiate(&local, &peer(PROTOCOL_VERSION + 1, PROTOCOL_VERSION + 2, &["ble"]))
+            .unwrap_err()
+            .contains("too new"));
+    }
+
+    #[test]
+    fn needs_a_shared_channel_and_currency() {
+        let local = PeerCapabilities::local();
+        assert!(negotiate(&local, &peer(1, 1, &["usb"])).is_err());
+        assert!(negotiate(&local, &peer(1, 1, &[])).is_err());
+
+        let mut euro_only = peer(1, 1, &["nfc"]);
+        euro_only.currencies = vec!["EUR".to_string()];
+        assert!(negotiate(&local, &euro_only).is_err());
+    }
+
+    /// Offer from `sender` as a peer limited to `channels` and `max_payload_bytes` would send it
+    fn limited_offer(sender: &mut BankingEngine, receiver: &BankingEngine, channels: &[&str], max_payload_bytes: u32) -> HandshakeMessage {
+        let mut offer = sender.p2p_start_payment(receiver.get_wallet().id, "Shop".to_string(), 500).unwrap();
+        if let HandshakeMessage::Offer { capabilities, .. } = &mut offer {
+            capabilities.channels = channels.iter().map(|c| c.to_string()).collect();
+            capabilities.max_payload_bytes = max_payload_bytes;
+        }
+        offer
+    }
+
+    #[test]
+    fn payment_must_use_a_negotiated_channel() {
+        let (mut sender, mut receiver) = (wallet(), wallet());
+        let offer = limited_offer(&mut sender, &receiver, &["ble"], 4096);
+        let session_id = offer.session_id().to_string();
+        let accept = receiver.p2p_handle_message(offer, None).unwrap().unwrap();
+        let payment = sender.p2p_handle_message(accept, Some("ble".to_string())).unwrap().unwrap();
+
+        let reply = receiver.p2p_handle_message(payment, Some("nfc".to_string())).unwrap().unwrap();
+        assert!(matches!(reply, HandshakeMessage::Reject { .. }));
+        assert_eq!(receiver.get_wallet().offline_balance, 15000);
+        assert_ne!(receiver.p2p_get_session(&session_id).unwrap().state, HandshakeState::Completed);
+    }
+
+    #[test]
+    fn offer_without_a_common_channel_is_rejected() {
+        let (mut sender, mut receiver) = (wallet(), wallet());
+        let offer = limited_offer(&mut sender, &receiver, &["usb"], 4096);
+        let reply = receiver.p2p_handle_message(offer, None).unwrap().unwrap();
+        assert!(matches!(reply, HandshakeMessage::Reject { .. }));
+    }
+
+    #[test]
+    fn session_exposes_the_negotiated_message_limit() {
+        let (mut sender, mut receiver) = (wallet(), wallet());
+        let offer = limited_offer(&mut sender, &receiver, &["ble"], 4096);
+        let session_id = offer.session_id().to_string();
+        assert_eq!(sender.p2p_max_message_bytes(&session_id).unwrap(), DEFAULT_MAX_MESSAGE_BYTES);
+
+        receiver.p2p_handle_message(offer, None).unwrap();
+        assert_eq!(receiver.p2p_max_message_bytes(&session_id).unwrap(), 4096);
+        assert!(receiver.p2p_max_message_bytes("unknown").is_err());
+    }
+}
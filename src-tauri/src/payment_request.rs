@@ -0,0 +1,197 @@
+// Demandes de paiement (factures) signées par le marchand
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{codec, crypto};
+
+/// Longest validity a request can be given (30 days)
+pub const MAX_EXPIRY_SECS: i64 = 30 * 24 * 3600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentRequest {
+    pub id: String,
+    pub merchant_wallet_id: String,
+    pub merchant_public_key: String,
+    pub merchant_name: String,
+    pub amount: u64,
+    pub reference: String,
+    pub description: Option<String>,
+    pub created_at: String,
+    pub expires_at: String,
+    pub signature: String,
+    /// Local state ("open", "paid"), not covered by the signature
+    #[serde(default = "default_status")]
+    pub status: String,
+}
+
+fn default_status() -> String {
+    "open".to_string()
+}
+
+impl PaymentRequest {
+    #[allow(clippy::too_many_arguments)]
+    pub fn create(
+        merchant_wallet_id: &str,
+        merchant_public_key: &str,
+        merchant_private_key: &str,
+        merchant_name: String,
+        amount: u64,
+        reference: String,
+        description: Option<String>,
+        expires_in_secs: i64,
+    ) -> Result<Self, String> {
+        if expires_in_secs <= 0 || expires_in_secs > MAX_EXPIRY_SECS {
+            return Err(format!("Expiry must be between 1 second and {} days", MAX_EXPIRY_SECS / 86400));
+        }
+
+        let now = Utc::now();
+        let expires_at = Duration::try_seconds(expires_in_secs)
+            .and_then(|validity| now.checked_add_signed(validity))
+            .ok_or("Expiry out of range")?;
+        let mut request = PaymentRequest {
+            id: Uuid::new_v4().to_string(),
+            merchant_wallet_id: merchant_wallet_id.to_string(),
+            merchant_public_key: merchant_public_key.to_string(),
+            merchant_name,
+            amount,
+            reference,
+            description,
+            created_at: now.to_rfc3339(),
+            expires_at: expires_at.to_rfc3339(),
+            signature: String::new(),
+            status: default_status(),
+        };
+
+        request.signature = crypto::sign(merchant_private_key, &request.signing_bytes())?;
+        Ok(request)
+    }
+
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        codec::Encoder::new("payreq")
+            .str(&self.id)
+            .str(&self.merchant_wallet_id)
+            .str(&self.merchant_public_key)
+            .str(&self.merchant_name)
+            .u64(self.amount)
+            .str(&self.reference)
+            .opt_str(self.description.as_deref())
+            .str(&self.created_at)
+            .str(&self.expires_at)
+            .finish()
+    }
+
+    pub fn verify(&self) -> Result<bool, String> {
+        crypto::verify(
+            &self.merchant_public_key,
+            &self.signing_bytes(),
+            &self.signature,
+        )
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match DateTime::parse_from_rfc3339(&self.expires_at) {
+            Ok(expires_at) => expires_at.with_timezone(&Utc) <= Utc::now(),
+            Err(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::BankingEngine;
+
+    fn create(expires_in_secs: i64) -> Result<PaymentRequest, String> {
+        let (private_key, public_key) = crypto::generate_keypair();
+        PaymentRequest::create(
+            "wallet-b",
+            &public_key,
+            &private_key,
+            "Shop".to_string(),
+            700,
+            "INV-1".to_string(),
+            Some("rice".to_string()),
+            expires_in_secs,
+        )
+    }
+
+    #[test]
+    fn signed_request_verifies() {
+        let request = create(600).unwrap();
+        assert!(request.verify().unwrap());
+        assert!(!request.is_expired());
+        assert_eq!(request.status, "open");
+
+        let mut tampered = request.clone();
+        tampered.amount = 1;
+        assert!(!tampered.verify().unwrap());
+    }
+
+    #[test]
+    fn expiry_is_bounded() {
+        assert!(create(0).is_err());
+        assert!(create(-5).is_err());
+        assert!(create(MAX_EXPIRY_SECS + 1).is_err());
+        assert!(create(i64::MAX).is_err());
+        assert!(create(MAX_EXPIRY_SECS).is_ok());
+    }
+
+    #[test]
+    fn unparsable_expiry_counts_as_expired() {
+        let mut request = create(600).unwrap();
+        request.expires_at = "soon".to_string();
+        assert!(request.is_expired());
+    }
+
+    #[test]
+    fn payment_request_paid_once() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        assert!(payee.create_payment_request("Shop".to_string(), 700, "INV-1".to_string(), None, i64::MAX).is_err());
+        let request = payee.create_payment_request("Shop".to_string(), 700, "INV-1".to_string(), None, 600).unwrap();
+
+        payer.import_payment_request(request.clone()).unwrap();
+        let tx = payer.pay_payment_request(request.id.clone(), "offline".to_string()).unwrap();
+        assert!(payer.pay_payment_request(request.id.clone(), "offline".to_string()).is_err());
+
+        let mut unlinked = tx.clone();
+        unlinked.request_id = None;
+        assert!(payee.receive_payment(unlinked, payer.get_public_key().unwrap(), None).is_err());
+
+        payee.receive_payment(tx, payer.get_public_key().unwrap(), None).unwrap();
+        assert_eq!(payee.get_issued_payment_requests()[0].status, "paid");
+    }
+
+    #[test]
+    fn payment_requests_survive_restart() {
+        let (payer_dir, payee_dir) = (temp_dir(), temp_dir());
+        let (mut payer, mut payee) = (BankingEngine::new(), BankingEngine::new());
+        payer.set_data_dir(payer_dir.clone()).unwrap();
+        payee.set_data_dir(payee_dir.clone()).unwrap();
+        payer.initialize_keys().unwrap();
+        payee.initialize_keys().unwrap();
+        let request = payee.create_payment_request("Shop".to_string(), 700, "INV-1".to_string(), None, 600).unwrap();
+        payer.import_payment_request(request.clone()).unwrap();
+
+        let mut payer = BankingEngine::new();
+        payer.set_data_dir(payer_dir.clone()).unwrap();
+        let tx = payer.pay_payment_request(request.id.clone(), "offline".to_string()).unwrap();
+
+        let mut payee = BankingEngine::new();
+        payee.set_data_dir(payee_dir.clone()).unwrap();
+        payee.receive_payment(tx.clone(), payer.get_public_key().unwrap(), None).unwrap();
+
+        let mut payer = BankingEngine::new();
+        payer.set_data_dir(payer_dir.clone()).unwrap();
+        assert_eq!(payer.get_payment_requests()[0].status, "paid");
+        assert!(payer.pay_payment_request(request.id.clone(), "offline".to_string()).is_err());
+
+        let mut payee = BankingEngine::new();
+        payee.set_data_dir(payee_dir.clone()).unwrap();
+        assert_eq!(payee.get_issued_payment_request(&request.id).unwrap().status, "paid");
+        std::fs::remove_dir_all(payer_dir).ok();
+        std::fs::remove_dir_all(payee_dir).ok();
+    }
+}
@@ -0,0 +1,315 @@
+// Reçus numériques contresignés par le destinataire d'un paiement
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::{codec, crypto};
+use crate::annotations::TxAnnotations;
+use crate::Transaction;
+
+/// Receipts kept in the data directory, one per line
+pub const RECEIPTS_FILE: &str = "receipts.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentReceipt {
+    pub id: String,
+    pub tx_id: String,
+    pub tx_hash: String,
+    pub payer_wallet_id: String,
+    pub payer_public_key: String,
+    pub payee_wallet_id: String,
+    pub payee_public_key: String,
+    pub merchant_name: String,
+    pub amount: u64,
+    #[serde(default)]
+    pub tx_type: String,
+    pub tx_timestamp: String,
+    pub issued_at: String,
+    pub payer_signature: String,
+    pub receiver_signature: String,
+    #[serde(default)]
+    pub request_id: Option<String>,
+    #[serde(default)]
+    pub sequence: u64,
+}
+
+/// Hash identifying a signed transaction: covers the signed fields and the payer signature
+pub fn transaction_hash(tx: &Transaction) -> String {
+    let data = codec::Encoder::new("tx-hash")
+        .bytes(&tx.signing_bytes())
+        .str(&tx.signature)
+        .finish();
+    hex::encode(Sha256::digest(&data))
+}
+
+impl PaymentReceipt {
+    /// Build and countersign a receipt for a payment received by the holder of `payee_private_key`
+    pub fn issue(
+        tx: &Transaction,
+        payer_public_key: &str,
+        payee_public_key: &str,
+        payee_private_key: &str,
+    ) -> Result<Self, String> {
+        let mut receipt = PaymentReceipt {
+            id: Uuid::new_v4().to_string(),
+            tx_id: tx.id.clone(),
+            tx_hash: transaction_hash(tx),
+            payer_wallet_id: tx.from_wallet_id.clone(),
+            payer_public_key: payer_public_key.to_string(),
+            payee_wallet_id: tx.to_wallet_id.clone(),
+            payee_public_key: payee_public_key.to_string(),
+            merchant_name: tx.merchant_name.clone(),
+            amount: tx.amount,
+            tx_type: tx.tx_type.clone(),
+            tx_timestamp: tx.timestamp.clone(),
+            issued_at: Utc::now().to_rfc3339(),
+            payer_signature: tx.signature.clone(),
+            receiver_signature: String::new(),
+            request_id: tx.request_id.clone(),
+            sequence: tx.sequence,
+        };
+
+        receipt.receiver_signature =
+            crypto::sign(payee_private_key, &receipt.signing_bytes())?;
+        Ok(receipt)
+    }
+
+    /// Canonical encoding of the fields covered by the receiver's countersignature
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        codec::Encoder::new("receipt")
+            .str(&self.id)
+            .str(&self.tx_id)
+            .str(&self.tx_hash)
+            .str(&self.payer_wallet_id)
+            .str(&self.payer_public_key)
+            .str(&self.payee_wallet_id)
+            .str(&self.payee_public_key)
+            .str(&self.merchant_name)
+            .u64(self.amount)
+            .str(&self.tx_type)
+            .str(&self.tx_timestamp)
+            .str(&self.issued_at)
+            .str(&self.payer_signature)
+            .opt_str(self.request_id.as_deref())
+            .u64(self.sequence)
+            .finish()
+    }
+
+    /// Rebuild the payer's transaction as it was signed
+    fn original_transaction(&self) -> Transaction {
+        Transaction {
+            id: self.tx_id.clone(),
+            from_wallet_id: self.payer_wallet_id.clone(),
+            to_wallet_id: self.payee_wallet_id.clone(),
+            merchant_name: self.merchant_name.clone(),
+            amount: self.amount,
+            timestamp: self.tx_timestamp.clone(),
+            signature: self.payer_signature.clone(),
+            tx_type: self.tx_type.clone(),
+            status: String::new(),
+            request_id: self.request_id.clone(),
+            sequence: self.sequence,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
+        }
+    }
+
+    /// Check both the payer signature and the receiver countersignature.
+    /// Needs no engine state, so payer, payee or the settlement server can run it.
+    pub fn verify(&self) -> Result<bool, String> {
+        let tx = self.original_transaction();
+        if transaction_hash(&tx) != self.tx_hash {
+            return Ok(false);
+        }
+
+        let payer_ok = crypto::verify(
+            &self.payer_public_key,
+            &tx.signing_bytes(),
+            &self.payer_signature,
+        )?;
+        if !payer_ok {
+            return Ok(false);
+        }
+
+        crypto::verify(
+            &self.payee_public_key,
+            &self.signing_bytes(),
+            &self.receiver_signature,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::{crypto, Transaction};
+    use crate::directory::MerchantIdentity;
+    use crate::events::{MemorySink, CONTACT_KEY_CHANGED};
+    use crate::BankingEngine;
+
+    fn signed_payment(payer_private_key: &str) -> Transaction {
+        let mut tx = Transaction {
+            id: "tx-1".to_string(),
+            from_wallet_id: "wallet-a".to_string(),
+            to_wallet_id: "wallet-b".to_string(),
+            merchant_name: "Shop".to_string(),
+            amount: 1500,
+            timestamp: "2026-10-01T10:00:00+00:00".to_string(),
+            signature: String::new(),
+            tx_type: "offline".to_string(),
+            status: "pending".to_string(),
+            request_id: None,
+            sequence: 1,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
+        };
+        tx.signature = crypto::sign(payer_private_key, &tx.signing_bytes()).unwrap();
+        tx
+    }
+
+    #[test]
+    fn receipt_verifies_both_signatures() {
+        let (payer_private, payer_public) = crypto::generate_keypair();
+        let (payee_private, payee_public) = crypto::generate_keypair();
+        let tx = signed_payment(&payer_private);
+
+        let receipt = PaymentReceipt::issue(&tx, &payer_public, &payee_public, &payee_private).unwrap();
+        assert!(receipt.verify().unwrap());
+        assert_eq!(receipt.tx_hash, transaction_hash(&tx));
+
+        let mut tampered = receipt.clone();
+        tampered.amount = 1;
+        assert!(!tampered.verify().unwrap());
+
+        let (_, other_public) = crypto::generate_keypair();
+        let mut wrong_payer = receipt.clone();
+        wrong_payer.payer_public_key = other_public;
+        assert!(!wrong_payer.verify().unwrap());
+    }
+
+    #[test]
+    fn hash_covers_signature() {
+        let (payer_private, _) = crypto::generate_keypair();
+        let tx = signed_payment(&payer_private);
+        let mut other = tx.clone();
+        other.signature = crypto::sign(&payer_private, b"other").unwrap();
+        assert_ne!(transaction_hash(&tx), transaction_hash(&other));
+    }
+
+    #[test]
+    fn receipt_confirms_pending_payment_once() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 1000, None).unwrap();
+        let receipt = payee.receive_payment(tx.clone(), payer.get_public_key().unwrap(), None).unwrap();
+
+        assert_eq!(payer.import_receipt(receipt.clone()).unwrap().status, "confirmed");
+        assert!(payer.import_receipt(receipt).is_err());
+        assert_eq!(payer.get_receipts().len(), 1);
+    }
+
+    #[test]
+    fn receipt_does_not_revive_cancelled_payment() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 1000, None).unwrap();
+        let receipt = payee.receive_payment(tx.clone(), payer.get_public_key().unwrap(), None).unwrap();
+        payer.cancel_transaction(tx.id.clone()).unwrap();
+
+        assert!(payer.import_receipt(receipt).is_err());
+        assert_eq!(payer.transactions.get(&tx.id).unwrap().status, "cancelled");
+        assert_eq!(payer.get_wallet().offline_balance, 15000);
+    }
+
+    /// `tx` re-signed with a fresh key pair, as a wallet impersonating the payer would
+    fn resigned(tx: &Transaction) -> (Transaction, String) {
+        let (private_key, public_key) = crypto::generate_keypair();
+        let mut forged = tx.clone();
+        forged.signature = crypto::sign(&private_key, &forged.signing_bytes()).unwrap();
+        (forged, public_key)
+    }
+
+    #[test]
+    fn known_payer_must_use_the_key_on_record() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        pay(&mut payer, &mut payee, 100);
+        let sink = MemorySink::new();
+        payee.set_event_sink(Box::new(sink.clone()));
+        let balance = payee.get_wallet().offline_balance;
+        let count = payee.get_transactions().len();
+
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 500, None).unwrap();
+        let (forged, forged_key) = resigned(&tx);
+        assert!(payee.receive_payment(forged, forged_key, None).is_err());
+
+        // Reported, but nothing is recorded for the refused key
+        let names: Vec<_> = sink.drain().iter().map(|e| e.name()).collect();
+        assert_eq!(names, [CONTACT_KEY_CHANGED]);
+        assert_eq!(payee.get_wallet().offline_balance, balance);
+        assert_eq!(payee.get_transactions().len(), count);
+        assert!(payee.get_contact_warnings().is_empty());
+
+        // Le vrai paiement passe toujours : le rejet n'a rien consommé
+        payee.receive_payment(tx, payer.get_public_key().unwrap(), None).unwrap();
+        assert_eq!(payee.get_wallet().offline_balance, balance + 500);
+    }
+
+    #[test]
+    fn unknown_payer_is_trusted_on_first_use() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        let first = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+        let second = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+
+        let (forged, forged_key) = resigned(&first);
+        payee.receive_payment(forged, forged_key.clone(), None).unwrap();
+        assert_eq!(payee.contacts.find(&payer.get_wallet().id).unwrap().public_key, forged_key);
+
+        // La première clé vue fait foi pour la suite
+        assert!(payee.receive_payment(second, payer.get_public_key().unwrap(), None).is_err());
+    }
+
+    #[test]
+    fn payer_key_is_checked_against_the_directory() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        let (issuer_private, issuer_public) = trust_issuer(&mut payee);
+        let mut identity = MerchantIdentity {
+            wallet_id: payer.get_wallet().id,
+            public_key: payer.get_public_key().unwrap(),
+            display_name: "Chez Ali".to_string(),
+            category: "food".to_string(),
+            badge: None,
+        };
+        let expires_at = (Utc::now() + chrono::Duration::days(30)).to_rfc3339();
+        identity.sign_badge(&issuer_public, &issuer_private, &Utc::now().to_rfc3339(), &expires_at).unwrap();
+        payee.import_merchant_directory(vec![identity]).unwrap();
+
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+        let (forged, forged_key) = resigned(&tx);
+        assert!(payee.receive_payment(forged, forged_key, None).is_err());
+        assert!(payee.get_contacts().is_empty());
+
+        payee.receive_payment(tx, payer.get_public_key().unwrap(), None).unwrap();
+    }
+
+    #[test]
+    fn receipts_survive_restart() {
+        let (payer_dir, payee_dir) = (temp_dir(), temp_dir());
+        let (mut payer, mut payee) = (wallet(), wallet());
+        payer.set_data_dir(payer_dir.clone()).unwrap();
+        payee.set_data_dir(payee_dir.clone()).unwrap();
+        let tx = pay(&mut payer, &mut payee, 1000);
+
+        for dir in [&payer_dir, &payee_dir] {
+            let mut reloaded = BankingEngine::new();
+            reloaded.set_data_dir(dir.clone()).unwrap();
+            let receipt = reloaded.get_receipt(&tx.id).unwrap();
+            assert!(receipt.verify().unwrap());
+            assert_eq!(reloaded.get_receipts().len(), 1);
+        }
+        std::fs::remove_dir_all(payer_dir).ok();
+        std::fs::remove_dir_all(payee_dir).ok();
+    }
+}
@@ -0,0 +1,344 @@
+// Récupération du portefeuille à partir d'une phrase mnémonique (BIP39)
+//
+// Les clés ne sont plus tirées au hasard : la phrase de 12 mots donne une graine
+// BIP39, d'où l'on dérive les clés Ed25519 (dérivation durcie SLIP-0010, chemin
+// m/index') et l'identifiant du portefeuille. Retrouver la phrase suffit donc à
+// régénérer la même identité ; l'historique et les soldes sont ensuite rechargés
+// depuis le serveur de règlement.
+//
+// L'export de la phrase est protégé par un code PIN haché avec Argon2.
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use bip39::{Language, Mnemonic};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::succession::KeySuccession;
+use crate::{codec, crypto, Transaction};
+
+const SLIP10_CURVE_KEY: &[u8] = b"ed25519 seed";
+const HARDENED: u32 = 0x8000_0000;
+const MIN_PIN_LEN: usize = 4;
+/// Wrong PINs allowed before seed export is refused
+pub const MAX_PIN_ATTEMPTS: u32 = 5;
+
+/// New 12-word English mnemonic from 128 bits of entropy
+pub fn generate_mnemonic() -> String {
+    let entropy: [u8; 16] = rand::random();
+    // 16 bytes is a valid BIP39 entropy length
+    Mnemonic::from_entropy(&entropy).expect("valid entropy length").to_string()
+}
+
+fn parse_mnemonic(phrase: &str) -> Result<Mnemonic, String> {
+    let normalized = phrase.split_whitespace()
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Mnemonic::parse_in_normalized(Language::English, &normalized)
+        .map_err(|e| format!("Invalid recovery phrase: {}", e))
+}
+
+/// Check the word list and checksum, returning the phrase in canonical form
+pub fn normalize_mnemonic(phrase: &str) -> Result<String, String> {
+    parse_mnemonic(phrase).map(|m| m.to_string())
+}
+
+/// 64-byte BIP39 seed of a phrase (no passphrase)
+pub fn seed_from_mnemonic(phrase: &str) -> Result<[u8; 64], String> {
+    parse_mnemonic(phrase).map(|m| m.to_seed_normalized(""))
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// Ed25519 signing seed for key number `index` (hardened path m/index')
+pub fn derive_signing_seed(seed: &[u8; 64], index: u32) -> Result<[u8; 32], String> {
+    if index >= HARDENED {
+        return Err("Key index out of range".to_string());
+    }
+
+    let master = hmac_sha512(SLIP10_CURVE_KEY, seed);
+    let (master_key, chain_code) = master.split_at(32);
+
+    let mut data = Vec::with_capacity(37);
+    data.push(0);
+    data.extend_from_slice(master_key);
+    data.extend_from_slice(&(index | HARDENED).to_be_bytes());
+    let child = hmac_sha512(chain_code, &data);
+
+    let mut signing_seed = [0u8; 32];
+    signing_seed.copy_from_slice(&child[..32]);
+    Ok(signing_seed)
+}
+
+/// Wallet ID derived from the seed, so a restored wallet keeps its identity
+pub fn wallet_id(seed: &[u8; 64]) -> String {
+    let digest = Sha256::new()
+        .chain_update(b"fluxa wallet id")
+        .chain_update(seed)
+        .finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    uuid::Builder::from_random_bytes(bytes).into_uuid().to_string()
+}
+
+pub fn hash_pin(pin: &str) -> Result<String, String> {
+    if pin.len() < MIN_PIN_LEN || !pin.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("PIN must be at least {} digits", MIN_PIN_LEN));
+    }
+
+    let salt_bytes: [u8; 16] = rand::random();
+    let salt = SaltString::encode_b64(&salt_bytes).map_err(|e| e.to_string())?;
+    Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("PIN hashing failed: {}", e))
+}
+
+pub fn verify_pin(pin: &str, pin_hash: &str) -> Result<bool, String> {
+    let parsed = PasswordHash::new(pin_hash).map_err(|_| "Corrupted PIN hash".to_string())?;
+    Ok(Argon2::default().verify_password(pin.as_bytes(), &parsed).is_ok())
+}
+
+/// Account state returned by the settlement server for a restored wallet, signed by
+/// the issuer. Balances are the server's view: offline payments it has not seen yet
+/// are reconciled when their payees upload them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountSnapshot {
+    pub wallet_id: String,
+    pub online_balance: u64,
+    pub offline_balance: u64,
+    pub transactions: Vec<Transaction>,
+    /// Highest sequence number the server has seen from this wallet
+    pub last_sequence: u64,
+    /// Key rotations recorded by the server, to recover the current key
+    #[serde(default)]
+    pub key_successions: Vec<KeySuccession>,
+    pub issued_at: String,
+    pub issuer_public_key: String,
+    pub signature: String,
+}
+
+impl AccountSnapshot {
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut encoder = codec::Encoder::new("account-snapshot")
+            .str(&self.wallet_id)
+            .u64(self.online_balance)
+            .u64(self.offline_balance)
+            .u64(self.last_sequence)
+            .str(&self.issued_at)
+            .u64(self.transactions.len() as u64);
+        for tx in &self.transactions {
+            encoder = encoder
+                .bytes(&tx.signing_bytes())
+                .str(&tx.signature)
+                .str(&tx.status);
+        }
+        encoder = encoder.u64(self.key_successions.len() as u64);
+        for record in &self.key_successions {
+            encoder = encoder
+                .bytes(&record.signing_bytes())
+                .str(&record.old_key_signature)
+                .str(&record.new_key_signature);
+        }
+        encoder.finish()
+    }
+
+    /// Sign the snapshot; used by the issuer and in tests
+    pub fn sign(&mut self, issuer_public_key: &str, issuer_private_key: &str) -> Result<(), String> {
+        self.issuer_public_key = issuer_public_key.to_string();
+        self.signature = crypto::sign(issuer_private_key, &self.signing_bytes())?;
+        Ok(())
+    }
+
+    /// Whether the snapshot is signed by a trusted issuer
+    pub fn verify(&self, trusted_issuers: &[String]) -> Result<bool, String> {
+        if !trusted_issuers.contains(&self.issuer_public_key.to_lowercase()) {
+            return Ok(false);
+        }
+        crypto::verify(&self.issuer_public_key, &self.signing_bytes(), &self.signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::BankingEngine;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn snapshot() -> AccountSnapshot {
+        AccountSnapshot {
+            wallet_id: "wallet-a".to_string(),
+            online_balance: 1000,
+            offline_balance: 500,
+            transactions: Vec::new(),
+            last_sequence: 3,
+            key_successions: Vec::new(),
+            issued_at: "2026-10-01T00:00:00+00:00".to_string(),
+            issuer_public_key: String::new(),
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn same_phrase_gives_same_identity() {
+        let seed = seed_from_mnemonic(PHRASE).unwrap();
+        let messy = PHRASE.to_uppercase().replace(' ', "   ");
+        assert_eq!(normalize_mnemonic(&messy).unwrap(), PHRASE);
+        assert_eq!(wallet_id(&seed), wallet_id(&seed_from_mnemonic(&messy).unwrap()));
+        assert_eq!(derive_signing_seed(&seed, 0).unwrap(), derive_signing_seed(&seed, 0).unwrap());
+        assert_ne!(derive_signing_seed(&seed, 0).unwrap(), derive_signing_seed(&seed, 1).unwrap());
+    }
+
+    #[test]
+    fn bad_checksum_is_rejected() {
+        let phrase = PHRASE.replace("about", "abandon");
+        assert!(normalize_mnemonic(&phrase).is_err());
+        assert!(normalize_mnemonic(&generate_mnemonic()).is_ok());
+    }
+
+    #[test]
+    fn pin_hash_round_trip() {
+        let hash = hash_pin("1234").unwrap();
+        assert!(verify_pin("1234", &hash).unwrap());
+        assert!(!verify_pin("4321", &hash).unwrap());
+        assert!(hash_pin("12a4").is_err());
+        assert!(hash_pin("123").is_err());
+    }
+
+    #[test]
+    fn snapshot_needs_trusted_issuer_signature() {
+        let (issuer_private, issuer_public) = crypto::generate_keypair();
+        let trusted = vec![issuer_public.clone()];
+
+        let mut snap = snapshot();
+        assert!(!snap.verify(&trusted).unwrap());
+
+        snap.sign(&issuer_public, &issuer_private).unwrap();
+        assert!(snap.verify(&trusted).unwrap());
+        assert!(!snap.verify(&[]).unwrap());
+
+        snap.online_balance += 1;
+        assert!(!snap.verify(&trusted).unwrap());
+    }
+
+    #[test]
+    fn invalid_phrase_keeps_current_identity() {
+        let mut engine = wallet();
+        let key = engine.get_public_key().unwrap();
+        let wallet_id = engine.get_wallet().id;
+
+        assert!(engine.restore_from_mnemonic("not a recovery phrase", None).is_err());
+        assert_eq!(engine.get_public_key().unwrap(), key);
+        assert_eq!(engine.get_wallet().id, wallet_id);
+        assert_eq!(engine.get_wallet().total_balance, 40000);
+    }
+
+    #[test]
+    fn restore_requires_current_pin() {
+        let mut engine = wallet();
+        engine.set_pin("1234", None).unwrap();
+        let phrase = generate_mnemonic();
+
+        assert!(engine.restore_from_mnemonic(&phrase, None).is_err());
+        assert!(engine.restore_from_mnemonic(&phrase, Some("9999")).is_err());
+        assert_eq!(engine.get_wallet().total_balance, 40000);
+
+        let wallet = engine.restore_from_mnemonic(&phrase, Some("1234")).unwrap();
+        assert_eq!(wallet.total_balance, 0);
+        assert!(engine.restore_pending);
+    }
+
+    #[test]
+    fn restore_gives_same_identity() {
+        let original = wallet();
+        let phrase = original.mnemonic.clone().unwrap();
+        let mut restored = BankingEngine::new();
+        restored.restore_from_mnemonic(&phrase, None).unwrap();
+
+        assert_eq!(restored.get_wallet().id, original.get_wallet().id);
+        assert_eq!(restored.get_public_key().unwrap(), original.get_public_key().unwrap());
+        assert!(restored.create_offline_transaction(original.get_wallet().id, "Shop".to_string(), 10, None).is_err());
+    }
+
+    #[test]
+    fn snapshot_only_applies_during_restore() {
+        let mut engine = wallet();
+        let (issuer_private, issuer_public) = trust_issuer(&mut engine);
+        let mut snapshot = snapshot_for(&engine, 1_000_000, 0);
+        snapshot.sign(&issuer_public, &issuer_private).unwrap();
+
+        assert!(engine.apply_account_snapshot(snapshot).is_err());
+        assert_eq!(engine.get_wallet().total_balance, 40000);
+    }
+
+    #[test]
+    fn snapshot_must_be_signed_by_trusted_issuer() {
+        let mut engine = BankingEngine::new();
+        engine.restore_from_mnemonic(&generate_mnemonic(), None).unwrap();
+        let (issuer_private, issuer_public) = trust_issuer(&mut engine);
+
+        let unsigned = snapshot_for(&engine, 500, 500);
+        assert!(engine.apply_account_snapshot(unsigned).is_err());
+
+        let (rogue_private, rogue_public) = crypto::generate_keypair();
+        let mut rogue = snapshot_for(&engine, 500, 500);
+        rogue.sign(&rogue_public, &rogue_private).unwrap();
+        assert!(engine.apply_account_snapshot(rogue).is_err());
+        assert!(engine.restore_pending);
+
+        let mut snapshot = snapshot_for(&engine, 700, 300);
+        snapshot.last_sequence = 4;
+        snapshot.sign(&issuer_public, &issuer_private).unwrap();
+        let wallet = engine.apply_account_snapshot(snapshot).unwrap();
+        assert_eq!(wallet.total_balance, 1000);
+        assert!(!engine.restore_pending);
+        assert_eq!(engine.next_sequence, 4);
+    }
+
+    #[test]
+    fn snapshot_balances_must_not_overflow() {
+        let mut engine = BankingEngine::new();
+        engine.restore_from_mnemonic(&generate_mnemonic(), None).unwrap();
+        let (issuer_private, issuer_public) = trust_issuer(&mut engine);
+
+        let mut snapshot = snapshot_for(&engine, u64::MAX, 1);
+        snapshot.last_sequence = 4;
+        snapshot.sign(&issuer_public, &issuer_private).unwrap();
+        assert!(engine.apply_account_snapshot(snapshot).is_err());
+        assert!(engine.restore_pending);
+        assert_eq!(engine.next_sequence, 0);
+        assert_eq!(engine.get_wallet().total_balance, 0);
+    }
+
+    #[test]
+    fn identity_and_pin_state_survive_restart() {
+        let dir = temp_dir();
+        let mut engine = BankingEngine::new();
+        engine.set_data_dir(dir.clone()).unwrap();
+        engine.initialize_keys().unwrap();
+        engine.set_pin("1234", None).unwrap();
+        assert!(engine.export_seed_phrase("0000").is_err());
+        let tx = engine.create_offline_transaction("wallet-b".to_string(), "Shop".to_string(), 100, None).unwrap();
+
+        let mut reloaded = BankingEngine::new();
+        reloaded.set_data_dir(dir.clone()).unwrap();
+        assert_eq!(reloaded.get_wallet().id, engine.get_wallet().id);
+        assert_eq!(reloaded.get_public_key().unwrap(), engine.get_public_key().unwrap());
+        assert_eq!(reloaded.get_wallet().offline_balance, 15000 - 100);
+        assert_eq!(reloaded.failed_pin_attempts, 1);
+        assert_eq!(reloaded.next_sequence, tx.sequence);
+        assert!(reloaded.export_seed_phrase("1234").is_ok());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,248 @@
+// Remboursements (totaux ou partiels) émis par le destinataire d'un paiement confirmé
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::{codec, crypto};
+use crate::annotations::TxAnnotations;
+use crate::Transaction;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Refund {
+    pub id: String,
+    pub original_tx_id: String,
+    pub refunder_wallet_id: String,
+    pub refunder_public_key: String,
+    pub recipient_wallet_id: String,
+    pub amount: u64,
+    pub reason: String,
+    pub timestamp: String,
+    pub signature: String,
+}
+
+impl Refund {
+    /// Create a refund for `original` signed by its receiver
+    pub fn issue(
+        original: &Transaction,
+        amount: u64,
+        reason: String,
+        refunder_public_key: &str,
+        refunder_private_key: &str,
+    ) -> Result<Self, String> {
+        let mut refund = Refund {
+            id: Uuid::new_v4().to_string(),
+            original_tx_id: original.id.clone(),
+            refunder_wallet_id: original.to_wallet_id.clone(),
+            refunder_public_key: refunder_public_key.to_string(),
+            recipient_wallet_id: original.from_wallet_id.clone(),
+            amount,
+            reason,
+            timestamp: Utc::now().to_rfc3339(),
+            signature: String::new(),
+        };
+
+        refund.signature = crypto::sign(refunder_private_key, &refund.signing_bytes())?;
+        Ok(refund)
+    }
+
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        codec::Encoder::new("refund")
+            .str(&self.id)
+            .str(&self.original_tx_id)
+            .str(&self.refunder_wallet_id)
+            .str(&self.refunder_public_key)
+            .str(&self.recipient_wallet_id)
+            .u64(self.amount)
+            .str(&self.reason)
+            .str(&self.timestamp)
+            .finish()
+    }
+
+    pub fn verify(&self) -> Result<bool, String> {
+        crypto::verify(
+            &self.refunder_public_key,
+            &self.signing_bytes(),
+            &self.signature,
+        )
+    }
+
+    /// Ledger entry recording this refund, linked to the original through `refunds`
+    pub fn to_transaction(&self, original: &Transaction) -> Transaction {
+        Transaction {
+            id: self.id.clone(),
+            from_wallet_id: self.refunder_wallet_id.clone(),
+            to_wallet_id: self.recipient_wallet_id.clone(),
+            merchant_name: original.merchant_name.clone(),
+            amount: self.amount,
+            timestamp: self.timestamp.clone(),
+            signature: self.signature.clone(),
+            tx_type: "refund".to_string(),
+            status: "confirmed".to_string(),
+            request_id: None,
+            sequence: 0,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
+        }
+    }
+}
+
+/// Sum of refunds already recorded against `original_tx_id`
+pub fn refunded_total(refunds: &[Refund], original_tx_id: &str) -> Result<u64, String> {
+    refunds.iter()
+        .filter(|r| r.original_tx_id == original_tx_id)
+        .try_fold(0u64, |total, r| total.checked_add(r.amount))
+        .ok_or("Refund total overflow".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::BankingEngine;
+
+    fn payment() -> Transaction {
+        Transaction {
+            id: "tx-1".to_string(),
+            from_wallet_id: "wallet-a".to_string(),
+            to_wallet_id: "wallet-b".to_string(),
+            merchant_name: "Shop".to_string(),
+            amount: 1000,
+            timestamp: "2026-10-01T10:00:00+00:00".to_string(),
+            signature: String::new(),
+            tx_type: "offline".to_string(),
+            status: "confirmed".to_string(),
+            request_id: None,
+            sequence: 1,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
+        }
+    }
+
+    #[test]
+    fn refund_is_signed_by_refunder() {
+        let (private_key, public_key) = crypto::generate_keypair();
+        let refund = Refund::issue(&payment(), 400, "damaged".to_string(), &public_key, &private_key).unwrap();
+        assert!(refund.verify().unwrap());
+        assert_eq!(refund.refunder_wallet_id, "wallet-b");
+        assert_eq!(refund.recipient_wallet_id, "wallet-a");
+
+        let mut tampered = refund.clone();
+        tampered.amount = 1000;
+        assert!(!tampered.verify().unwrap());
+    }
+
+    #[test]
+    fn refunded_total_rejects_overflow() {
+        let (private_key, public_key) = crypto::generate_keypair();
+        let mut first = Refund::issue(&payment(), 1, String::new(), &public_key, &private_key).unwrap();
+        let mut second = first.clone();
+        assert_eq!(refunded_total(&[first.clone(), second.clone()], "tx-1").unwrap(), 2);
+        assert_eq!(refunded_total(&[first.clone()], "tx-2").unwrap(), 0);
+
+        first.amount = u64::MAX;
+        second.amount = 1;
+        assert!(refunded_total(&[first, second], "tx-1").is_err());
+    }
+
+    #[test]
+    fn partial_refunds_up_to_original_amount() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        let tx = pay(&mut payer, &mut payee, 1000);
+
+        let first = payee.issue_refund(tx.id.clone(), 600, "damaged".to_string()).unwrap();
+        assert!(payee.issue_refund(tx.id.clone(), 500, "again".to_string()).is_err());
+        assert!(payee.issue_refund(tx.id.clone(), u64::MAX, "overflow".to_string()).is_err());
+        let second = payee.issue_refund(tx.id.clone(), 400, "rest".to_string()).unwrap();
+
+        let before = payer.get_wallet().offline_balance;
+        payer.apply_refund(first.clone()).unwrap();
+        payer.apply_refund(second).unwrap();
+        assert_eq!(payer.get_wallet().offline_balance, before + 1000);
+        assert!(payer.apply_refund(first).is_err());
+    }
+
+    #[test]
+    fn refund_of_cancelled_payment_is_refused() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 1000, None).unwrap();
+        payee.receive_payment(tx.clone(), payer.get_public_key().unwrap(), None).unwrap();
+        payer.cancel_transaction(tx.id.clone()).unwrap();
+        payer.contacts.add("Shop", &payee.get_wallet().id, &payee.get_public_key().unwrap()).unwrap();
+
+        let refund = payee.issue_refund(tx.id.clone(), 1000, "refund".to_string()).unwrap();
+        let before = payer.get_wallet().total_balance;
+        assert!(payer.apply_refund(refund).is_err());
+        assert_eq!(payer.get_wallet().total_balance, before);
+    }
+
+    #[test]
+    fn refund_needs_known_payee_key() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        let payee_id = payee.get_wallet().id;
+        let tx = payer.create_offline_transaction(payee_id.clone(), "Shop".to_string(), 1000, None).unwrap();
+        payee.receive_payment(tx.clone(), payer.get_public_key().unwrap(), None).unwrap();
+        // Confirmed by the server, without a receipt from the payee
+        payer.confirm_transaction(tx.id.clone()).unwrap();
+
+        let refund = payee.issue_refund(tx.id.clone(), 300, "refund".to_string()).unwrap();
+        assert!(payer.apply_refund(refund.clone()).is_err());
+
+        // A key that merely claims to be the payee's is not enough
+        let (forger_private, forger_public) = crypto::generate_keypair();
+        let original = payer.transactions.get(&tx.id).unwrap().clone();
+        let mut forged = Refund::issue(&original, 300, "refund".to_string(), &forger_public, &forger_private).unwrap();
+        forged.refunder_wallet_id = payee_id.clone();
+        payer.contacts.add("Shop", &payee_id, &payee.get_public_key().unwrap()).unwrap();
+        assert!(payer.apply_refund(forged).is_err());
+
+        payer.apply_refund(refund).unwrap();
+    }
+
+    #[test]
+    fn applied_refunds_survive_restart() {
+        let dir = temp_dir();
+        let mut payer = wallet();
+        payer.set_data_dir(dir.clone()).unwrap();
+        let mut payee = wallet();
+        let tx = pay(&mut payer, &mut payee, 1000);
+
+        let refund = payee.issue_refund(tx.id.clone(), 600, "damaged".to_string()).unwrap();
+        payer.apply_refund(refund.clone()).unwrap();
+
+        let mut reloaded = BankingEngine::new();
+        reloaded.set_data_dir(dir.clone()).unwrap();
+        let balance = reloaded.get_wallet().offline_balance;
+        let count = reloaded.get_transactions().len();
+        assert_eq!(reloaded.get_refunds(&tx.id).len(), 1);
+        assert!(reloaded.apply_refund(refund).is_err());
+
+        // Signed by the payee, but over what is left of the original payment
+        let payee_key = payee.keypair.clone().unwrap();
+        let original = reloaded.transactions.get(&tx.id).unwrap().clone();
+        let excess = Refund::issue(&original, 500, "again".to_string(), &payee_key.public_key, &payee_key.private_key).unwrap();
+        assert!(reloaded.apply_refund(excess).is_err());
+
+        assert_eq!(reloaded.get_wallet().offline_balance, balance);
+        assert_eq!(reloaded.get_transactions().len(), count);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn issued_refunds_survive_restart() {
+        let dir = temp_dir();
+        let mut payer = wallet();
+        let mut payee = wallet();
+        payee.set_data_dir(dir.clone()).unwrap();
+        let tx = pay(&mut payer, &mut payee, 1000);
+        payee.issue_refund(tx.id.clone(), 600, "damaged".to_string()).unwrap();
+
+        let mut reloaded = BankingEngine::new();
+        reloaded.set_data_dir(dir.clone()).unwrap();
+        assert!(reloaded.issue_refund(tx.id.clone(), 500, "again".to_string()).is_err());
+        reloaded.issue_refund(tx.id.clone(), 400, "rest".to_string()).unwrap();
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
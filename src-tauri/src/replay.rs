@@ -0,0 +1,145 @@
+// Protection contre le rejeu des paiements P2P reçus
+//
+// Chaque paiement accepté est mémorisé par ID et par (payeur, numéro de séquence).
+// Une fois réglés par le serveur, les paiements sont retirés de la liste et seul le plus
+// grand numéro de séquence réglé par payeur est conservé : la taille reste bornée par
+// le nombre de paiements non réglés et de payeurs connus.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use chrono::Utc;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeenPayment {
+    pub tx_id: String,
+    pub sender_wallet_id: String,
+    pub sequence: u64,
+    pub received_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayGuard {
+    unsettled: Vec<SeenPayment>,
+    /// Highest settled sequence number per sender; anything at or below it is a replay
+    settled_floor: HashMap<String, u64>,
+}
+
+impl ReplayGuard {
+    pub fn check(&self, tx_id: &str, sender_wallet_id: &str, sequence: u64) -> Result<(), String> {
+        if sequence == 0 {
+            return Err("Missing sender sequence number".to_string());
+        }
+
+        if self.unsettled.iter().any(|p| p.tx_id == tx_id) {
+            return Err("Payment already received".to_string());
+        }
+
+        if self.unsettled.iter()
+            .any(|p| p.sender_wallet_id == sender_wallet_id && p.sequence == sequence)
+        {
+            return Err("Sender sequence number already used".to_string());
+        }
+
+        if let Some(floor) = self.settled_floor.get(sender_wallet_id) {
+            if sequence <= *floor {
+                return Err("Sender sequence number already settled".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn record(&mut self, tx_id: &str, sender_wallet_id: &str, sequence: u64) {
+        self.unsettled.push(SeenPayment {
+            tx_id: tx_id.to_string(),
+            sender_wallet_id: sender_wallet_id.to_string(),
+            sequence,
+            received_at: Utc::now().to_rfc3339(),
+        });
+    }
+
+    /// Drop settled payments, keeping only each sender's highest settled sequence.
+    /// Returns how many entries were pruned.
+    pub fn mark_settled(&mut self, tx_ids: &[String]) -> usize {
+        let before = self.unsettled.len();
+        let floors = &mut self.settled_floor;
+
+        self.unsettled.retain(|p| {
+            if !tx_ids.contains(&p.tx_id) {
+                return true;
+            }
+            let floor = floors.entry(p.sender_wallet_id.clone()).or_insert(0);
+            *floor = (*floor).max(p.sequence);
+            false
+        });
+
+        before - self.unsettled.len()
+    }
+
+    pub fn unsettled_count(&self) -> usize {
+        self.unsettled.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::BankingEngine;
+
+    #[test]
+    fn rejects_repeated_id_and_sequence() {
+        let mut guard = ReplayGuard::default();
+        assert!(guard.check("tx-1", "wallet-a", 0).is_err());
+        guard.check("tx-1", "wallet-a", 1).unwrap();
+        guard.record("tx-1", "wallet-a", 1);
+
+        assert!(guard.check("tx-1", "wallet-a", 2).is_err());
+        assert!(guard.check("tx-2", "wallet-a", 1).is_err());
+        guard.check("tx-2", "wallet-b", 1).unwrap();
+    }
+
+    #[test]
+    fn settled_payments_leave_a_floor() {
+        let mut guard = ReplayGuard::default();
+        guard.record("tx-1", "wallet-a", 1);
+        guard.record("tx-2", "wallet-a", 2);
+        guard.record("tx-3", "wallet-a", 3);
+
+        assert_eq!(guard.mark_settled(&["tx-1".to_string(), "tx-2".to_string()]), 2);
+        assert_eq!(guard.unsettled_count(), 1);
+        assert!(guard.check("tx-9", "wallet-a", 2).is_err());
+        assert!(guard.check("tx-3", "wallet-a", 4).is_err());
+        guard.check("tx-4", "wallet-a", 4).unwrap();
+    }
+
+    #[test]
+    fn failed_save_leaves_received_payment_unapplied() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        let dir = temp_dir();
+        payee.set_data_dir(dir.clone()).unwrap();
+        // A directory in place of the file makes the write fail
+        std::fs::create_dir_all(dir.join("replay_guard.json")).unwrap();
+
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 1000, None).unwrap();
+        let key = payer.get_public_key().unwrap();
+        assert!(payee.receive_payment(tx.clone(), key.clone(), None).is_err());
+        assert_eq!(payee.get_wallet().offline_balance, 15000);
+        // Seul le dépôt d'ouverture figure au registre
+        assert_eq!(payee.get_transactions().len(), 1);
+        assert!(payee.get_receipts().is_empty());
+        assert!(payee.get_contacts().is_empty());
+
+        std::fs::remove_dir(dir.join("replay_guard.json")).unwrap();
+        payee.receive_payment(tx.clone(), key.clone(), None).unwrap();
+        assert_eq!(payee.get_wallet().offline_balance, 16000);
+        assert!(payee.receive_payment(tx, key, None).is_err());
+
+        let mut reloaded = BankingEngine::new();
+        reloaded.set_data_dir(dir.clone()).unwrap();
+        assert_eq!(reloaded.get_wallet().offline_balance, 16000);
+        assert_eq!(reloaded.get_contacts().len(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,324 @@
+// Liste de révocation des portefeuilles (téléphones perdus ou volés)
+//
+// L'émetteur publie une liste signée et versionnée des portefeuilles révoqués. Un
+// appareil la télécharge en entier une première fois, puis par deltas (ajouts et
+// retraits entre deux versions) quand il est en ligne. Chaque delta porte l'empreinte
+// de la liste obtenue après application, ce qui détecte une copie locale divergente.
+// Hors ligne, les paiements entrants d'un portefeuille révoqué sont refusés ; l'âge de
+// la liste est exposé pour que le marchand sache depuis quand il n'est plus protégé.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use chrono::{DateTime, Utc};
+
+use crate::{codec, crypto};
+
+/// Lists older than this are reported as stale
+pub const STALE_AFTER_SECS: i64 = 24 * 3600;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationEntry {
+    pub wallet_id: String,
+    pub public_key: String,
+    pub revoked_at: String,
+    pub reason: String,
+}
+
+fn encode_entries(encoder: codec::Encoder, entries: &[RevocationEntry]) -> codec::Encoder {
+    entries.iter().fold(encoder.u64(entries.len() as u64), |enc, e| {
+        enc.str(&e.wallet_id).str(&e.public_key).str(&e.revoked_at).str(&e.reason)
+    })
+}
+
+/// Digest of a set of entries, independent of their order
+pub fn set_digest(entries: &[RevocationEntry]) -> String {
+    let mut sorted = entries.to_vec();
+    sorted.sort_by(|a, b| a.public_key.cmp(&b.public_key));
+    hex::encode(Sha256::digest(encode_entries(codec::Encoder::new("revocation-set"), &sorted).finish()))
+}
+
+/// Complete list as published by the issuer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevocationList {
+    pub version: u64,
+    pub issued_at: String,
+    pub entries: Vec<RevocationEntry>,
+    pub issuer_public_key: String,
+    pub signature: String,
+}
+
+impl RevocationList {
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let encoder = codec::Encoder::new("revocation-list")
+            .u64(self.version)
+            .str(&self.issued_at);
+        encode_entries(encoder, &self.entries).finish()
+    }
+}
+
+/// Changes between two list versions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevocationDelta {
+    pub from_version: u64,
+    pub to_version: u64,
+    pub issued_at: String,
+    pub added: Vec<RevocationEntry>,
+    /// Public keys taken off the list (device recovered, revoked by mistake)
+    pub removed: Vec<String>,
+    /// `set_digest` of the list once the delta is applied
+    pub digest: String,
+    pub issuer_public_key: String,
+    pub signature: String,
+}
+
+impl RevocationDelta {
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let encoder = codec::Encoder::new("revocation-delta")
+            .u64(self.from_version)
+            .u64(self.to_version)
+            .str(&self.issued_at);
+        let encoder = encode_entries(encoder, &self.added).u64(self.removed.len() as u64);
+        self.removed.iter()
+            .fold(encoder, |enc, key| enc.str(key))
+            .str(&self.digest)
+            .finish()
+    }
+}
+
+fn check_issuer(
+    issuer_public_key: &str,
+    data: &[u8],
+    signature: &str,
+    trusted_issuers: &[String],
+) -> Result<(), String> {
+    if !trusted_issuers.contains(&issuer_public_key.to_lowercase()) {
+        return Err("Revocation list signed by an unknown issuer".to_string());
+    }
+    if !crypto::verify(issuer_public_key, data, signature)? {
+        return Err("Invalid revocation list signature".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevocationStatus {
+    pub version: u64,
+    /// When the issuer produced the list we hold
+    pub issued_at: Option<String>,
+    /// When this device last applied an update
+    pub updated_at: Option<String>,
+    pub age_secs: Option<i64>,
+    pub stale: bool,
+    pub revoked_count: usize,
+}
+
+/// Local copy of the revocation list
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RevocationState {
+    version: u64,
+    issued_at: Option<String>,
+    updated_at: Option<String>,
+    entries: Vec<RevocationEntry>,
+}
+
+impl RevocationState {
+    /// Replace the local copy with a full list; older versions are refused
+    pub fn import_list(&mut self, list: RevocationList, trusted_issuers: &[String]) -> Result<(), String> {
+        check_issuer(&list.issuer_public_key, &list.signing_bytes(), &list.signature, trusted_issuers)?;
+
+        if list.version < self.version {
+            return Err("Revocation list older than the local copy".to_string());
+        }
+
+        self.version = list.version;
+        self.issued_at = Some(list.issued_at);
+        self.updated_at = Some(Utc::now().to_rfc3339());
+        self.entries = list.entries;
+        Ok(())
+    }
+
+    pub fn apply_delta(&mut self, delta: RevocationDelta, trusted_issuers: &[String]) -> Result<(), String> {
+        check_issuer(&delta.issuer_public_key, &delta.signing_bytes(), &delta.signature, trusted_issuers)?;
+
+        if delta.from_version != self.version || delta.to_version <= delta.from_version {
+            return Err(format!(
+                "Delta {} -> {} does not apply to local version {}; fetch the full list",
+                delta.from_version, delta.to_version, self.version
+            ));
+        }
+
+        let mut entries: Vec<RevocationEntry> = self.entries.iter()
+            .filter(|e| !delta.removed.contains(&e.public_key))
+            .cloned()
+            .collect();
+        for entry in delta.added {
+            if !entries.iter().any(|e| e.public_key == entry.public_key) {
+                entries.push(entry);
+            }
+        }
+
+        if set_digest(&entries) != delta.digest {
+            return Err("Local revocation list diverged; fetch the full list".to_string());
+        }
+
+        self.version = delta.to_version;
+        self.issued_at = Some(delta.issued_at);
+        self.updated_at = Some(Utc::now().to_rfc3339());
+        self.entries = entries;
+        Ok(())
+    }
+
+    /// Revocation entry covering this wallet or key, if any
+    pub fn find(&self, wallet_id: &str, public_key: &str) -> Option<&RevocationEntry> {
+        self.entries.iter().find(|e| e.wallet_id == wallet_id || e.public_key == public_key)
+    }
+
+    pub fn status(&self) -> RevocationStatus {
+        let age_secs = self.issued_at.as_ref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| (Utc::now() - t.with_timezone(&Utc)).num_seconds());
+
+        RevocationStatus {
+            version: self.version,
+            issued_at: self.issued_at.clone(),
+            updated_at: self.updated_at.clone(),
+            age_secs,
+            stale: age_secs.map(|age| age > STALE_AFTER_SECS).unwrap_or(true),
+            revoked_count: self.entries.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    struct Issuer {
+        private_key: String,
+        public_key: String,
+    }
+
+    impl Issuer {
+        fn new() -> Self {
+            let (private_key, public_key) = crypto::generate_keypair();
+            Issuer { private_key, public_key }
+        }
+
+        fn trusted(&self) -> Vec<String> {
+            vec![self.public_key.clone()]
+        }
+
+        fn list(&self, version: u64, issued_at: DateTime<Utc>, entries: Vec<RevocationEntry>) -> RevocationList {
+            let mut list = RevocationList {
+                version,
+                issued_at: issued_at.to_rfc3339(),
+                entries,
+                issuer_public_key: self.public_key.clone(),
+                signature: String::new(),
+            };
+            list.signature = crypto::sign(&self.private_key, &list.signing_bytes()).unwrap();
+            list
+        }
+
+        fn delta(&self, from_version: u64, added: Vec<RevocationEntry>, removed: Vec<String>, result: &[RevocationEntry]) -> RevocationDelta {
+            let mut delta = RevocationDelta {
+                from_version,
+                to_version: from_version + 1,
+                issued_at: Utc::now().to_rfc3339(),
+                added,
+                removed,
+                digest: set_digest(result),
+                issuer_public_key: self.public_key.clone(),
+                signature: String::new(),
+            };
+            delta.signature = crypto::sign(&self.private_key, &delta.signing_bytes()).unwrap();
+            delta
+        }
+    }
+
+    fn entry(name: &str) -> RevocationEntry {
+        RevocationEntry {
+            wallet_id: format!("wallet-{}", name),
+            public_key: format!("key-{}", name),
+            revoked_at: "2026-10-01T10:00:00+00:00".to_string(),
+            reason: "Téléphone volé".to_string(),
+        }
+    }
+
+    #[test]
+    fn digest_ignores_entry_order() {
+        assert_eq!(set_digest(&[entry("a"), entry("b")]), set_digest(&[entry("b"), entry("a")]));
+        assert_ne!(set_digest(&[entry("a")]), set_digest(&[entry("a"), entry("b")]));
+    }
+
+    #[test]
+    fn full_list_must_be_signed_by_a_trusted_issuer() {
+        let (issuer, rogue) = (Issuer::new(), Issuer::new());
+        let mut state = RevocationState::default();
+
+        assert!(state.import_list(rogue.list(1, Utc::now(), vec![entry("a")]), &issuer.trusted()).is_err());
+        let mut tampered = issuer.list(1, Utc::now(), vec![entry("a")]);
+        tampered.entries.clear();
+        assert!(state.import_list(tampered, &issuer.trusted()).is_err());
+
+        state.import_list(issuer.list(2, Utc::now(), vec![entry("a")]), &issuer.trusted()).unwrap();
+        assert!(state.import_list(issuer.list(1, Utc::now(), Vec::new()), &issuer.trusted()).is_err());
+        assert_eq!(state.status().version, 2);
+    }
+
+    #[test]
+    fn revoked_wallet_is_found_by_id_or_key() {
+        let issuer = Issuer::new();
+        let mut state = RevocationState::default();
+        state.import_list(issuer.list(1, Utc::now(), vec![entry("a")]), &issuer.trusted()).unwrap();
+
+        assert!(state.find("wallet-a", "other-key").is_some());
+        assert!(state.find("wallet-x", "key-a").is_some());
+        assert!(state.find("wallet-b", "key-b").is_none());
+    }
+
+    #[test]
+    fn deltas_add_and_remove_entries() {
+        let issuer = Issuer::new();
+        let mut state = RevocationState::default();
+        state.import_list(issuer.list(1, Utc::now(), vec![entry("a"), entry("b")]), &issuer.trusted()).unwrap();
+
+        let delta = issuer.delta(1, vec![entry("c")], vec!["key-a".to_string()], &[entry("b"), entry("c")]);
+        state.apply_delta(delta, &issuer.trusted()).unwrap();
+        assert_eq!(state.status().version, 2);
+        assert!(state.find("wallet-a", "key-a").is_none());
+        assert!(state.find("wallet-c", "key-c").is_some());
+        assert_eq!(state.status().revoked_count, 2);
+    }
+
+    #[test]
+    fn out_of_sequence_or_diverged_delta_is_refused() {
+        let issuer = Issuer::new();
+        let mut state = RevocationState::default();
+        state.import_list(issuer.list(1, Utc::now(), vec![entry("a")]), &issuer.trusted()).unwrap();
+
+        let skipped = issuer.delta(2, vec![entry("c")], Vec::new(), &[entry("a"), entry("c")]);
+        assert!(state.apply_delta(skipped, &issuer.trusted()).is_err());
+
+        // L'empreinte annoncée ne correspond pas à la copie locale
+        let diverged = issuer.delta(1, vec![entry("c")], Vec::new(), &[entry("c")]);
+        assert!(state.apply_delta(diverged, &issuer.trusted()).unwrap_err().contains("diverged"));
+        assert_eq!(state.status().version, 1);
+        assert!(state.find("wallet-c", "key-c").is_none());
+    }
+
+    #[test]
+    fn old_or_missing_list_is_stale() {
+        let issuer = Issuer::new();
+        let mut state = RevocationState::default();
+        assert!(state.status().stale);
+
+        state.import_list(issuer.list(1, Utc::now(), Vec::new()), &issuer.trusted()).unwrap();
+        assert!(!state.status().stale);
+
+        let old = Utc::now() - Duration::seconds(STALE_AFTER_SECS + 60);
+        state.import_list(issuer.list(2, old, Vec::new()), &issuer.trusted()).unwrap();
+        assert!(state.status().stale);
+    }
+}
@@ -0,0 +1,418 @@
+// Exports de l'historique et relevés mensuels
+//
+// Les exports CSV et JSON couvrent une période choisie, dans l'ordre chronologique,
+// et reprennent toutes les transactions avec leur statut. Le relevé mensuel rejoue
+// le registre depuis le dépôt d'ouverture : ouverture + crédits - débits = clôture.
+// Le solde obtenu en rejouant tout le registre est comparé au solde total affiché
+// par le portefeuille, et tout écart est signalé sur le relevé au lieu d'être
+// absorbé. Les virements entre le compte en ligne et le coffre ne changent pas le
+// solde total et n'y figurent pas, pas plus que les transactions annulées.
+
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Months, NaiveDate, Utc};
+
+use crate::contacts::ContactBook;
+use crate::stats::{channel_of, is_internal};
+use crate::Transaction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRow {
+    pub timestamp: String,
+    pub tx_id: String,
+    pub tx_type: String,
+    pub status: String,
+    /// "in", "out" or "internal" (vault transfers)
+    pub direction: String,
+    pub counterparty: String,
+    pub merchant_name: String,
+    pub channel: String,
+    pub amount: u64,
+}
+
+impl ExportRow {
+    pub fn from_transaction(tx: &Transaction, wallet_id: &str) -> Self {
+        let (direction, counterparty) = if is_internal(tx, wallet_id) {
+            ("internal", tx.to_wallet_id.clone())
+        } else if tx.to_wallet_id == wallet_id {
+            ("in", tx.from_wallet_id.clone())
+        } else {
+            ("out", tx.to_wallet_id.clone())
+        };
+
+        ExportRow {
+            timestamp: tx.timestamp.clone(),
+            tx_id: tx.id.clone(),
+            tx_type: tx.tx_type.clone(),
+            status: tx.status.clone(),
+            direction: direction.to_string(),
+            counterparty,
+            merchant_name: tx.merchant_name.clone(),
+            channel: channel_of(tx),
+            amount: tx.amount,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryExport {
+    pub wallet_id: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub generated_at: String,
+    pub rows: Vec<ExportRow>,
+}
+
+const CSV_HEADER: &str = "timestamp,tx_id,tx_type,status,direction,counterparty,merchant_name,channel,amount";
+
+fn csv_field(value: &str) -> String {
+    // Merchant names come from peers; keep spreadsheets from reading them as formulas
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+pub fn to_csv(rows: &[ExportRow]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+    for row in rows {
+        let fields = [
+            &row.timestamp,
+            &row.tx_id,
+            &row.tx_type,
+            &row.status,
+            &row.direction,
+            &row.counterparty,
+            &row.merchant_name,
+            &row.channel,
+        ];
+        for field in fields {
+            out.push_str(&csv_field(field));
+            out.push(',');
+        }
+        out.push_str(&row.amount.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementEntry {
+    pub timestamp: String,
+    pub tx_id: String,
+    pub description: String,
+    pub tx_type: String,
+    pub status: String,
+    pub channel: String,
+    pub credit: u64,
+    pub debit: u64,
+    /// Total balance after this entry
+    pub balance: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthlyStatement {
+    pub wallet_id: String,
+    /// Merchant display name, when a profile is set
+    pub account_name: Option<String>,
+    /// "2026-10"
+    pub month: String,
+    pub period_start: String,
+    /// Exclusive
+    pub period_end: String,
+    pub opening_balance: u64,
+    pub total_credits: u64,
+    pub total_debits: u64,
+    pub closing_balance: u64,
+    /// Balance obtained by replaying the whole ledger
+    pub ledger_balance: u64,
+    /// Total balance shown by the wallet when the statement was built
+    pub wallet_balance: u64,
+    /// False when the ledger does not add up to the wallet balance
+    pub reconciled: bool,
+    pub entries: Vec<StatementEntry>,
+    pub generated_at: String,
+}
+
+/// First instant of `month` ("YYYY-MM") and of the month after, in UTC
+pub fn month_bounds(month: &str) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+    let start = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid month: {}", month))?;
+    let end = start.checked_add_months(Months::new(1))
+        .ok_or_else(|| format!("Invalid month: {}", month))?;
+    Ok((
+        start.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+        end.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+    ))
+}
+
+/// Change a transaction made to the total balance
+fn balance_effect(tx: &Transaction, wallet_id: &str) -> i128 {
+    if tx.status == "cancelled" || is_internal(tx, wallet_id) {
+        0
+    } else if tx.to_wallet_id == wallet_id {
+        tx.amount as i128
+    } else {
+        -(tx.amount as i128)
+    }
+}
+
+fn to_balance(value: i128) -> Result<u64, String> {
+    u64::try_from(value).map_err(|_| "Ledger balance went negative".to_string())
+}
+
+/// Payments received show who paid, by contact nickname when we know them
+fn describe(tx: &Transaction, wallet_id: &str, contacts: &ContactBook) -> String {
+    if tx.to_wallet_id == wallet_id {
+        contacts.find(&tx.from_wallet_id)
+            .filter(|c| !c.nickname.is_empty())
+            .map(|c| c.nickname.clone())
+            .unwrap_or_else(|| tx.from_wallet_id.clone())
+    } else {
+        tx.merchant_name.clone()
+    }
+}
+
+/// Build the statement for `month` by replaying the whole ledger (oldest first)
+/// and check the result against the wallet's total balance
+pub fn monthly_statement(
+    wallet_id: &str,
+    account_name: Option<String>,
+    month: &str,
+    wallet_balance: u64,
+    ledger: &[&Transaction],
+    contacts: &ContactBook,
+) -> Result<MonthlyStatement, String> {
+    let (start, end) = month_bounds(month)?;
+    let (start_ms, end_ms) = (start.timestamp_millis(), end.timestamp_millis());
+
+    let mut opening = 0i128;
+    let mut balance = 0i128;
+    let mut ledger_balance = 0i128;
+    let mut entries = Vec::new();
+    let (mut credits, mut debits) = (0u64, 0u64);
+
+    for tx in ledger {
+        let effect = balance_effect(tx, wallet_id);
+        ledger_balance += effect;
+        if effect == 0 {
+            continue;
+        }
+
+        // Undated entries cannot be placed in a month but still count in the ledger balance
+        let Ok(time) = DateTime::parse_from_rfc3339(&tx.timestamp) else { continue };
+        let time_ms = time.timestamp_millis();
+        if time_ms < start_ms {
+            opening += effect;
+            balance += effect;
+            continue;
+        }
+        if time_ms >= end_ms {
+            continue;
+        }
+
+        balance += effect;
+        let (credit, debit) = if effect > 0 { (tx.amount, 0) } else { (0, tx.amount) };
+        credits = credits.saturating_add(credit);
+        debits = debits.saturating_add(debit);
+
+        entries.push(StatementEntry {
+            timestamp: tx.timestamp.clone(),
+            tx_id: tx.id.clone(),
+            description: describe(tx, wallet_id, contacts),
+            tx_type: tx.tx_type.clone(),
+            status: tx.status.clone(),
+            channel: channel_of(tx),
+            credit,
+            debit,
+            balance: to_balance(balance)?,
+        });
+    }
+
+    let opening_balance = to_balance(opening)?;
+    let closing_balance = to_balance(balance)?;
+    let ledger_balance = to_balance(ledger_balance)?;
+
+    Ok(MonthlyStatement {
+        wallet_id: wallet_id.to_string(),
+        account_name,
+        month: month.to_string(),
+        period_start: start.to_rfc3339(),
+        period_end: end.to_rfc3339(),
+        opening_balance,
+        total_credits: credits,
+        total_debits: debits,
+        closing_balance,
+        ledger_balance,
+        wallet_balance,
+        reconciled: ledger_balance == wallet_balance,
+        entries,
+        generated_at: Utc::now().to_rfc3339(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::BankingEngine;
+    use crate::annotations::TxAnnotations;
+
+    const ME: &str = "wallet-me";
+
+    fn tx(id: &str, from: &str, to: &str, amount: u64, timestamp: &str) -> Transaction {
+        Transaction {
+            id: id.to_string(),
+            from_wallet_id: from.to_string(),
+            to_wallet_id: to.to_string(),
+            merchant_name: "Wallet Me".to_string(),
+            amount,
+            timestamp: timestamp.to_string(),
+            signature: String::new(),
+            tx_type: "offline".to_string(),
+            status: "confirmed".to_string(),
+            request_id: None,
+            sequence: 0,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
+        }
+    }
+
+    fn ledger() -> Vec<Transaction> {
+        vec![
+            tx("deposit", "fluxa-funding", ME, 1000, "2026-09-01T08:00:00+00:00"),
+            tx("sept", ME, "shop", 200, "2026-09-20T08:00:00+00:00"),
+            tx("oct-in", "alice", ME, 500, "2026-10-02T08:00:00+00:00"),
+            tx("oct-out", ME, "shop", 300, "2026-10-03T08:00:00+00:00"),
+            tx("vault", ME, ME, 400, "2026-10-04T08:00:00+00:00"),
+            tx("nov", ME, "shop", 100, "2026-11-01T00:00:00+00:00"),
+        ]
+    }
+
+    #[test]
+    fn opening_balance_replays_the_ledger_before_the_month() {
+        let ledger = ledger();
+        let refs: Vec<&Transaction> = ledger.iter().collect();
+
+        let statement = monthly_statement(ME, None, "2026-10", 900, &refs, &ContactBook::default()).unwrap();
+        assert_eq!(statement.opening_balance, 800);
+        assert_eq!((statement.total_credits, statement.total_debits), (500, 300));
+        assert_eq!(statement.closing_balance, 1000);
+        let ids: Vec<&str> = statement.entries.iter().map(|e| e.tx_id.as_str()).collect();
+        assert_eq!(ids, ["oct-in", "oct-out"]);
+        assert_eq!(statement.ledger_balance, 900);
+        assert!(statement.reconciled);
+    }
+
+    #[test]
+    fn reports_a_ledger_that_does_not_match_the_wallet() {
+        let ledger = ledger();
+        let refs: Vec<&Transaction> = ledger.iter().collect();
+
+        // Le solde affiché ne provient pas du registre : l'écart doit apparaître
+        let statement = monthly_statement(ME, None, "2026-10", 5000, &refs, &ContactBook::default()).unwrap();
+        assert_eq!(statement.opening_balance, 800);
+        assert_eq!(statement.ledger_balance, 900);
+        assert_eq!(statement.wallet_balance, 5000);
+        assert!(!statement.reconciled);
+    }
+
+    #[test]
+    fn incoming_entries_name_the_payer() {
+        let ledger = ledger();
+        let refs: Vec<&Transaction> = ledger.iter().collect();
+
+        let statement = monthly_statement(ME, None, "2026-10", 900, &refs, &ContactBook::default()).unwrap();
+        assert_eq!(statement.entries[0].description, "alice");
+        assert_eq!(statement.entries[1].description, "Wallet Me");
+
+        let mut contacts = ContactBook::default();
+        contacts.add("Alice", "alice", "key-alice").unwrap();
+        let statement = monthly_statement(ME, None, "2026-10", 900, &refs, &contacts).unwrap();
+        assert_eq!(statement.entries[0].description, "Alice");
+
+        // Un portefeuille vu lors d'un échange mais jamais nommé garde son identifiant
+        let mut contacts = ContactBook::default();
+        contacts.observe("alice", "key-alice");
+        let statement = monthly_statement(ME, None, "2026-10", 900, &refs, &contacts).unwrap();
+        assert_eq!(statement.entries[0].description, "alice");
+    }
+
+    #[test]
+    fn rejects_invalid_months() {
+        assert!(month_bounds("2026-13").is_err());
+        let (start, end) = month_bounds("2026-12").unwrap();
+        assert_eq!(start.to_rfc3339(), "2026-12-01T00:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2027-01-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn csv_fields_cannot_start_a_formula() {
+        for value in ["=1+1", "+1", "-1", "@SUM(A1)", "\tcmd", "\rcmd"] {
+            assert!(csv_field(value).trim_start_matches('"').starts_with('\''), "{:?}", value);
+        }
+        assert_eq!(csv_field("Shop, \"Ali\""), "\"Shop, \"\"Ali\"\"\"");
+        assert_eq!(csv_field("Shop"), "Shop");
+    }
+
+    #[test]
+    fn csv_rows_follow_the_header() {
+        let ledger = ledger();
+        let rows: Vec<ExportRow> = ledger.iter().map(|tx| ExportRow::from_transaction(tx, ME)).collect();
+        let csv = to_csv(&rows);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines.len(), ledger.len() + 1);
+        assert!(lines[3].contains(",in,alice,"));
+        assert!(lines[5].contains(",internal,"));
+        assert!(lines[1].ends_with(",offline,1000"));
+    }
+
+    #[test]
+    fn new_wallet_statement_reconciles_from_the_opening_deposit() {
+        let (mut payer, mut payee) = (wallet(), wallet());
+        let month = Utc::now().format("%Y-%m").to_string();
+        pay(&mut payer, &mut payee, 1000);
+
+        let statement = payee.monthly_statement(&month).unwrap();
+        assert_eq!(statement.opening_balance, 0);
+        assert_eq!(statement.entries[0].tx_type, "deposit");
+        assert_eq!(statement.entries[0].credit, 40000);
+        assert_eq!(statement.entries[1].description, payer.get_wallet().id);
+        assert_eq!(statement.closing_balance, 41000);
+        assert!(statement.reconciled);
+
+        let statement = payer.monthly_statement(&month).unwrap();
+        assert_eq!(statement.closing_balance, payer.get_wallet().total_balance);
+        assert!(statement.reconciled);
+    }
+
+    #[test]
+    fn statement_flags_a_balance_missing_from_the_ledger() {
+        let mut engine = BankingEngine::new();
+        engine.initialize_keys().unwrap();
+        engine.wallet.online_balance += 500;
+        engine.wallet.total_balance += 500;
+
+        let month = Utc::now().format("%Y-%m").to_string();
+        let statement = engine.monthly_statement(&month).unwrap();
+        assert_eq!(statement.ledger_balance, 40000);
+        assert_eq!(statement.wallet_balance, 40500);
+        assert!(!statement.reconciled);
+    }
+}
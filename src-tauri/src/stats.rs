@@ -0,0 +1,317 @@
+// Statistiques et analyse des dépenses
+//
+// Les montants sont répartis entre entrées et sorties du point de vue de ce
+// portefeuille. Les virements entre le compte en ligne et le coffre ne sont ni des
+// entrées ni des sorties et sont ignorés, de même que les transactions annulées.
+// Un paiement émis en attente de reçu a déjà quitté le solde : il compte dans les
+// sorties et apparaît en plus dans l'exposition en attente.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use chrono::{DateTime, Datelike, Utc};
+
+use crate::Transaction;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PeriodTotal {
+    /// "2026-10-18", "2026-W42" or "2026-10" (UTC)
+    pub period: String,
+    pub incoming: u64,
+    pub outgoing: u64,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChannelStats {
+    /// "nfc", "ble" or "qr" when recorded, otherwise "online" or "offline"
+    pub channel: String,
+    pub count: usize,
+    pub incoming: u64,
+    pub outgoing: u64,
+}
+
+/// Payments sent in one user category, largest first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CategoryStats {
+    /// `UNCATEGORIZED` for payments the user has not categorized
+    pub category: String,
+    pub count: usize,
+    pub amount: u64,
+}
+
+pub const UNCATEGORIZED: &str = "uncategorized";
+
+/// Spending at one payee, largest first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MerchantStats {
+    pub wallet_id: String,
+    /// Name on the most recent payment
+    pub merchant_name: String,
+    pub count: usize,
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WalletStats {
+    pub wallet_id: String,
+    pub created_at: String,
+    pub total_transactions: usize,
+    pub confirmed_transactions: usize,
+    pub incoming: u64,
+    pub incoming_count: usize,
+    pub outgoing: u64,
+    pub outgoing_count: usize,
+    /// Average payment sent / received, refunds excluded
+    pub average_sent: u64,
+    pub average_received: u64,
+    /// Payments sent that no receipt or server has confirmed yet
+    pub pending_count: usize,
+    pub pending_amount: u64,
+    pub daily: Vec<PeriodTotal>,
+    pub weekly: Vec<PeriodTotal>,
+    pub monthly: Vec<PeriodTotal>,
+    pub by_channel: Vec<ChannelStats>,
+    pub by_merchant: Vec<MerchantStats>,
+    pub by_category: Vec<CategoryStats>,
+}
+
+/// Vault transfers move money between our own balances
+pub fn is_internal(tx: &Transaction, wallet_id: &str) -> bool {
+    tx.from_wallet_id == wallet_id && tx.to_wallet_id == wallet_id
+}
+
+/// How the payment travelled, falling back to its type when no channel was recorded
+pub fn channel_of(tx: &Transaction) -> String {
+    match &tx.channel {
+        Some(channel) => channel.clone(),
+        None if tx.tx_type == "online" => "online".to_string(),
+        None => "offline".to_string(),
+    }
+}
+
+fn add_to_period(periods: &mut BTreeMap<String, PeriodTotal>, key: String, incoming: bool, amount: u64) {
+    let total = periods.entry(key.clone()).or_insert_with(|| PeriodTotal {
+        period: key,
+        ..Default::default()
+    });
+    total.count += 1;
+    if incoming {
+        total.incoming = total.incoming.saturating_add(amount);
+    } else {
+        total.outgoing = total.outgoing.saturating_add(amount);
+    }
+}
+
+fn average(total: u64, count: usize) -> u64 {
+    if count == 0 { 0 } else { total / count as u64 }
+}
+
+pub fn compute<'a>(
+    transactions: impl Iterator<Item = &'a Transaction>,
+    wallet_id: &str,
+    created_at: &str,
+) -> WalletStats {
+    let mut stats = WalletStats {
+        wallet_id: wallet_id.to_string(),
+        created_at: created_at.to_string(),
+        ..Default::default()
+    };
+
+    let mut daily = BTreeMap::new();
+    let mut weekly = BTreeMap::new();
+    let mut monthly = BTreeMap::new();
+    let mut channels: BTreeMap<String, ChannelStats> = BTreeMap::new();
+    let mut merchants: HashMap<String, (MerchantStats, String)> = HashMap::new();
+    let mut categories: HashMap<String, CategoryStats> = HashMap::new();
+    let (mut sent, mut sent_count, mut received, mut received_count) = (0u64, 0usize, 0u64, 0usize);
+
+    for tx in transactions {
+        stats.total_transactions += 1;
+        if tx.status == "confirmed" {
+            stats.confirmed_transactions += 1;
+        }
+        if tx.status == "cancelled" || is_internal(tx, wallet_id) {
+            continue;
+        }
+
+        let incoming = tx.to_wallet_id == wallet_id;
+        let is_payment = tx.tx_type == "online" || tx.tx_type == "offline";
+
+        if incoming {
+            stats.incoming = stats.incoming.saturating_add(tx.amount);
+            stats.incoming_count += 1;
+            if is_payment {
+                received = received.saturating_add(tx.amount);
+                received_count += 1;
+            }
+        } else {
+            stats.outgoing = stats.outgoing.saturating_add(tx.amount);
+            stats.outgoing_count += 1;
+            if tx.status == "pending" {
+                stats.pending_count += 1;
+                stats.pending_amount = stats.pending_amount.saturating_add(tx.amount);
+            }
+            if is_payment {
+                sent = sent.saturating_add(tx.amount);
+                sent_count += 1;
+
+                let (merchant, last_seen) = merchants.entry(tx.to_wallet_id.clone()).or_insert_with(|| (
+                    MerchantStats { wallet_id: tx.to_wallet_id.clone(), ..Default::default() },
+                    String::new(),
+                ));
+                merchant.count += 1;
+                merchant.amount = merchant.amount.saturating_add(tx.amount);
+                if tx.timestamp >= *last_seen {
+                    merchant.merchant_name = tx.merchant_name.clone();
+                    *last_seen = tx.timestamp.clone();
+                }
+
+                let category = tx.annotations.category.clone().unwrap_or_else(|| UNCATEGORIZED.to_string());
+                let spent = categories.entry(category).or_insert_with_key(|key| CategoryStats {
+                    category: key.clone(),
+                    ..Default::default()
+                });
+                spent.count += 1;
+                spent.amount = spent.amount.saturating_add(tx.amount);
+            }
+        }
+
+        let channel = channels.entry(channel_of(tx)).or_insert_with_key(|key| ChannelStats {
+            channel: key.clone(),
+            ..Default::default()
+        });
+        channel.count += 1;
+        if incoming {
+            channel.incoming = channel.incoming.saturating_add(tx.amount);
+        } else {
+            channel.outgoing = channel.outgoing.saturating_add(tx.amount);
+        }
+
+        if let Ok(time) = DateTime::parse_from_rfc3339(&tx.timestamp) {
+            let time = time.with_timezone(&Utc);
+            let week = time.iso_week();
+            add_to_period(&mut daily, time.format("%Y-%m-%d").to_string(), incoming, tx.amount);
+            add_to_period(&mut weekly, format!("{}-W{:02}", week.year(), week.week()), incoming, tx.amount);
+            add_to_period(&mut monthly, time.format("%Y-%m").to_string(), incoming, tx.amount);
+        }
+    }
+
+    stats.average_sent = average(sent, sent_count);
+    stats.average_received = average(received, received_count);
+    stats.daily = daily.into_values().collect();
+    stats.weekly = weekly.into_values().collect();
+    stats.monthly = monthly.into_values().collect();
+    stats.by_channel = channels.into_values().collect();
+
+    let mut by_merchant: Vec<MerchantStats> = merchants.into_values().map(|(m, _)| m).collect();
+    by_merchant.sort_by(|a, b| b.amount.cmp(&a.amount).then_with(|| a.wallet_id.cmp(&b.wallet_id)));
+    stats.by_merchant = by_merchant;
+
+    let mut by_category: Vec<CategoryStats> = categories.into_values().collect();
+    by_category.sort_by(|a, b| b.amount.cmp(&a.amount).then_with(|| a.category.cmp(&b.category)));
+    stats.by_category = by_category;
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::TxAnnotations;
+
+    const ME: &str = "wallet-me";
+
+    fn tx(from: &str, to: &str, amount: u64, timestamp: &str) -> Transaction {
+        Transaction {
+            id: format!("{}-{}-{}", from, to, amount),
+            from_wallet_id: from.to_string(),
+            to_wallet_id: to.to_string(),
+            merchant_name: "Shop".to_string(),
+            amount,
+            timestamp: timestamp.to_string(),
+            signature: String::new(),
+            tx_type: "offline".to_string(),
+            status: "confirmed".to_string(),
+            request_id: None,
+            sequence: 0,
+            channel: None,
+            merchant_status: None,
+            annotations: TxAnnotations::default(),
+        }
+    }
+
+    #[test]
+    fn splits_incoming_and_outgoing_and_skips_transfers() {
+        let mut pending = tx(ME, "shop", 300, "2026-10-05T09:00:00+00:00");
+        pending.status = "pending".to_string();
+        let mut cancelled = tx(ME, "shop", 999, "2026-10-05T09:00:00+00:00");
+        cancelled.status = "cancelled".to_string();
+        let txs = [
+            tx("alice", ME, 1000, "2026-10-05T08:00:00+00:00"),
+            tx(ME, "shop", 200, "2026-10-12T08:00:00+00:00"),
+            pending,
+            cancelled,
+            tx(ME, ME, 5000, "2026-10-05T10:00:00+00:00"),
+        ];
+
+        let stats = compute(txs.iter(), ME, "2026-01-01T00:00:00+00:00");
+        assert_eq!(stats.total_transactions, 5);
+        assert_eq!((stats.incoming, stats.incoming_count), (1000, 1));
+        assert_eq!((stats.outgoing, stats.outgoing_count), (500, 2));
+        assert_eq!((stats.pending_count, stats.pending_amount), (1, 300));
+        assert_eq!(stats.average_sent, 250);
+        assert_eq!(stats.average_received, 1000);
+
+        let weeks: Vec<&str> = stats.weekly.iter().map(|w| w.period.as_str()).collect();
+        assert_eq!(weeks, ["2026-W41", "2026-W42"]);
+        assert_eq!(stats.monthly.len(), 1);
+        assert_eq!((stats.monthly[0].incoming, stats.monthly[0].outgoing), (1000, 500));
+    }
+
+    #[test]
+    fn groups_by_recorded_channel_then_type() {
+        let mut nfc = tx(ME, "shop", 100, "2026-10-05T08:00:00+00:00");
+        nfc.channel = Some("nfc".to_string());
+        let mut online = tx(ME, "shop", 200, "2026-10-05T08:00:00+00:00");
+        online.tx_type = "online".to_string();
+        let txs = [nfc, online, tx("alice", ME, 50, "2026-10-05T08:00:00+00:00")];
+
+        let stats = compute(txs.iter(), ME, "");
+        let channels: Vec<(&str, usize, u64, u64)> = stats.by_channel.iter()
+            .map(|c| (c.channel.as_str(), c.count, c.incoming, c.outgoing))
+            .collect();
+        assert_eq!(channels, [("nfc", 1, 0, 100), ("offline", 1, 50, 0), ("online", 1, 0, 200)]);
+    }
+
+    #[test]
+    fn ranks_merchants_and_categories_by_amount() {
+        let mut food = tx(ME, "market", 400, "2026-10-05T08:00:00+00:00");
+        food.annotations.category = Some("food".to_string());
+        let mut renamed = tx(ME, "taxi", 150, "2026-10-06T08:00:00+00:00");
+        renamed.merchant_name = "Taxi Moto".to_string();
+        let txs = [food, tx(ME, "taxi", 100, "2026-10-05T08:00:00+00:00"), renamed];
+
+        let stats = compute(txs.iter(), ME, "");
+        assert_eq!(stats.by_merchant[0].wallet_id, "market");
+        assert_eq!((stats.by_merchant[1].count, stats.by_merchant[1].amount), (2, 250));
+        assert_eq!(stats.by_merchant[1].merchant_name, "Taxi Moto");
+
+        let categories: Vec<(&str, u64)> = stats.by_category.iter()
+            .map(|c| (c.category.as_str(), c.amount))
+            .collect();
+        assert_eq!(categories, [("food", 400), (UNCATEGORIZED, 250)]);
+    }
+
+    #[test]
+    fn sums_saturate_instead_of_overflowing() {
+        let txs = [
+            tx("alice", ME, u64::MAX, "2026-10-05T08:00:00+00:00"),
+            tx("bob", ME, 10, "2026-10-05T08:00:00+00:00"),
+        ];
+
+        let stats = compute(txs.iter(), ME, "");
+        assert_eq!(stats.incoming, u64::MAX);
+        assert_eq!(stats.daily[0].incoming, u64::MAX);
+        assert_eq!(stats.by_channel[0].incoming, u64::MAX);
+    }
+}
@@ -0,0 +1,184 @@
+// Persistance locale : fichiers JSON dans le dossier de données de l'application,
+// journaux JSON en ajout seul (une valeur par ligne) et fichiers binaires
+// (sauvegardes chiffrées)
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Sub-folder of the data directory holding user exports and backups
+pub const EXPORTS_DIR: &str = "exports";
+
+/// Read a JSON file, returning `None` when it does not exist yet
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, String> {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| format!("Corrupt data file {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Cannot read {}: {}", path.display(), e)),
+    }
+}
+
+/// Write a JSON file atomically so a crash never leaves a half-written file behind
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let bytes = serde_json::to_vec(value).map_err(|e| e.to_string())?;
+    save_bytes(path, &bytes)
+}
+
+/// Write raw bytes atomically
+pub fn save_bytes(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+    }
+
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes).map_err(|e| format!("Cannot write {}: {}", tmp.display(), e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+pub fn load_bytes(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))
+}
+
+/// Append one value as a JSON line, flushed to disk before returning
+pub fn append_json_line<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let mut line = serde_json::to_vec(value).map_err(|e| e.to_string())?;
+    line.push(b'\n');
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    file.write_all(&line)
+        .and_then(|_| file.sync_data())
+        .map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+/// Replace a JSON lines file atomically
+pub fn save_json_lines<T: Serialize>(path: &Path, values: &[T]) -> Result<(), String> {
+    let mut bytes = Vec::new();
+    for value in values {
+        serde_json::to_writer(&mut bytes, value).map_err(|e| e.to_string())?;
+        bytes.push(b'\n');
+    }
+    save_bytes(path, &bytes)
+}
+
+/// Read a JSON lines file, empty when it does not exist yet. A torn last line,
+/// left by a crash during an append, is dropped; damage anywhere else is an error.
+pub fn load_json_lines<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, String> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+    };
+
+    let lines: Vec<&[u8]> = bytes.split(|b| *b == b'\n').filter(|l| !l.is_empty()).collect();
+    let mut values = Vec::with_capacity(lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        match serde_json::from_slice(line) {
+            Ok(value) => values.push(value),
+            Err(_) if i + 1 == lines.len() && !bytes.ends_with(b"\n") => break,
+            Err(e) => return Err(format!("Corrupt data file {}: line {}: {}", path.display(), i + 1, e)),
+        }
+    }
+    Ok(values)
+}
+
+/// Path of a user-named export file, kept inside the exports folder of `data_dir`
+pub fn export_path(data_dir: &Path, file_name: &str) -> Result<PathBuf, String> {
+    let name = file_name.trim();
+    if name.is_empty()
+        || name == "."
+        || name.contains("..")
+        || name.contains(['/', '\\', ':'])
+        || name.chars().any(char::is_control)
+    {
+        return Err(format!("Invalid file name: {}", file_name));
+    }
+    Ok(data_dir.join(EXPORTS_DIR).join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    #[test]
+    fn export_path_stays_in_exports_folder() {
+        let dir = Path::new("/data/fluxa");
+        assert_eq!(
+            export_path(dir, "wallet-2026.bak").unwrap(),
+            dir.join(EXPORTS_DIR).join("wallet-2026.bak")
+        );
+    }
+
+    #[test]
+    fn export_path_rejects_traversal_and_separators() {
+        let dir = Path::new("/data/fluxa");
+        for name in ["", " ", ".", "..", "../wallet.json", "a/b.csv", "a\\b.csv", "/etc/passwd", "C:x", "a\nb"] {
+            assert!(export_path(dir, name).is_err(), "{:?} accepted", name);
+        }
+    }
+
+    #[test]
+    fn json_lines_append_and_reload() {
+        let dir = std::env::temp_dir().join(format!("fluxa-storage-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("log.jsonl");
+        assert!(load_json_lines::<u32>(&path).unwrap().is_empty());
+
+        append_json_line(&path, &1u32).unwrap();
+        append_json_line(&path, &2u32).unwrap();
+        assert_eq!(load_json_lines::<u32>(&path).unwrap(), vec![1, 2]);
+
+        save_json_lines(&path, &[7u32]).unwrap();
+        assert_eq!(load_json_lines::<u32>(&path).unwrap(), vec![7]);
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn torn_last_line_is_dropped_but_inner_damage_is_not() {
+        let dir = std::env::temp_dir().join(format!("fluxa-storage-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("log.jsonl");
+        fs::create_dir_all(&dir).unwrap();
+
+        fs::write(&path, b"1\n2\n[3").unwrap();
+        assert_eq!(load_json_lines::<u32>(&path).unwrap(), vec![1, 2]);
+
+        fs::write(&path, b"1\n[x\n3\n").unwrap();
+        assert!(load_json_lines::<u32>(&path).is_err());
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn json_round_trip_and_missing_file() {
+        let dir = std::env::temp_dir().join(format!("fluxa-storage-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("state.json");
+        assert_eq!(load_json::<Vec<u32>>(&path).unwrap(), None);
+
+        save_json(&path, &vec![1u32, 2, 3]).unwrap();
+        assert_eq!(load_json::<Vec<u32>>(&path).unwrap(), Some(vec![1, 2, 3]));
+        assert!(!path.with_extension("tmp").exists());
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn exports_resolve_inside_data_dir() {
+        let mut engine = wallet();
+        assert!(engine.export_path("backup.bak").is_err());
+
+        let dir = temp_dir();
+        engine.set_data_dir(dir.clone()).unwrap();
+        assert!(engine.export_path("backup.bak").unwrap().starts_with(&dir));
+        assert!(engine.export_path("../wallet.json").is_err());
+        assert!(engine.export_path("/tmp/backup.bak").is_err());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
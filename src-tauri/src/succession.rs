@@ -0,0 +1,296 @@
+// Rotation des clés : chaque nouvelle clé est annoncée par un acte de succession
+// signé par l'ancienne clé (qui autorise le changement) et par la nouvelle (qui prouve
+// sa possession). La chaîne des actes part de la première clé du portefeuille ; un pair
+// ou le serveur qui connaît une clé ancienne peut ainsi suivre la chaîne jusqu'à la
+// clé actuelle, et les anciennes transactions restent vérifiables avec la clé de
+// l'époque.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{codec, crypto};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeySuccession {
+    pub wallet_id: String,
+    /// Derivation index of the new key; the first key is generation 0
+    pub generation: u32,
+    pub old_public_key: String,
+    pub new_public_key: String,
+    pub rotated_at: String,
+    /// Highest transaction sequence signed with the old key; it signs nothing after that
+    pub last_sequence: u64,
+    pub old_key_signature: String,
+    pub new_key_signature: String,
+}
+
+impl KeySuccession {
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        codec::Encoder::new("key-succession")
+            .str(&self.wallet_id)
+            .u64(self.generation as u64)
+            .str(&self.old_public_key)
+            .str(&self.new_public_key)
+            .str(&self.rotated_at)
+            .u64(self.last_sequence)
+            .finish()
+    }
+
+    /// Record from its signing bytes; the signatures are filled in by the caller
+    pub fn from_signing_bytes(data: &[u8]) -> Result<Self, String> {
+        let mut decoder = codec::Decoder::new(data, "key-succession")?;
+        let record = KeySuccession {
+            wallet_id: decoder.str()?,
+            generation: u32::try_from(decoder.u64()?)
+                .map_err(|_| "Invalid key generation".to_string())?,
+            old_public_key: decoder.str()?,
+            new_public_key: decoder.str()?,
+            rotated_at: decoder.str()?,
+            last_sequence: decoder.u64()?,
+            old_key_signature: String::new(),
+            new_key_signature: String::new(),
+        };
+        decoder.finish()?;
+        Ok(record)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn sign(
+        wallet_id: &str,
+        generation: u32,
+        old_private_key: &str,
+        old_public_key: &str,
+        new_private_key: &str,
+        new_public_key: &str,
+        rotated_at: &str,
+        last_sequence: u64,
+    ) -> Result<Self, String> {
+        let mut record = KeySuccession {
+            wallet_id: wallet_id.to_string(),
+            generation,
+            old_public_key: old_public_key.to_string(),
+            new_public_key: new_public_key.to_string(),
+            rotated_at: rotated_at.to_string(),
+            last_sequence,
+            old_key_signature: String::new(),
+            new_key_signature: String::new(),
+        };
+
+        let bytes = record.signing_bytes();
+        record.old_key_signature = crypto::sign(old_private_key, &bytes)?;
+        record.new_key_signature = crypto::sign(new_private_key, &bytes)?;
+        Ok(record)
+    }
+
+    pub fn verify(&self) -> Result<bool, String> {
+        let bytes = self.signing_bytes();
+        Ok(crypto::verify(&self.old_public_key, &bytes, &self.old_key_signature)?
+            && crypto::verify(&self.new_public_key, &bytes, &self.new_key_signature)?)
+    }
+}
+
+/// Follow a chain of records from a known key; returns the keys it moves through,
+/// the current key last. Records that predate `known_key` are skipped, so a peer
+/// that saw an intermediate key can start from there.
+pub fn follow_chain(
+    wallet_id: &str,
+    known_key: &str,
+    records: &[KeySuccession],
+) -> Result<Vec<String>, String> {
+    let start = records.iter()
+        .position(|r| r.old_public_key == known_key)
+        .ok_or("Succession chain does not include the known key")?;
+
+    let mut current = known_key.to_string();
+    let mut generation = records[start].generation;
+    let mut keys = Vec::new();
+
+    for (i, record) in records[start..].iter().enumerate() {
+        if record.wallet_id != wallet_id {
+            return Err("Succession record for another wallet".to_string());
+        }
+        if record.old_public_key != current {
+            return Err("Broken succession chain".to_string());
+        }
+        if i > 0 && record.generation <= generation {
+            return Err("Succession generations out of order".to_string());
+        }
+        if !record.verify()? {
+            return Err("Invalid succession signature".to_string());
+        }
+        generation = record.generation;
+        current = record.new_public_key.clone();
+        keys.push(current.clone());
+    }
+
+    Ok(keys)
+}
+
+/// Every key a wallet has used, oldest first
+pub fn key_history(genesis_key: &str, records: &[KeySuccession]) -> Vec<String> {
+    std::iter::once(genesis_key.to_string())
+        .chain(records.iter().map(|r| r.new_public_key.clone()))
+        .collect()
+}
+
+/// What a wallet publishes so others can follow its keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyChain {
+    pub wallet_id: String,
+    pub genesis_public_key: String,
+    pub current_public_key: String,
+    pub successions: Vec<KeySuccession>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::p2p::SignedPayment;
+
+    /// `n` successive keys and the records linking them
+    fn chain(n: usize) -> (Vec<(String, String)>, Vec<KeySuccession>) {
+        let keys: Vec<(String, String)> = (0..n).map(|_| crypto::generate_keypair()).collect();
+        let records = keys.windows(2)
+            .enumerate()
+            .map(|(i, pair)| {
+                KeySuccession::sign(
+                    "wallet-a",
+                    i as u32 + 1,
+                    &pair[0].0,
+                    &pair[0].1,
+                    &pair[1].0,
+                    &pair[1].1,
+                    "2026-10-01T10:00:00+00:00",
+                    i as u64 * 10,
+                )
+                .unwrap()
+            })
+            .collect();
+        (keys, records)
+    }
+
+    #[test]
+    fn record_is_signed_by_both_keys() {
+        let (_, records) = chain(2);
+        assert!(records[0].verify().unwrap());
+
+        let mut tampered = records[0].clone();
+        tampered.last_sequence += 1;
+        assert!(!tampered.verify().unwrap());
+    }
+
+    #[test]
+    fn signing_bytes_round_trip() {
+        let (_, records) = chain(2);
+        let mut decoded = KeySuccession::from_signing_bytes(&records[0].signing_bytes()).unwrap();
+        decoded.old_key_signature = records[0].old_key_signature.clone();
+        decoded.new_key_signature = records[0].new_key_signature.clone();
+        assert!(decoded.verify().unwrap());
+        assert_eq!(decoded.generation, 1);
+    }
+
+    #[test]
+    fn chain_is_followed_from_any_known_key() {
+        let (keys, records) = chain(4);
+        let from_genesis = follow_chain("wallet-a", &keys[0].1, &records).unwrap();
+        assert_eq!(from_genesis, vec![keys[1].1.clone(), keys[2].1.clone(), keys[3].1.clone()]);
+
+        let from_middle = follow_chain("wallet-a", &keys[2].1, &records).unwrap();
+        assert_eq!(from_middle, vec![keys[3].1.clone()]);
+    }
+
+    #[test]
+    fn broken_or_foreign_chain_is_refused() {
+        let (keys, records) = chain(4);
+        assert!(follow_chain("wallet-b", &keys[0].1, &records).is_err());
+        assert!(follow_chain("wallet-a", "unknown", &records).is_err());
+
+        let gap = vec![records[0].clone(), records[2].clone()];
+        assert!(follow_chain("wallet-a", &keys[0].1, &gap).is_err());
+    }
+
+    #[test]
+    fn history_lists_every_key() {
+        let (keys, records) = chain(3);
+        let history = key_history(&keys[0].1, &records);
+        assert_eq!(history, keys.iter().map(|k| k.1.clone()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn rotation_requires_pin() {
+        let mut engine = wallet();
+        assert!(engine.rotate_keys("1234").is_err());
+
+        engine.set_pin("1234", None).unwrap();
+        assert!(engine.rotate_keys("0000").is_err());
+        let record = engine.rotate_keys("1234").unwrap();
+        assert!(record.verify().unwrap());
+        assert_eq!(engine.get_public_key().unwrap(), record.new_public_key);
+    }
+
+    #[test]
+    fn retired_key_only_covers_earlier_sequences() {
+        let (mut payer, mut payee) = (wallet_with_pin(), wallet());
+        let old_keys = payer.keypair.clone().unwrap();
+        let before = pay(&mut payer, &mut payee, 100);
+
+        let record = payer.rotate_keys("1234").unwrap();
+        assert_eq!(record.last_sequence, before.sequence);
+        assert!(payer.verify_signature(&before).unwrap());
+
+        // Une transaction postérieure signée avec l'ancienne clé n'est plus reconnue
+        let mut forged = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+        forged.signature = crypto::sign(&old_keys.private_key, &forged.signing_bytes()).unwrap();
+        assert!(!payer.verify_signature(&forged).unwrap());
+    }
+
+    #[test]
+    fn signed_payment_carries_successions_to_its_key() {
+        let (mut payer, mut payee) = (wallet_with_pin(), wallet());
+        let genesis = payer.get_public_key().unwrap();
+        let before = pay(&mut payer, &mut payee, 100);
+        payer.rotate_keys("1234").unwrap();
+        let after = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+
+        let old = payer.signed_payment(&before.id).unwrap();
+        assert_eq!(old.sender_public_key, genesis);
+        assert!(old.successions.is_empty());
+
+        let new = payer.signed_payment(&after.id).unwrap();
+        assert_eq!(new.successions.len(), 1);
+        assert_eq!(new.successions[0].new_public_key, new.sender_public_key);
+    }
+
+    #[test]
+    fn payee_follows_rotation_carried_by_payment() {
+        let (mut payer, mut payee) = (wallet_with_pin(), wallet());
+        pay(&mut payer, &mut payee, 100);
+        payer.rotate_keys("1234").unwrap();
+        payer.rotate_keys("1234").unwrap();
+
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+        let wire = payer.signed_payment(&tx.id).unwrap().to_wire().unwrap();
+        let payment = SignedPayment::from_wire(&wire).unwrap();
+        payee.receive_signed_payment(payment, Some("nfc".to_string())).unwrap();
+
+        let contact = payee.contacts.find(&payer.get_wallet().id).unwrap();
+        assert_eq!(contact.public_key, payer.get_public_key().unwrap());
+        assert_eq!(contact.previous_keys.len(), 2);
+        assert!(payee.get_contact_warnings().is_empty());
+    }
+
+    #[test]
+    fn forged_succession_in_payment_is_refused() {
+        let (mut payer, mut payee) = (wallet_with_pin(), wallet());
+        pay(&mut payer, &mut payee, 100);
+        payer.rotate_keys("1234").unwrap();
+
+        let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), 100, None).unwrap();
+        let mut payment = payer.signed_payment(&tx.id).unwrap();
+        payment.successions[0].last_sequence += 1;
+
+        let balance = payee.get_wallet().offline_balance;
+        assert!(payee.receive_signed_payment(payment, None).is_err());
+        assert_eq!(payee.get_wallet().offline_balance, balance);
+    }
+}
@@ -0,0 +1,73 @@
+// Outils partagés par les tests du moteur : portefeuilles, émetteur de confiance, paiements
+
+use chrono::Utc;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::p2p::handshake::HandshakeMessage;
+use crate::p2p::session::SessionRole;
+use crate::recovery::AccountSnapshot;
+use crate::{crypto, BankingEngine, Transaction};
+
+/// Engine with freshly generated keys and the opening deposit
+pub fn wallet() -> BankingEngine {
+    let mut engine = BankingEngine::new();
+    engine.initialize_keys().unwrap();
+    engine
+}
+
+/// Initialized engine protected by PIN "1234"
+pub fn wallet_with_pin() -> BankingEngine {
+    let mut engine = wallet();
+    engine.set_pin("1234", None).unwrap();
+    engine
+}
+
+pub fn temp_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("fluxa-test-{}", Uuid::new_v4()))
+}
+
+/// Issuer key pair trusted by `engine`
+pub fn trust_issuer(engine: &mut BankingEngine) -> (String, String) {
+    let (private_key, public_key) = crypto::generate_keypair();
+    engine.add_trusted_issuer(&public_key);
+    (private_key, public_key)
+}
+
+pub fn snapshot_for(engine: &BankingEngine, online_balance: u64, offline_balance: u64) -> AccountSnapshot {
+    AccountSnapshot {
+        wallet_id: engine.get_wallet().id,
+        online_balance,
+        offline_balance,
+        transactions: Vec::new(),
+        last_sequence: 0,
+        key_successions: Vec::new(),
+        issued_at: Utc::now().to_rfc3339(),
+        issuer_public_key: String::new(),
+        signature: String::new(),
+    }
+}
+
+/// Offline payment from `payer` to `payee`, received and acknowledged
+pub fn pay(payer: &mut BankingEngine, payee: &mut BankingEngine, amount: u64) -> Transaction {
+    let tx = payer.create_offline_transaction(payee.get_wallet().id, "Shop".to_string(), amount, None).unwrap();
+    let receipt = payee.receive_payment(tx.clone(), payer.get_public_key().unwrap(), None).unwrap();
+    payer.import_receipt(receipt).unwrap()
+}
+
+/// Run Offer -> Accept -> Payment between `sender` and `receiver`, returning the Ack
+pub fn handshake_until_ack(sender: &mut BankingEngine, receiver: &mut BankingEngine, amount: u64) -> HandshakeMessage {
+    let offer = sender.p2p_start_payment(receiver.get_wallet().id, "Shop".to_string(), amount).unwrap();
+    let accept = receiver.p2p_handle_message(offer, None).unwrap().unwrap();
+    let payment = sender.p2p_handle_message(accept, None).unwrap().unwrap();
+    receiver.p2p_handle_message(payment, None).unwrap().unwrap()
+}
+
+/// Run the key exchange between two engines; returns (sender channel, receiver channel)
+pub fn secure_pair(sender: &mut BankingEngine, receiver: &mut BankingEngine) -> (String, String) {
+    let ours = sender.p2p_secure_begin(SessionRole::Initiator).unwrap();
+    let theirs = receiver.p2p_secure_begin(SessionRole::Responder).unwrap();
+    sender.p2p_secure_complete(ours.channel_id.clone(), theirs.hello).unwrap();
+    receiver.p2p_secure_complete(theirs.channel_id.clone(), ours.hello).unwrap();
+    (ours.channel_id, theirs.channel_id)
+}
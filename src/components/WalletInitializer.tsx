@@ -1,5 +1,6 @@
 import { useEffect, useState } from "react";
 import { useRustWallet } from "../hooks/useRustWallet";
+import { reportDeviceStatus } from "../utils/tauri";
 
 interface WalletInitializerProps {
   children: React.ReactNode;
@@ -9,6 +10,10 @@ export const WalletInitializer: React.FC<WalletInitializerProps> = ({ children }
   const { wallet, loading, error } = useRustWallet();
   const [isReady, setIsReady] = useState(false);
 
+  useEffect(() => {
+    reportDeviceStatus();
+  }, []);
+
   useEffect(() => {
     if (!loading && wallet) {
       setIsReady(true);
@@ -1,15 +1,15 @@
 import { useEffect, useState } from "react";
-import { useRustWallet } from "../hooks/useRustWallet";
+import { useRustWallet, WalletStatsData } from "../hooks/useRustWallet";
 
 export const WalletStats: React.FC = () => {
   const { getStats } = useRustWallet();
-  const [stats, setStats] = useState<any>(null);
+  const [stats, setStats] = useState<WalletStatsData | null>(null);
   const [loading, setLoading] = useState(true);
 
   useEffect(() => {
     const fetchStats = async () => {
       const data = await getStats();
-      setStats(data);
+      setStats(data ?? null);
       setLoading(false);
     };
     fetchStats();
@@ -31,9 +31,19 @@ export const WalletStats: React.FC = () => {
         <p className="text-xs text-gray-500 mt-1">{stats.confirmed_transactions} confirmées</p>
       </div>
       <div className="glass-card p-4">
-        <p className="text-xs text-gray-400 uppercase tracking-widest mb-2">Volume Total</p>
-        <p className="text-2xl font-grotesk text-neon-green">{stats.total_volume.toLocaleString()}</p>
-        <p className="text-xs text-gray-500 mt-1">FCFA</p>
+        <p className="text-xs text-gray-400 uppercase tracking-widest mb-2">Entrées</p>
+        <p className="text-2xl font-grotesk text-neon-green">{stats.incoming.toLocaleString()}</p>
+        <p className="text-xs text-gray-500 mt-1">FCFA · {stats.incoming_count} reçus</p>
+      </div>
+      <div className="glass-card p-4">
+        <p className="text-xs text-gray-400 uppercase tracking-widest mb-2">Sorties</p>
+        <p className="text-2xl font-grotesk text-gold-royal">{stats.outgoing.toLocaleString()}</p>
+        <p className="text-xs text-gray-500 mt-1">Panier moyen {stats.average_sent.toLocaleString()} FCFA</p>
+      </div>
+      <div className="glass-card p-4">
+        <p className="text-xs text-gray-400 uppercase tracking-widest mb-2">En attente</p>
+        <p className="text-2xl font-grotesk text-gold-royal">{stats.pending_amount.toLocaleString()}</p>
+        <p className="text-xs text-gray-500 mt-1">{stats.pending_count} paiements sans reçu</p>
       </div>
     </div>
   );
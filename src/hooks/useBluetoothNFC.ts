@@ -1,12 +1,34 @@
 import { useState, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { checkPermissions, startScan, type BleDevice } from "@mnlphlp/plugin-blec";
 
 export interface BluetoothDevice {
   id: string;
   name: string;
   rssi: number;
+  merchant_id?: string | null;
+  smoothed_rssi?: number;
+  distance_m?: number;
+  proximity?: "immediate" | "near" | "far";
+  last_seen_ms?: number;
 }
 
+/** Durée d'une fenêtre de scan BLE */
+const SCAN_TIMEOUT_MS = 8000;
+
+/** MTU ATT négociée par défaut pour découper les messages chiffrés */
+const BLE_MTU = 185;
+
+/** Annonce BLE au format attendu par bluetooth_ingest_advertisements */
+const toAdvertisement = (device: BleDevice) => ({
+  address: device.address,
+  local_name: device.name || null,
+  rssi: device.rssi,
+  service_uuids: device.services,
+  service_data: device.serviceData,
+  seen_at_ms: Date.now(),
+});
+
 export interface P2PTransaction {
   id: string;
   sender_wallet_id: string;
@@ -25,6 +47,7 @@ export const useBluetoothNFC = () => {
   const [receivedTransaction, setReceivedTransaction] = useState<P2PTransaction | null>(null);
   const [nfcAvailable, setNfcAvailable] = useState(false);
   const [nfcScanning, setNfcScanning] = useState(false);
+  const [secureChannelId, setSecureChannelId] = useState<string | null>(null);
 
   const checkNfcAvailability = useCallback(async () => {
     try {
@@ -45,7 +68,7 @@ export const useBluetoothNFC = () => {
 
       try {
         const prepareResult = await invoke<any>("nfc_send_transaction", {
-          receiver_id: receiverId,
+          receiverId,
           amount,
         });
 
@@ -55,14 +78,8 @@ export const useBluetoothNFC = () => {
 
         setNfcScanning(true);
 
-        const payload = {
-          receiverId,
-          amount,
-          timestamp: new Date().toISOString(),
-          type: "fluxa_payment",
-        };
-
-        console.log("NFC Payload prepared:", payload);
+        // Paiement signé, encodage canonique en hexadécimal à écrire sur le tag
+        const payload: string = prepareResult.data.payload;
 
         setNfcScanning(false);
         return {
@@ -83,12 +100,16 @@ export const useBluetoothNFC = () => {
     []
   );
 
-  const receiveTransactionNFC = useCallback(async () => {
+  const receiveTransactionNFC = useCallback(async (payload?: string) => {
     setError(null);
     setNfcScanning(true);
 
     try {
-      const result = await invoke<any>("nfc_receive_transaction");
+      if (!payload) {
+        throw new Error("Aucune donnée NFC lue");
+      }
+
+      const result = await invoke<any>("nfc_receive_transaction", { payload });
 
       if (result.success && result.data) {
         setReceivedTransaction(result.data);
@@ -119,6 +140,20 @@ export const useBluetoothNFC = () => {
     setDevices([]);
 
     try {
+      if (!(await checkPermissions())) {
+        throw new Error("Permission Bluetooth refusée");
+      }
+
+      // Chaque lot d'annonces passe par le suivi Rust, qui ne garde que les appareils Fluxa
+      await startScan(async (found: BleDevice[]) => {
+        const ingest = await invoke<any>("bluetooth_ingest_advertisements", {
+          advertisements: found.map(toAdvertisement),
+        });
+        if (ingest.success && ingest.data) {
+          setDevices(ingest.data);
+        }
+      }, SCAN_TIMEOUT_MS);
+
       const result = await invoke<any>("bluetooth_scan_devices");
 
       if (result.success && result.data) {
@@ -164,16 +199,39 @@ export const useBluetoothNFC = () => {
   }, []);
 
   const disconnectDevice = useCallback(async () => {
+    if (secureChannelId) {
+      await invoke("p2p_secure_close", { channelId: secureChannelId });
+      setSecureChannelId(null);
+    }
     setConnectedDevice(null);
     setError(null);
     return {
       success: true,
       message: "Déconnecté",
     };
+  }, [secureChannelId]);
+
+  /** Démarre l'échange de clés ; le hello retourné est envoyé en clair au pair */
+  const beginSecureChannel = useCallback(async () => {
+    const result = await invoke<any>("p2p_secure_begin", { role: "initiator" });
+    if (!result.success) {
+      throw new Error(result.error || "Failed to start secure channel");
+    }
+    return result.data;
+  }, []);
+
+  /** Termine l'échange avec le hello du pair ; retourne son identifiant de portefeuille */
+  const completeSecureChannel = useCallback(async (channelId: string, peerHello: unknown) => {
+    const result = await invoke<any>("p2p_secure_complete", { channelId, peerHello });
+    if (!result.success) {
+      throw new Error(result.error || "Failed to complete secure channel");
+    }
+    setSecureChannelId(channelId);
+    return result.data as string;
   }, []);
 
   const sendTransactionBluetooth = useCallback(
-    async (receiverId: string, amount: number) => {
+    async (_receiverId: string, amount: number) => {
       if (!connectedDevice) {
         setError("Aucun appareil connecté");
         return {
@@ -182,19 +240,30 @@ export const useBluetoothNFC = () => {
         };
       }
 
+      if (!secureChannelId) {
+        setError("Canal sécurisé non établi");
+        return {
+          success: false,
+          message: "Le canal chiffré avec cet appareil n'est pas encore établi",
+        };
+      }
+
       setError(null);
 
       try {
+        // L'offre est chiffrée pour le pair du canal puis découpée en trames BLE
         const result = await invoke<any>("bluetooth_send_transaction", {
           deviceId: connectedDevice.id,
-          receiverId: receiverId,
+          channelId: secureChannelId,
           amount,
+          mtu: BLE_MTU,
         });
 
         if (result.success) {
           return {
             success: true,
             message: `Transaction ${amount} FCFA envoyée à ${connectedDevice.name}`,
+            frames: result.data as string[],
           };
         }
 
@@ -208,7 +277,7 @@ export const useBluetoothNFC = () => {
         };
       }
     },
-    [connectedDevice]
+    [connectedDevice, secureChannelId]
   );
 
   const acceptTransaction = useCallback(async () => {
@@ -258,6 +327,8 @@ export const useBluetoothNFC = () => {
     startBluetoothScan,
     connectBluetoothDevice,
     disconnectDevice,
+    beginSecureChannel,
+    completeSecureChannel,
     sendTransactionBluetooth,
     acceptTransaction,
     rejectTransaction,
@@ -1,5 +1,6 @@
 import { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
 
 export interface WalletData {
   id: string;
@@ -22,12 +23,82 @@ export interface TransactionData {
   status: string;
 }
 
+export interface PeriodTotal {
+  period: string;
+  incoming: number;
+  outgoing: number;
+  count: number;
+}
+
+export interface ChannelStats {
+  channel: string;
+  count: number;
+  incoming: number;
+  outgoing: number;
+}
+
+export interface MerchantStats {
+  wallet_id: string;
+  merchant_name: string;
+  count: number;
+  amount: number;
+}
+
+export interface CategoryStats {
+  category: string;
+  count: number;
+  amount: number;
+}
+
+export interface WalletStatsData {
+  wallet_id: string;
+  created_at: string;
+  total_transactions: number;
+  confirmed_transactions: number;
+  incoming: number;
+  incoming_count: number;
+  outgoing: number;
+  outgoing_count: number;
+  average_sent: number;
+  average_received: number;
+  pending_count: number;
+  pending_amount: number;
+  daily: PeriodTotal[];
+  weekly: PeriodTotal[];
+  monthly: PeriodTotal[];
+  by_channel: ChannelStats[];
+  by_merchant: MerchantStats[];
+  by_category: CategoryStats[];
+}
+
 export interface KeyPairData {
   public_key: string;
   private_key: string;
   created_at: string;
 }
 
+export interface TransactionStatusChange {
+  tx_id: string;
+  old_status: string;
+  new_status: string;
+  transaction: TransactionData;
+}
+
+export interface PaymentReceivedEvent {
+  transaction: TransactionData;
+  receipt: unknown;
+}
+
+export interface SyncProgressEvent {
+  operation: string;
+  processed: number;
+  total: number;
+  done: boolean;
+}
+
+const upsertTransaction = (list: TransactionData[], tx: TransactionData) =>
+  list.some((t) => t.id === tx.id) ? list.map((t) => (t.id === tx.id ? tx : t)) : [...list, tx];
+
 export interface ApiResponse<T> {
   success: boolean;
   data?: T;
@@ -46,6 +117,32 @@ export const useRustWallet = () => {
     initWallet();
   }, []);
 
+  // The engine reports every balance and transaction change, including payments
+  // received over BLE while another screen is shown
+  useEffect(() => {
+    const subscriptions: Promise<UnlistenFn>[] = [
+      listen<WalletData>("balance-changed", (e) => setWallet(e.payload)),
+      listen<TransactionData>("transaction-created", (e) =>
+        setTransactions((prev) => upsertTransaction(prev, e.payload))
+      ),
+      listen<TransactionStatusChange>("transaction-status-changed", (e) =>
+        setTransactions((prev) => upsertTransaction(prev, e.payload.transaction))
+      ),
+      listen<PaymentReceivedEvent>("payment-received", (e) =>
+        setTransactions((prev) => upsertTransaction(prev, e.payload.transaction))
+      ),
+      listen<SyncProgressEvent>("sync-progress", (e) => {
+        if (e.payload.done) {
+          refreshWallet();
+        }
+      }),
+    ];
+
+    return () => {
+      subscriptions.forEach((s) => s.then((unlisten) => unlisten()));
+    };
+  }, []);
+
   const initWallet = useCallback(async () => {
     try {
       setLoading(true);
@@ -125,7 +222,7 @@ export const useRustWallet = () => {
   }, []);
 
   const createOfflineTransaction = useCallback(
-    async (toWalletId: string, merchantName: string, amount: number) => {
+    async (toWalletId: string, merchantName: string, amount: number, category?: string) => {
       try {
         setLoading(true);
         setError(null);
@@ -134,6 +231,7 @@ export const useRustWallet = () => {
           toWalletId: toWalletId,
           merchantName: merchantName,
           amount,
+          category,
         });
 
         if (!res.success) {
@@ -141,9 +239,7 @@ export const useRustWallet = () => {
         }
 
         if (res.data) {
-          setTransactions((prev) => [...prev, res.data!]);
-          // Refresh wallet
-          await refreshWallet();
+          setTransactions((prev) => upsertTransaction(prev, res.data!));
         }
 
         setLoading(false);
@@ -159,7 +255,7 @@ export const useRustWallet = () => {
   );
 
   const createOnlineTransaction = useCallback(
-    async (toWalletId: string, merchantName: string, amount: number) => {
+    async (toWalletId: string, merchantName: string, amount: number, category?: string) => {
       try {
         setLoading(true);
         setError(null);
@@ -168,6 +264,7 @@ export const useRustWallet = () => {
           to_wallet_id: toWalletId,
           merchant_name: merchantName,
           amount,
+          category,
         });
 
         if (!res.success) {
@@ -175,9 +272,7 @@ export const useRustWallet = () => {
         }
 
         if (res.data) {
-          setTransactions((prev) => [...prev, res.data!]);
-          // Refresh wallet
-          await refreshWallet();
+          setTransactions((prev) => upsertTransaction(prev, res.data!));
         }
 
         setLoading(false);
@@ -265,7 +360,7 @@ export const useRustWallet = () => {
 
   const getStats = useCallback(async () => {
     try {
-      const res = await invoke<ApiResponse<any>>("get_wallet_stats");
+      const res = await invoke<ApiResponse<WalletStatsData>>("get_wallet_stats");
       return res.data;
     } catch (err) {
       console.error("Stats error:", err);
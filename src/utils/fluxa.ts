@@ -78,24 +78,28 @@ export const rustWalletAPI = {
   async createOfflineTransaction(
     toWalletId: string,
     merchantName: string,
-    amount: number
+    amount: number,
+    category?: string
   ) {
     return invokeCommand<any>("create_offline_transaction", {
       to_wallet_id: toWalletId,
       merchant_name: merchantName,
       amount,
+      category,
     });
   },
 
   async createOnlineTransaction(
     toWalletId: string,
     merchantName: string,
-    amount: number
+    amount: number,
+    category?: string
   ) {
     return invokeCommand<any>("create_online_transaction", {
       to_wallet_id: toWalletId,
       merchant_name: merchantName,
       amount,
+      category,
     });
   },
 
@@ -111,8 +115,8 @@ export const rustWalletAPI = {
     return invokeCommand<any[]>("get_transactions");
   },
 
-  async verifySignature(data: string, signature: string) {
-    return invokeCommand<boolean>("verify_tx_signature", { data, signature });
+  async verifySignature(tx: any) {
+    return invokeCommand<boolean>("verify_tx_signature", { tx });
   },
 };
 
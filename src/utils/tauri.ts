@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { checkPermissions, getAdapterState } from "@mnlphlp/plugin-blec";
 
 export interface TransactionPayload {
   amount: number;
@@ -98,3 +99,25 @@ export async function getOnlineBalance(): Promise<number> {
     throw error;
   }
 }
+
+/**
+ * Reports the Bluetooth adapter state and permission to the Rust backend.
+ * NFC keeps the state the backend already assumes for this platform.
+ */
+export async function reportDeviceStatus(): Promise<void> {
+  try {
+    const current = await invoke<any>("device_capabilities");
+    const [adapter, granted] = await Promise.all([getAdapterState(), checkPermissions(false)]);
+    await invoke("report_device_status", {
+      status: {
+        nfc_present: current.data?.nfc_present ?? false,
+        nfc_enabled: current.data?.nfc_enabled ?? false,
+        ble_present: adapter !== "Unknown",
+        ble_enabled: adapter === "On",
+        ble_permission: granted ? "granted" : "prompt",
+      },
+    });
+  } catch (error) {
+    console.error("Error reporting device status:", error);
+  }
+}